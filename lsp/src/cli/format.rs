@@ -0,0 +1,77 @@
+use std::{fs, path::PathBuf};
+
+use clap::{ArgMatches, Command, arg, value_parser};
+
+use super::create_parser;
+use crate::provider::formatter::{FormatterOptions, format_document};
+
+pub fn format_command() -> Command {
+  Command::new("format")
+    .about("Formats rules files in place")
+    .args(&[
+      arg!(check: --check "only report files that are not formatted, without writing them"),
+      arg!(indent: --indent <WIDTH> "number of spaces per indentation level")
+        .value_parser(value_parser!(usize))
+        .default_value("2"),
+      arg!(max_line_length: --"max-line-length" <LENGTH> "line length after which long expressions are broken up")
+        .value_parser(value_parser!(usize))
+        .default_value("100"),
+      arg!(files: <FILES> ... "rules files to format").value_parser(value_parser!(PathBuf)),
+    ])
+}
+
+/// Formats the given files and returns whether all of them succeeded. With `--check`
+/// unformatted files count as a failure instead of being rewritten.
+pub fn run_format(matches: &ArgMatches) -> bool {
+  let check = matches.get_flag("check");
+
+  let options = FormatterOptions::new(
+    *matches.get_one::<usize>("indent").unwrap(),
+    true,
+    *matches.get_one::<usize>("max_line_length").unwrap(),
+  );
+
+  let mut parser = create_parser();
+  let mut success = true;
+
+  for file in matches.get_many::<PathBuf>("files").unwrap() {
+    let source = match fs::read_to_string(file) {
+      Ok(source) => source,
+      Err(err) => {
+        eprintln!("{}: {}", file.display(), err);
+        success = false;
+        continue;
+      }
+    };
+
+    let formatted = parser
+      .parse(&source, None)
+      .and_then(|tree| format_document(&tree, &source, &options));
+
+    let Some(formatted) = formatted else {
+      eprintln!(
+        "{}: could not be formatted due to syntax errors",
+        file.display()
+      );
+      success = false;
+      continue;
+    };
+
+    if formatted == source {
+      continue;
+    }
+
+    if check {
+      println!("{}: not formatted", file.display());
+      success = false;
+      continue;
+    }
+
+    if let Err(err) = fs::write(file, formatted) {
+      eprintln!("{}: {}", file.display(), err);
+      success = false;
+    }
+  }
+
+  success
+}
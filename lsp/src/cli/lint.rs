@@ -0,0 +1,266 @@
+use std::{fs, path::PathBuf, rc::Rc};
+
+use clap::{ArgMatches, Command, arg, value_parser};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use serde_json::{Value, json};
+
+use super::create_parser;
+use crate::{
+  parser::{evaluation::evaluate_tree, schema::DocumentSchemas},
+  provider::diagnoser::{SYNTAX_ERROR, build_diagnostics},
+};
+
+pub fn lint_command() -> Command {
+  Command::new("lint")
+    .about("Reports the diagnostics of rules files without an editor")
+    .args(&[
+      arg!(format: --format <FORMAT> "output format of the diagnostics")
+        .value_parser(["text", "json", "sarif", "github"])
+        .default_value("text"),
+      arg!(files: <FILES> ... "rules files to lint").value_parser(value_parser!(PathBuf)),
+    ])
+}
+
+/// Lints the given files and returns whether none of them has errors. Warnings are
+/// reported but do not count as a failure.
+pub fn run_lint(matches: &ArgMatches) -> bool {
+  let format = matches.get_one::<String>("format").unwrap();
+
+  let mut parser = create_parser();
+  let mut success = true;
+  let mut results: Vec<(PathBuf, Vec<Diagnostic>)> = vec![];
+
+  for file in matches.get_many::<PathBuf>("files").unwrap() {
+    let source = match fs::read_to_string(file) {
+      Ok(source) => source,
+      Err(err) => {
+        eprintln!("{}: {}", file.display(), err);
+        success = false;
+        continue;
+      }
+    };
+
+    let Some(tree) = parser.parse(&source, None) else {
+      eprintln!("{}: could not be parsed", file.display());
+      success = false;
+      continue;
+    };
+
+    let document_schemas = match DocumentSchemas::load_for(file) {
+      Ok(document_schemas) => document_schemas.map(Rc::new),
+      Err(err) => {
+        eprintln!("{}", err);
+        success = false;
+        continue;
+      }
+    };
+
+    let mut rules_tree = evaluate_tree(tree.clone(), source.as_bytes());
+    rules_tree.set_document_schemas(document_schemas);
+
+    let mut diagnostics = build_diagnostics(&tree, &rules_tree);
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end));
+
+    if diagnostics
+      .iter()
+      .any(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+    {
+      success = false;
+    }
+
+    results.push((file.clone(), diagnostics));
+  }
+
+  match format.as_str() {
+    "json" => println!("{}", to_json(&results)),
+    "sarif" => println!("{}", to_sarif(&results)),
+    "github" => print_github_annotations(&results),
+    _ => print_text(&results),
+  }
+
+  success
+}
+
+fn code(diagnostic: &Diagnostic) -> &str {
+  match &diagnostic.code {
+    Some(NumberOrString::String(code)) => code,
+    _ => SYNTAX_ERROR,
+  }
+}
+
+fn severity_name(diagnostic: &Diagnostic) -> &'static str {
+  match diagnostic.severity {
+    Some(DiagnosticSeverity::ERROR) => "error",
+    Some(DiagnosticSeverity::WARNING) => "warning",
+    Some(DiagnosticSeverity::INFORMATION) => "info",
+    _ => "hint",
+  }
+}
+
+/// 1-based (line, column, end line, end column) of the diagnostic
+fn location(diagnostic: &Diagnostic) -> (u32, u32, u32, u32) {
+  let range = diagnostic.range;
+
+  (
+    range.start.line + 1,
+    range.start.character + 1,
+    range.end.line + 1,
+    range.end.character + 1,
+  )
+}
+
+fn print_text(results: &[(PathBuf, Vec<Diagnostic>)]) {
+  let mut errors = 0;
+  let mut warnings = 0;
+
+  for (file, diagnostics) in results {
+    for diagnostic in diagnostics {
+      let (line, column, _, _) = location(diagnostic);
+
+      match diagnostic.severity {
+        Some(DiagnosticSeverity::ERROR) => errors += 1,
+        _ => warnings += 1,
+      }
+
+      println!(
+        "{}:{}:{}: {}[{}]: {}",
+        file.display(),
+        line,
+        column,
+        severity_name(diagnostic),
+        code(diagnostic),
+        diagnostic.message
+      );
+    }
+  }
+
+  if errors + warnings > 0 {
+    println!("\n{} error(s), {} warning(s)", errors, warnings);
+  }
+}
+
+fn to_json(results: &[(PathBuf, Vec<Diagnostic>)]) -> Value {
+  let entries = results
+    .iter()
+    .flat_map(|(file, diagnostics)| {
+      diagnostics.iter().map(move |diagnostic| {
+        let (line, column, end_line, end_column) = location(diagnostic);
+
+        json!({
+          "file": file.display().to_string(),
+          "line": line,
+          "column": column,
+          "endLine": end_line,
+          "endColumn": end_column,
+          "severity": severity_name(diagnostic),
+          "code": code(diagnostic),
+          "message": diagnostic.message,
+        })
+      })
+    })
+    .collect::<Vec<Value>>();
+
+  Value::Array(entries)
+}
+
+/// A SARIF 2.1.0 log with one run, as consumed by code scanning tools
+fn to_sarif(results: &[(PathBuf, Vec<Diagnostic>)]) -> Value {
+  let mut rule_ids: Vec<&str> = results
+    .iter()
+    .flat_map(|(_, diagnostics)| diagnostics.iter().map(code))
+    .collect();
+
+  rule_ids.sort();
+  rule_ids.dedup();
+
+  let rules = rule_ids
+    .iter()
+    .map(|id| json!({ "id": id }))
+    .collect::<Vec<Value>>();
+
+  let sarif_results = results
+    .iter()
+    .flat_map(|(file, diagnostics)| {
+      diagnostics.iter().map(move |diagnostic| {
+        let (line, column, end_line, end_column) = location(diagnostic);
+
+        let level = match diagnostic.severity {
+          Some(DiagnosticSeverity::ERROR) => "error",
+          Some(DiagnosticSeverity::WARNING) => "warning",
+          _ => "note",
+        };
+
+        json!({
+          "ruleId": code(diagnostic),
+          "level": level,
+          "message": { "text": diagnostic.message },
+          "locations": [{
+            "physicalLocation": {
+              "artifactLocation": { "uri": file.display().to_string().replace('\\', "/") },
+              "region": {
+                "startLine": line,
+                "startColumn": column,
+                "endLine": end_line,
+                "endColumn": end_column,
+              },
+            },
+          }],
+        })
+      })
+    })
+    .collect::<Vec<Value>>();
+
+  json!({
+    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": env!("CARGO_PKG_NAME"),
+          "version": env!("CARGO_PKG_VERSION"),
+          "rules": rules,
+        },
+      },
+      "results": sarif_results,
+    }],
+  })
+}
+
+/// Workflow commands that GitHub Actions turns into annotations on the changed files
+fn print_github_annotations(results: &[(PathBuf, Vec<Diagnostic>)]) {
+  for (file, diagnostics) in results {
+    for diagnostic in diagnostics {
+      let (line, column, end_line, end_column) = location(diagnostic);
+
+      let command = match diagnostic.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        _ => "notice",
+      };
+
+      println!(
+        "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+        command,
+        escape_property(&file.display().to_string()),
+        line,
+        column,
+        end_line,
+        end_column,
+        escape_property(code(diagnostic)),
+        escape_data(&diagnostic.message)
+      );
+    }
+  }
+}
+
+fn escape_data(value: &str) -> String {
+  value
+    .replace('%', "%25")
+    .replace('\r', "%0D")
+    .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+  escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
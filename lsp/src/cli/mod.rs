@@ -0,0 +1,18 @@
+pub mod format;
+pub mod lint;
+pub mod test;
+
+use tree_sitter::Parser;
+
+/// Parser for the command line tools, set up the same way as for the language server
+pub fn create_parser() -> Parser {
+  let language = tree_sitter_firestore_rules::LANGUAGE;
+
+  let mut parser = Parser::new();
+
+  parser
+    .set_language(&language.into())
+    .expect("Error loading FirestoreRules parser");
+
+  parser
+}
@@ -0,0 +1,300 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+  path::{Path, PathBuf},
+};
+
+use clap::{ArgMatches, Command, arg, value_parser};
+use serde::Deserialize;
+use tree_sitter::Point;
+
+use super::create_parser;
+use crate::{
+  parser::{
+    base::{MethodType, RulesTree, ServiceType, Spanned},
+    evaluation::evaluate_tree,
+  },
+  simulator::{
+    RuleOutcome, SimulationResult,
+    interpreter::method_name,
+    request::{MockDatabase, MockRequest},
+    simulate,
+    value::{NANOS_PER_SECOND, Value},
+  },
+};
+
+const DEFAULT_DATABASE: &str = "(default)";
+
+/// A file describing the documents in the database and the requests to test against the rules
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TestFile {
+  /// The rules file, relative to the test file
+  rules: Option<PathBuf>,
+  database: Option<String>,
+  #[serde(default)]
+  documents: HashMap<String, serde_json::Value>,
+  tests: Vec<TestCase>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TestCase {
+  name: String,
+  auth: Option<TestAuth>,
+  method: TestMethod,
+  path: String,
+  /// The document data after the write, i.e. `request.resource.data`
+  data: Option<serde_json::Value>,
+  /// Documents existing only for this test case
+  #[serde(default)]
+  documents: HashMap<String, serde_json::Value>,
+  /// The request time in milliseconds since the unix epoch, defaults to now
+  time: Option<i64>,
+  expect: Expectation,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestAuth {
+  uid: String,
+  #[serde(default)]
+  token: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TestMethod {
+  Get,
+  List,
+  Create,
+  Update,
+  Delete,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Expectation {
+  Allow,
+  Deny,
+}
+
+impl From<&TestMethod> for MethodType {
+  fn from(method: &TestMethod) -> Self {
+    match method {
+      TestMethod::Get => MethodType::Get,
+      TestMethod::List => MethodType::List,
+      TestMethod::Create => MethodType::Create,
+      TestMethod::Update => MethodType::Update,
+      TestMethod::Delete => MethodType::Delete,
+    }
+  }
+}
+
+pub fn test_command() -> Command {
+  Command::new("test")
+    .about("Runs request test cases against rules files without the emulator")
+    .args(&[
+      arg!(rules: --rules <FILE> "rules file to test, overrides the rules file of the test files")
+        .value_parser(value_parser!(PathBuf)),
+      arg!(files: <TEST_FILES> ... "JSON or YAML test files").value_parser(value_parser!(PathBuf)),
+    ])
+}
+
+/// Runs all test files and returns whether every test case passed
+pub fn run_test(matches: &ArgMatches) -> bool {
+  let rules_override = matches.get_one::<PathBuf>("rules");
+
+  let mut passed = 0;
+  let mut failed = 0;
+
+  for file in matches.get_many::<PathBuf>("files").unwrap() {
+    match run_test_file(file, rules_override) {
+      Ok((file_passed, file_failed)) => {
+        passed += file_passed;
+        failed += file_failed;
+      }
+      Err(err) => {
+        eprintln!("{}: {}", file.display(), err);
+        failed += 1;
+      }
+    }
+  }
+
+  println!("\n{} passed, {} failed", passed, failed);
+
+  failed == 0
+}
+
+/// Reads a test file as YAML if it has a `.yaml` or `.yml` extension, as JSON otherwise
+fn read_test_file(file: &Path) -> Result<TestFile, String> {
+  let content = fs::read_to_string(file).map_err(|err| err.to_string())?;
+
+  let is_yaml = file
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| ["yaml", "yml"].contains(&extension.to_lowercase().as_str()));
+
+  if is_yaml {
+    serde_yaml_ng::from_str(&content).map_err(|err| err.to_string())
+  } else {
+    serde_json::from_str(&content).map_err(|err| err.to_string())
+  }
+}
+
+fn run_test_file(file: &Path, rules_override: Option<&PathBuf>) -> Result<(usize, usize), String> {
+  let test_file = read_test_file(file)?;
+
+  let rules_path = match (rules_override, &test_file.rules) {
+    (Some(rules), _) => rules.clone(),
+    (None, Some(rules)) => file.parent().unwrap_or(Path::new("")).join(rules),
+    (None, None) => return Err("No rules file given, use `rules` or --rules".to_owned()),
+  };
+
+  let source =
+    fs::read_to_string(&rules_path).map_err(|err| format!("{}: {}", rules_path.display(), err))?;
+
+  let tree = create_parser()
+    .parse(&source, None)
+    .ok_or(format!("{}: could not be parsed", rules_path.display()))?;
+
+  if tree.root_node().has_error() {
+    eprintln!(
+      "{}: contains syntax errors, results may be inaccurate",
+      rules_path.display()
+    );
+  }
+
+  let rules_tree = evaluate_tree(tree, source.as_bytes());
+
+  let database = test_file.database.as_deref().unwrap_or(DEFAULT_DATABASE);
+  let expand = |path: &str| full_path(&rules_tree, database, path);
+
+  println!("{}", file.display());
+
+  let mut passed = 0;
+  let mut failed = 0;
+
+  for case in &test_file.tests {
+    let mut mock_database = MockDatabase::new();
+
+    for (path, data) in test_file.documents.iter().chain(case.documents.iter()) {
+      mock_database.insert(&expand(path), Value::from(data));
+    }
+
+    let mut request = MockRequest::new(MethodType::from(&case.method), &expand(&case.path));
+
+    if let Some(auth) = &case.auth {
+      let token = auth
+        .token
+        .iter()
+        .map(|(key, value)| (key.clone(), Value::from(value)))
+        .collect();
+
+      request = request.with_auth(&auth.uid, token);
+    }
+
+    if let Some(data) = &case.data {
+      request = request.with_resource_data(Value::from(data));
+    }
+
+    if let Some(time) = case.time {
+      request = request.with_time(time as i128 * (NANOS_PER_SECOND / 1000));
+    }
+
+    let result = simulate(&rules_tree, &request, &mock_database);
+    let allowed = result.allowed();
+
+    let status = match (allowed, &case.expect) {
+      (true, Expectation::Allow) | (false, Expectation::Deny) => {
+        passed += 1;
+        "PASS"
+      }
+      _ => {
+        failed += 1;
+        "FAIL"
+      }
+    };
+
+    println!(
+      "  {} {} ({} {}): {}",
+      status,
+      case.name,
+      method_name(result.method()),
+      case.path,
+      describe_result(&result, &rules_path)
+    );
+
+    if status == "FAIL" {
+      println!(
+        "    expected {:?}, {} rule(s) applied",
+        case.expect,
+        result.evaluations().len()
+      );
+
+      for evaluation in result.evaluations() {
+        println!(
+          "    {}: {}",
+          location(evaluation.rule().span().0, &rules_path),
+          describe_outcome(evaluation.outcome(), &rules_path)
+        );
+      }
+    }
+  }
+
+  Ok((passed, failed))
+}
+
+/// Expands paths relative to the database documents, e.g. `/users/alice`,
+/// to the full document path the rules are matched against
+fn full_path(tree: &RulesTree, database: &str, path: &str) -> String {
+  let is_firestore = !matches!(tree.service_type(), Some(ServiceType::Storage));
+
+  if is_firestore && !path.starts_with("/databases/") {
+    return format!(
+      "/databases/{}/documents/{}",
+      database,
+      path.trim_start_matches('/')
+    );
+  }
+
+  path.to_owned()
+}
+
+fn describe_result(result: &SimulationResult<'_>, rules_path: &Path) -> String {
+  let Some(deciding) = result.deciding_rule() else {
+    return "denied, no rule matched the request".to_owned();
+  };
+
+  let location = location(deciding.rule().span().0, rules_path);
+
+  match deciding.outcome() {
+    RuleOutcome::Allow => format!("allowed by rule at {}", location),
+    _ => format!(
+      "denied by rule at {}, {}",
+      location,
+      describe_outcome(deciding.outcome(), rules_path)
+    ),
+  }
+}
+
+fn describe_outcome(outcome: &RuleOutcome, rules_path: &Path) -> String {
+  match outcome {
+    RuleOutcome::Allow => "condition is true".to_owned(),
+    RuleOutcome::Deny => "condition is false".to_owned(),
+    RuleOutcome::Error(err) => format!(
+      "{} at {}",
+      err.message(),
+      location(err.span().0, rules_path)
+    ),
+  }
+}
+
+fn location(point: Point, rules_path: &Path) -> String {
+  format!(
+    "{}:{}:{}",
+    rules_path.display(),
+    point.row + 1,
+    point.column + 1
+  )
+}
@@ -1,20 +1,33 @@
 #![deny(elided_lifetimes_in_paths)]
 
+mod cli;
 mod parser;
 mod provider;
 mod server;
+mod simulator;
 
 use std::error::Error;
 
 use clap::{Command, arg};
+use cli::{
+  format::{format_command, run_format},
+  lint::{lint_command, run_lint},
+  test::{run_test, test_command},
+};
 use server::server::start_server;
 use tree_sitter_firestore_rules;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-  let args = Command::new("firestore-rules-lsp").args(&[
-    arg!(socket: --socket <NUMBER> "port if starting the lsp as a server").conflicts_with("stdio"),
-    arg!(stdio: --stdio "flag to start over stdio").conflicts_with("socket"),
-  ]);
+  let args = Command::new("firestore-rules-lsp")
+    .args(&[
+      arg!(socket: --socket <NUMBER> "port if starting the lsp as a server")
+        .conflicts_with("stdio"),
+      arg!(stdio: --stdio "flag to start over stdio").conflicts_with("socket"),
+    ])
+    .args_conflicts_with_subcommands(true)
+    .subcommand(format_command())
+    .subcommand(lint_command())
+    .subcommand(test_command());
 
   let arg_result = args.try_get_matches();
 
@@ -26,6 +39,30 @@ pub fn main() -> Result<(), Box<dyn Error>> {
 
   let matches = arg_result.unwrap();
 
+  if let Some(("format", format_matches)) = matches.subcommand() {
+    if !run_format(format_matches) {
+      std::process::exit(1);
+    }
+
+    return Ok(());
+  }
+
+  if let Some(("lint", lint_matches)) = matches.subcommand() {
+    if !run_lint(lint_matches) {
+      std::process::exit(1);
+    }
+
+    return Ok(());
+  }
+
+  if let Some(("test", test_matches)) = matches.subcommand() {
+    if !run_test(test_matches) {
+      std::process::exit(1);
+    }
+
+    return Ok(());
+  }
+
   let port_str = matches.get_one::<String>("socket");
 
   let mut startup_type = StartUpType::STDIO;
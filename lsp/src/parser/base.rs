@@ -1,13 +1,14 @@
 use std::{
   cell::{Cell, OnceCell},
   fmt::{Debug, Display},
+  rc::Rc,
 };
 
 use lsp_types::{Position, Range};
 use strum::{AsRefStr, Display};
 use tree_sitter::{Node, Point};
 
-use super::types::*;
+use super::{schema::DocumentSchemas, types::*};
 
 macro_rules! bm_span(
   ($clazz:ident $($life:lifetime),*) => (
@@ -196,6 +197,11 @@ impl Contains for (Point, Point) {
 pub struct FirebaseTypeInformation {
   firebase_type: FirebaseType,
   docstring: Option<&'static str>,
+  type_arguments: Vec<FirebaseTypeInformation>,
+  fields: Vec<(String, FirebaseTypeInformation)>,
+  required_fields: Vec<String>,
+  closed: bool,
+  deprecated: bool,
 }
 
 impl FirebaseTypeInformation {
@@ -203,6 +209,11 @@ impl FirebaseTypeInformation {
     Self {
       firebase_type,
       docstring: Some(docstring),
+      type_arguments: vec![],
+      fields: vec![],
+      required_fields: vec![],
+      closed: false,
+      deprecated: false,
     }
   }
 
@@ -210,9 +221,49 @@ impl FirebaseTypeInformation {
     Self {
       firebase_type,
       docstring: None,
+      type_arguments: vec![],
+      fields: vec![],
+      required_fields: vec![],
+      closed: false,
+      deprecated: false,
     }
   }
 
+  pub fn with_docstring(mut self, docstring: Option<&'static str>) -> Self {
+    self.docstring = docstring;
+    self
+  }
+
+  /// Sets the element type of a list or set, or the key and value types of a map
+  pub fn with_type_arguments(mut self, type_arguments: Vec<FirebaseTypeInformation>) -> Self {
+    self.type_arguments = type_arguments;
+    self
+  }
+
+  /// Declares the fields of a map, e.g. from a document schema. Closed maps may not
+  /// contain any other fields.
+  pub fn with_fields(
+    mut self,
+    fields: Vec<(String, FirebaseTypeInformation)>,
+    closed: bool,
+  ) -> Self {
+    self.fields = fields;
+    self.closed = closed;
+    self
+  }
+
+  /// Declares the fields of a map that every document has to contain
+  pub fn with_required_fields(mut self, required_fields: Vec<String>) -> Self {
+    self.required_fields = required_fields;
+    self
+  }
+
+  /// Marks a legacy built-in that has a replacement
+  pub fn deprecated(mut self) -> Self {
+    self.deprecated = true;
+    self
+  }
+
   pub fn firebase_type(&self) -> FirebaseType {
     self.firebase_type
   }
@@ -220,6 +271,87 @@ impl FirebaseTypeInformation {
   pub fn docstring(&self) -> Option<&'static str> {
     self.docstring
   }
+
+  pub fn type_arguments(&self) -> &[FirebaseTypeInformation] {
+    &self.type_arguments
+  }
+
+  pub fn fields(&self) -> &[(String, FirebaseTypeInformation)] {
+    &self.fields
+  }
+
+  pub fn field(&self, name: &str) -> Option<&FirebaseTypeInformation> {
+    self
+      .fields
+      .iter()
+      .find(|(field_name, _)| field_name == name)
+      .map(|(_, field_type)| field_type)
+  }
+
+  pub fn required_fields(&self) -> &[String] {
+    &self.required_fields
+  }
+
+  /// Whether the map declares all of the fields it may contain
+  pub fn is_closed(&self) -> bool {
+    self.closed
+  }
+
+  pub fn is_deprecated(&self) -> bool {
+    self.deprecated
+  }
+
+  /// The type of the elements of a list or set, or of the values of a map
+  pub fn element_type(&self) -> Option<&FirebaseTypeInformation> {
+    match self.firebase_type.type_name() {
+      "list" | "set" => self.type_arguments.first(),
+      "map" => self.type_arguments.get(1),
+      _ => None,
+    }
+  }
+
+  /// Whether a value of the given type can be passed where this type is expected, including
+  /// the element types of collections if both are known
+  pub fn accepts(&self, value_type: &FirebaseTypeInformation) -> bool {
+    let elements_accepted = match (self.element_type(), value_type.element_type()) {
+      (Some(expected), Some(found)) => expected.accepts(found),
+      _ => true,
+    };
+
+    self.firebase_type.accepts(&value_type.firebase_type) && elements_accepted
+  }
+
+  /// The same type without documentation, as used for the type arguments of collections
+  pub fn undocumented(&self) -> Self {
+    Self::new_undocumented(self.firebase_type)
+      .with_type_arguments(self.type_arguments.clone())
+      .with_fields(self.fields.clone(), self.closed)
+  }
+
+  /// The type including its type arguments as shown in hover and completion, e.g. `List<String>`
+  pub fn label(&self) -> String {
+    self.format_with(&|firebase_type| format!("{:?}", firebase_type))
+  }
+
+  /// The type including its type arguments as written in rules, e.g. `list<string>`
+  pub fn type_name(&self) -> String {
+    self.format_with(&|firebase_type| firebase_type.type_name().to_owned())
+  }
+
+  fn format_with(&self, name: &dyn Fn(FirebaseType) -> String) -> String {
+    if self.type_arguments.is_empty() {
+      return name(self.firebase_type);
+    }
+
+    let type_arguments = self
+      .type_arguments
+      .iter()
+      .map(|argument| argument.format_with(name))
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    format!("{}<{}>", name(self.firebase_type), type_arguments)
+  }
 }
 
 type DefinitionLocation = Result<(Point, Point), String>;
@@ -247,20 +379,31 @@ pub enum ServiceType {
   Storage,
 }
 
+/// Version 2 lets recursive wildcards match zero segments and be used anywhere in a path,
+/// version 1 is assumed when no `rules_version` is declared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RulesVersion {
+  V1,
+  V2,
+}
+
 #[derive(Debug, Clone)]
 pub struct RulesTree {
   service_type: Option<ServiceType>,
+  declared_rules_version: Option<(RulesVersion, (Point, Point))>,
   body: Option<ServiceBody>,
   functions: Vec<Function>,
   start: Point,
   end: Point,
   global_variables: Vec<VariableDefinition>,
   global_functions: Vec<Function>,
+  document_schemas: Option<Rc<DocumentSchemas>>,
 }
 
 impl RulesTree {
   pub fn new(
     service_type: Option<ServiceType>,
+    declared_rules_version: Option<(RulesVersion, (Point, Point))>,
     body: Option<ServiceBody>,
     functions: Vec<Function>,
     start: Point,
@@ -268,6 +411,7 @@ impl RulesTree {
   ) -> Self {
     Self {
       service_type,
+      declared_rules_version,
       body,
       functions,
       start,
@@ -331,7 +475,15 @@ impl RulesTree {
             get_null_position(),
             get_null_position(),
           )),
-          vec![],
+          vec![FunctionParameter::new_with_type(
+            "path",
+            get_null_position(),
+            get_null_position(),
+            TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+              FirebaseType::String,
+              FirebaseType::String.docstring(),
+            )),
+          )],
           None,
           get_null_position(),
           get_null_position(),
@@ -346,7 +498,15 @@ impl RulesTree {
             get_null_position(),
             get_null_position(),
           )),
-          vec![],
+          vec![FunctionParameter::new_with_type(
+            "value",
+            get_null_position(),
+            get_null_position(),
+            TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+              FirebaseType::Any,
+              FirebaseType::Any.docstring(),
+            )),
+          )],
           None,
           get_null_position(),
           get_null_position(),
@@ -356,13 +516,40 @@ impl RulesTree {
           )),
         ),
       ]),
+      document_schemas: None,
     }
   }
 
+  /// Types the document data of the matches with the declared schemas
+  pub fn set_document_schemas(&mut self, document_schemas: Option<Rc<DocumentSchemas>>) {
+    self.document_schemas = document_schemas;
+  }
+
+  pub fn document_schemas(&self) -> Option<&DocumentSchemas> {
+    self.document_schemas.as_deref()
+  }
+
   pub fn service_body(&self) -> Option<&ServiceBody> {
     self.body.as_ref()
   }
 
+  /// Removes the user defined function with the span from the tree, so it can be taken over by
+  /// the evaluation of an edited version of the document
+  pub fn take_function(&mut self, span: (Point, Point)) -> Option<Function> {
+    if let Some(i) = self.functions.iter().position(|f| f.span() == span) {
+      return Some(self.functions.remove(i));
+    }
+
+    let body = self.body.as_mut()?;
+    take_function_from(&mut body.functions, &mut body.matches, span)
+  }
+
+  /// Removes the match with the span from the tree, see `take_function`
+  pub fn take_match(&mut self, span: (Point, Point)) -> Option<Match> {
+    let body = self.body.as_mut()?;
+    take_match_from(&mut body.matches, span)
+  }
+
   pub fn functions(&self) -> &[Function] {
     &self.functions
   }
@@ -371,6 +558,17 @@ impl RulesTree {
     self.service_type.as_ref()
   }
 
+  pub fn rules_version(&self) -> RulesVersion {
+    self
+      .declared_rules_version
+      .map_or(RulesVersion::V1, |(version, _)| version)
+  }
+
+  /// The span of the version string of the `rules_version` declaration, if there is one
+  pub fn rules_version_span(&self) -> Option<(Point, Point)> {
+    self.declared_rules_version.map(|(_, span)| span)
+  }
+
   pub fn global_functions(&self) -> &[Function] {
     &self.global_functions
   }
@@ -398,6 +596,35 @@ impl<'a> HasChildren<'a> for RulesTree {
   }
 }
 
+fn take_function_from(
+  functions: &mut Vec<Function>,
+  matches: &mut [Match],
+  span: (Point, Point),
+) -> Option<Function> {
+  if let Some(i) = functions.iter().position(|f| f.span() == span) {
+    return Some(functions.remove(i));
+  }
+
+  matches
+    .iter_mut()
+    .filter(|m| m.start <= span.0 && span.1 <= m.end)
+    .find_map(|m| {
+      let body = m.body.as_mut()?;
+      take_function_from(&mut body.functions, &mut body.matches, span)
+    })
+}
+
+fn take_match_from(matches: &mut Vec<Match>, span: (Point, Point)) -> Option<Match> {
+  if let Some(i) = matches.iter().position(|m| m.span() == span) {
+    return Some(matches.remove(i));
+  }
+
+  matches
+    .iter_mut()
+    .filter(|m| m.start <= span.0 && span.1 <= m.end)
+    .find_map(|m| take_match_from(&mut m.body.as_mut()?.matches, span))
+}
+
 #[derive(Debug, Clone)]
 pub struct FunctionParameter {
   name: String,
@@ -416,6 +643,20 @@ impl FunctionParameter {
     }
   }
 
+  pub fn new_with_type(
+    name: &str,
+    start: Point,
+    end: Point,
+    param_type: TypeInferenceResult,
+  ) -> Self {
+    Self {
+      name: name.to_owned(),
+      start,
+      end,
+      param_type: OnceCell::from(Some(param_type)),
+    }
+  }
+
   pub fn name(&self) -> &str {
     &self.name
   }
@@ -453,6 +694,27 @@ pub struct Function {
 }
 
 impl Function {
+  /// Forgets the inferred types, which depend on the rest of the document
+  pub fn reset_type_caches(&mut self) {
+    self.return_type_cache.take();
+
+    for param in &mut self.parameters {
+      param.param_type.take();
+    }
+
+    if let Some(body) = &mut self.body {
+      for def in &mut body.variable_defs {
+        def.definition_type_cache.take();
+        def
+          .definition
+          .iter_mut()
+          .for_each(ExprNode::reset_type_caches);
+      }
+
+      body.ret.iter_mut().for_each(ExprNode::reset_type_caches);
+    }
+  }
+
   pub fn new<'a>(
     name: Option<Identifier>,
     parameters: Vec<FunctionParameter>,
@@ -781,7 +1043,12 @@ impl MatchPathPart {
         .unwrap()
         .strip_suffix("}")
         .unwrap(),
-      // TODO Handle multi path parts
+      MatchPathPartType::MultiPath => self
+        .value
+        .strip_prefix("/{")
+        .unwrap()
+        .strip_suffix("=**}")
+        .unwrap(),
       _ => self.value.as_ref(),
     }
   }
@@ -859,6 +1126,30 @@ pub struct Match {
 }
 
 impl Match {
+  /// Forgets the inferred types, which depend on the rest of the document
+  pub fn reset_type_caches(&mut self) {
+    if let Some(path) = &mut self.path {
+      for part in &mut path.path_parts {
+        part.pathpart_firebase_type.take();
+      }
+    }
+
+    if let Some(body) = &mut self.body {
+      body
+        .functions
+        .iter_mut()
+        .for_each(Function::reset_type_caches);
+      body.matches.iter_mut().for_each(Match::reset_type_caches);
+
+      for rule in &mut body.rules {
+        rule
+          .condition
+          .iter_mut()
+          .for_each(ExprNode::reset_type_caches);
+      }
+    }
+  }
+
   pub fn new<'b>(path: Option<MatchPath>, body: Option<MatchBody>, node: Node<'b>) -> Self {
     Self {
       path,
@@ -967,98 +1258,22 @@ impl ServiceBody {
     functions: Vec<Function>,
     matches: Vec<Match>,
     rules: Vec<Rule>,
+    service_type: Option<&ServiceType>,
     node: Node<'b>,
   ) -> Self {
+    let (service_global_variables, service_global_functions) = match service_type {
+      Some(ServiceType::Storage) => (storage_global_variables(), vec![]),
+      _ => (firestore_global_variables(), firestore_global_functions()),
+    };
+
     Self {
       matches,
       functions,
       rules,
       start: node.start_position(),
       end: node.end_position(),
-      service_global_variables: Vec::from([
-        VariableDefinition::new_with_type(
-          "request",
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Request,
-            FirebaseType::Request.docstring(),
-          )),
-        ),
-        VariableDefinition::new_with_type(
-          "resource",
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Resource,
-            FirebaseType::Resource.docstring(),
-          )),
-        ),
-      ]),
-      service_global_functions: Vec::from([
-        Function::new_with_type(
-          Some(Identifier::new(
-            "get",
-            get_null_position(),
-            get_null_position(),
-          )),
-          vec![],
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Resource,
-            FirebaseType::Resource.docstring(),
-          )),
-        ),
-        Function::new_with_type(
-          Some(Identifier::new(
-            "getAfter",
-            get_null_position(),
-            get_null_position(),
-          )),
-          vec![],
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Resource,
-            FirebaseType::Resource.docstring(),
-          )),
-        ),
-        Function::new_with_type(
-          Some(Identifier::new(
-            "exists",
-            get_null_position(),
-            get_null_position(),
-          )),
-          vec![],
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Boolean,
-            FirebaseType::Boolean.docstring(),
-          )),
-        ),
-        Function::new_with_type(
-          Some(Identifier::new(
-            "existsAfter",
-            get_null_position(),
-            get_null_position(),
-          )),
-          vec![],
-          None,
-          get_null_position(),
-          get_null_position(),
-          TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
-            FirebaseType::Boolean,
-            FirebaseType::Boolean.docstring(),
-          )),
-        ),
-      ]),
+      service_global_variables,
+      service_global_functions,
     }
   }
 
@@ -1083,6 +1298,163 @@ impl ServiceBody {
   }
 }
 
+fn firestore_global_variables() -> Vec<VariableDefinition> {
+  Vec::from([
+    VariableDefinition::new_with_type(
+      "request",
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Request,
+        FirebaseType::Request.docstring(),
+      )),
+    ),
+    VariableDefinition::new_with_type(
+      "resource",
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Resource,
+        FirebaseType::Resource.docstring(),
+      )),
+    ),
+  ])
+}
+
+fn firestore_global_functions() -> Vec<Function> {
+  Vec::from([
+    Function::new_with_type(
+      Some(Identifier::new(
+        "get",
+        get_null_position(),
+        get_null_position(),
+      )),
+      vec![FunctionParameter::new_with_type(
+        "path",
+        get_null_position(),
+        get_null_position(),
+        TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+          FirebaseType::Path,
+          FirebaseType::Path.docstring(),
+        )),
+      )],
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Resource,
+        FirebaseType::Resource.docstring(),
+      )),
+    ),
+    Function::new_with_type(
+      Some(Identifier::new(
+        "getAfter",
+        get_null_position(),
+        get_null_position(),
+      )),
+      vec![FunctionParameter::new_with_type(
+        "path",
+        get_null_position(),
+        get_null_position(),
+        TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+          FirebaseType::Path,
+          FirebaseType::Path.docstring(),
+        )),
+      )],
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Resource,
+        FirebaseType::Resource.docstring(),
+      )),
+    ),
+    Function::new_with_type(
+      Some(Identifier::new(
+        "exists",
+        get_null_position(),
+        get_null_position(),
+      )),
+      vec![FunctionParameter::new_with_type(
+        "path",
+        get_null_position(),
+        get_null_position(),
+        TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+          FirebaseType::Path,
+          FirebaseType::Path.docstring(),
+        )),
+      )],
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Boolean,
+        FirebaseType::Boolean.docstring(),
+      )),
+    ),
+    Function::new_with_type(
+      Some(Identifier::new(
+        "existsAfter",
+        get_null_position(),
+        get_null_position(),
+      )),
+      vec![FunctionParameter::new_with_type(
+        "path",
+        get_null_position(),
+        get_null_position(),
+        TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+          FirebaseType::Path,
+          FirebaseType::Path.docstring(),
+        )),
+      )],
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::Boolean,
+        FirebaseType::Boolean.docstring(),
+      )),
+    ),
+  ])
+}
+
+fn storage_global_variables() -> Vec<VariableDefinition> {
+  Vec::from([
+    VariableDefinition::new_with_type(
+      "request",
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::StorageRequest,
+        FirebaseType::StorageRequest.docstring(),
+      )),
+    ),
+    VariableDefinition::new_with_type(
+      "resource",
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::StorageResource,
+        FirebaseType::StorageResource.docstring(),
+      )),
+    ),
+    VariableDefinition::new_with_type(
+      "firestore",
+      None,
+      get_null_position(),
+      get_null_position(),
+      TypeInferenceResult::HiddenDefinition(FirebaseTypeInformation::new_documented(
+        FirebaseType::FirestoreModule,
+        FirebaseType::FirestoreModule.docstring(),
+      )),
+    ),
+  ])
+}
+
 bm_contains!(ServiceBody);
 bm_span!(ServiceBody);
 bm_to_base_model!(ServiceBody);
@@ -1130,7 +1502,7 @@ bm_contains!(Method);
 bm_span!(Method);
 bm_to_base_model!(Method);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MethodType {
   Read,
   Write,
@@ -1142,6 +1514,25 @@ pub enum MethodType {
   Unknown,
 }
 
+impl MethodType {
+  /// Whether granting this method also grants the given one,
+  /// e.g. `read` covers `get` and `list`
+  pub fn covers(&self, method: &MethodType) -> bool {
+    match self {
+      MethodType::Read => matches!(
+        method,
+        MethodType::Read | MethodType::Get | MethodType::List
+      ),
+      MethodType::Write => matches!(
+        method,
+        MethodType::Write | MethodType::Create | MethodType::Update | MethodType::Delete
+      ),
+      MethodType::Unknown => false,
+      _ => self == method,
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct Rule {
   methods: Vec<Method>,
@@ -1190,6 +1581,7 @@ pub struct Literal {
   start: Point,
   end: Point,
   literal_type: Cell<FirebaseType>,
+  value: String,
 }
 
 bm_contains!(Literal);
@@ -1197,17 +1589,23 @@ bm_span!(Literal);
 bm_to_base_model!(Literal);
 
 impl Literal {
-  pub fn new<'a>(literal_type: FirebaseType, node: Node<'a>) -> Self {
+  pub fn new<'a>(literal_type: FirebaseType, value: &str, node: Node<'a>) -> Self {
     Self {
       start: node.start_position(),
       end: node.end_position(),
       literal_type: Cell::new(literal_type),
+      value: value.to_owned(),
     }
   }
 
   pub fn firebase_type(&self) -> &Cell<FirebaseType> {
     &self.literal_type
   }
+
+  /// The literal as written in the source, e.g. strings still include their quotes
+  pub fn value(&self) -> &str {
+    &self.value
+  }
 }
 
 impl<'a> HasChildren<'a> for Literal {
@@ -1222,7 +1620,7 @@ pub enum Operation {
   Addition,
   Multiplication,
   Division,
-  Relation,
+  Relation(RelationOperator),
   And,
   Or,
   Substraction,
@@ -1230,6 +1628,39 @@ pub enum Operation {
   Contains,
 }
 
+impl Operation {
+  /// The operator as written in rules
+  pub fn symbol(&self) -> &'static str {
+    match self {
+      Operation::Negation => "!",
+      Operation::Addition => "+",
+      Operation::Multiplication => "*",
+      Operation::Division => "/",
+      Operation::Relation(RelationOperator::Equal) => "==",
+      Operation::Relation(RelationOperator::NotEqual) => "!=",
+      Operation::Relation(RelationOperator::Less) => "<",
+      Operation::Relation(RelationOperator::LessEqual) => "<=",
+      Operation::Relation(RelationOperator::Greater) => ">",
+      Operation::Relation(RelationOperator::GreaterEqual) => ">=",
+      Operation::And => "&&",
+      Operation::Or => "||",
+      Operation::Substraction => "-",
+      Operation::Modulo => "%",
+      Operation::Contains => "in",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelationOperator {
+  Equal,
+  NotEqual,
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+}
+
 #[derive(Debug, Clone, AsRefStr)]
 pub enum Expr {
   Unary(Option<Operation>, Option<Box<ExprNode>>),
@@ -1256,7 +1687,7 @@ pub enum Expr {
   Map(Vec<ExprNode>),
   MapEntry(Option<Box<ExprNode>>, Option<Box<ExprNode>>),
   ExprGroup(Option<Box<ExprNode>>),
-  TypeComparison(Option<Box<ExprNode>>),
+  TypeComparison(Option<Box<ExprNode>>, Option<FirebaseType>),
   Range(Option<Box<ExprNode>>, Option<Box<ExprNode>>),
 }
 
@@ -1284,6 +1715,41 @@ impl Debug for ExprNode {
 }
 
 impl ExprNode {
+  /// Forgets the inferred types of the expression and its operands
+  pub fn reset_type_caches(&mut self) {
+    self.inferred_type_cache.take();
+
+    let operands: Vec<&mut ExprNode> = match &mut self.expr {
+      Expr::Unary(_, operand)
+      | Expr::MemberObject(operand)
+      | Expr::ExprGroup(operand)
+      | Expr::TypeComparison(operand, _) => operand.iter_mut().map(|o| o.as_mut()).collect(),
+      Expr::Binary(_, left, right)
+      | Expr::Member(left, right)
+      | Expr::Indexing(left, right)
+      | Expr::MapEntry(left, right)
+      | Expr::Range(left, right) => left
+        .iter_mut()
+        .chain(right.iter_mut())
+        .map(|o| o.as_mut())
+        .collect(),
+      Expr::Ternary(condition, on_true, on_false) => condition
+        .iter_mut()
+        .chain(on_true.iter_mut())
+        .chain(on_false.iter_mut())
+        .map(|o| o.as_mut())
+        .collect(),
+      Expr::MemberFunction(_, nodes)
+      | Expr::FunctionCall(_, nodes)
+      | Expr::Path(nodes)
+      | Expr::List(nodes)
+      | Expr::Map(nodes) => nodes.iter_mut().collect(),
+      Expr::MemberVariable(_) | Expr::Literal(_) | Expr::Variable(_) => vec![],
+    };
+
+    operands.into_iter().for_each(ExprNode::reset_type_caches);
+  }
+
   pub fn new<'a>(expr: Expr, node: Node<'a>) -> Self {
     Self {
       expr,
@@ -1303,10 +1769,89 @@ impl ExprNode {
   pub fn inferred_type<'a>(&self, traversing_path: &Vec<Base<'a>>) -> Option<&TypeInferenceResult> {
     self
       .inferred_type_cache
-      .get_or_init(|| self.calculate_inference(traversing_path))
+      .get_or_init(|| {
+        let inferred = self.calculate_inference(traversing_path);
+        self.narrow_inference(inferred, traversing_path)
+      })
       .as_ref()
   }
 
+  /// Narrows an unknown or `Any` type by the conditions guarding this expression, e.g. in the
+  /// right hand side of `data.age is int && data.age > 18` or the branches of a ternary
+  fn narrow_inference<'a>(
+    &'a self,
+    inferred: Option<TypeInferenceResult>,
+    traversing_path: &Vec<Base<'a>>,
+  ) -> Option<TypeInferenceResult> {
+    let is_unknown = inferred.as_ref().is_none_or(|res| match res {
+      TypeInferenceResult::Definable(_, Err(_)) | TypeInferenceResult::HiddenDefinition(_) => false,
+      res => res.type_information().firebase_type() == FirebaseType::Any,
+    });
+
+    if !is_unknown {
+      return inferred;
+    }
+
+    // A member field is narrowed as the whole member expression it belongs to
+    let subject = match traversing_path.last().and_then(|el| el.as_expr_node()) {
+      Some(parent) => match parent.expr() {
+        Expr::Member(_, Some(member)) if std::ptr::eq(member.as_ref(), self) => parent,
+        _ => self,
+      },
+      None => self,
+    };
+
+    let mut chain = traversing_path
+      .iter()
+      .filter_map(|el| el.as_expr_node())
+      .collect::<Vec<&ExprNode>>();
+    chain.push(self);
+
+    let narrowed = chain.windows(2).rev().find_map(|pair| {
+      let (parent, child) = (pair[0], pair[1]);
+      let is_child = |node: &Option<Box<ExprNode>>| {
+        node
+          .as_deref()
+          .is_some_and(|node| std::ptr::eq(node, child))
+      };
+
+      let facts = match parent.expr() {
+        Expr::Binary(Some(Operation::And), Some(left), right) if is_child(right) => {
+          narrowing_facts(left, true)
+        }
+        Expr::Binary(Some(Operation::Or), Some(left), right) if is_child(right) => {
+          narrowing_facts(left, false)
+        }
+        Expr::Ternary(Some(condition), on_true, _) if is_child(on_true) => {
+          narrowing_facts(condition, true)
+        }
+        Expr::Ternary(Some(condition), _, on_false) if is_child(on_false) => {
+          narrowing_facts(condition, false)
+        }
+        _ => vec![],
+      };
+
+      facts
+        .into_iter()
+        .rev()
+        .find(|(narrowed, _)| same_access(narrowed, subject))
+        .map(|(_, firebase_type)| firebase_type)
+    });
+
+    let Some(narrowed) = narrowed else {
+      return inferred;
+    };
+
+    let info = FirebaseTypeInformation::new_documented(narrowed, narrowed.docstring());
+
+    match inferred {
+      Some(TypeInferenceResult::Definable(_, span)) => {
+        Some(TypeInferenceResult::Definable(info, span))
+      }
+      _ => Some(TypeInferenceResult::Undefinable(info)),
+    }
+  }
+
   fn calculate_inference<'a>(
     &'a self,
     traversing_path: &Vec<Base<'a>>,
@@ -1344,33 +1889,54 @@ impl ExprNode {
         FirebaseTypeInformation::new_undocumented(literal.firebase_type().get()),
       )),
       Expr::Variable(ident) => find_variable_type(ident, &traversing_path),
-      Expr::List(_) => Some(TypeInferenceResult::Undefinable(
-        FirebaseTypeInformation::new_undocumented(FirebaseType::List),
-      )),
-      Expr::Unary(op, _) => match op {
+      Expr::List(elements) => {
+        let mut traversal_to_elements = traversing_path.clone();
+        traversal_to_elements.push(self.to_base_model());
+
+        let list = FirebaseTypeInformation::new_undocumented(FirebaseType::List);
+
+        Some(TypeInferenceResult::Undefinable(
+          match common_type(elements, &traversal_to_elements) {
+            Some(element_type) => list.with_type_arguments(vec![element_type]),
+            None => list,
+          },
+        ))
+      }
+      Expr::Unary(op, operand) => match op {
         Some(Operation::Negation) => Some(TypeInferenceResult::Undefinable(
           FirebaseTypeInformation::new_undocumented(FirebaseType::Boolean),
         )),
+        Some(Operation::Substraction) => {
+          let negated = match known_operand_type(operand, traversing_path) {
+            Some(operand_type) if is_numeric(operand_type) => operand_type,
+            Some(_) => FirebaseType::Any,
+            None => FirebaseType::Number,
+          };
+
+          Some(TypeInferenceResult::Undefinable(
+            FirebaseTypeInformation::new_undocumented(negated),
+          ))
+        }
         _ => Some(TypeInferenceResult::Undefinable(
           FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
         )),
       },
-      Expr::Binary(op, _, _) => match op {
+      Expr::Binary(op, left, right) => match op {
         None => None,
         Some(Operation::And)
         | Some(Operation::Or)
-        | Some(Operation::Relation)
+        | Some(Operation::Relation(_))
         | Some(Operation::Contains) => Some(TypeInferenceResult::Undefinable(
           FirebaseTypeInformation::new_undocumented(FirebaseType::Boolean),
         )),
-        Some(Operation::Addition)
-        | Some(Operation::Multiplication)
-        | Some(Operation::Division)
-        | Some(Operation::Substraction)
-        | Some(Operation::Modulo)
-        | Some(Operation::Negation) => Some(TypeInferenceResult::Undefinable(
-          FirebaseTypeInformation::new_undocumented(FirebaseType::Number),
+        Some(Operation::Negation) => Some(TypeInferenceResult::Undefinable(
+          FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
         )),
+        Some(operation) => Some(TypeInferenceResult::Undefinable(arithmetic_type(
+          operation,
+          known_operand_type(left, traversing_path),
+          known_operand_type(right, traversing_path),
+        ))),
       },
       Expr::Ternary(_, expr1, expr2) => {
         let t1 = expr1
@@ -1392,54 +1958,40 @@ impl ExprNode {
           FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
         ))
       }
-      Expr::Indexing(indexable, _) => {
-        if indexable.is_none() {
-          return None;
+      Expr::Indexing(indexable, index) => {
+        let indexed = match indexable.as_ref()?.inferred_type(traversing_path)? {
+          TypeInferenceResult::Definable(_, Err(_)) => return None,
+          res => res.type_information(),
+        };
+
+        // Slicing a list with a range results in a list of the same type
+        if let Some(Expr::Range(_, _)) = index.as_ref().map(|index| index.expr()) {
+          return Some(TypeInferenceResult::Undefinable(indexed.undocumented()));
         }
 
-        match indexable.as_ref().unwrap().expr() {
-          Expr::List(elements) | Expr::Map(elements) => {
-            if elements.is_empty() {
-              return Some(TypeInferenceResult::Undefinable(
-                FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
-              ));
-            }
-
-            let first_type = elements[0]
-              .inferred_type(&traversing_path)
-              .unwrap()
-              .type_information();
-
-            let all_types_whilemapped = elements.iter().map_while(|el| {
-              let el_type = el
-                .inferred_type(&traversing_path)
-                .as_ref()
-                .unwrap()
-                .type_information();
-
-              if el_type == first_type {
-                Some(true)
-              } else {
-                None
-              }
-            });
-
-            if all_types_whilemapped.count() == elements.len() {
-              return Some(TypeInferenceResult::Undefinable(first_type.to_owned()));
-            }
-
-            Some(TypeInferenceResult::Undefinable(
-              FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
-            ))
-          }
-          //TODO
-          Expr::Path(_) => None,
-          _ => None,
-        }
+        Some(TypeInferenceResult::Undefinable(
+          indexed
+            .element_type()
+            .cloned()
+            .unwrap_or(FirebaseTypeInformation::new_undocumented(FirebaseType::Any)),
+        ))
+      }
+      Expr::Map(entries) => {
+        let mut traversal_to_entries = traversing_path.clone();
+        traversal_to_entries.push(self.to_base_model());
+
+        let map = FirebaseTypeInformation::new_undocumented(FirebaseType::Map);
+
+        Some(TypeInferenceResult::Undefinable(
+          match common_type(entries, &traversal_to_entries) {
+            Some(value_type) => map.with_type_arguments(vec![
+              FirebaseTypeInformation::new_undocumented(FirebaseType::String),
+              value_type,
+            ]),
+            None => map,
+          },
+        ))
       }
-      Expr::Map(_) => Some(TypeInferenceResult::Undefinable(
-        FirebaseTypeInformation::new_undocumented(FirebaseType::Map),
-      )),
       Expr::MapEntry(_, val) => {
         if val.is_none() {
           return None;
@@ -1465,7 +2017,7 @@ impl ExprNode {
           .inferred_type(traversing_path)
           .cloned()
       }
-      Expr::TypeComparison(_) => Some(TypeInferenceResult::Undefinable(
+      Expr::TypeComparison(_, _) => Some(TypeInferenceResult::Undefinable(
         FirebaseTypeInformation::new_undocumented(FirebaseType::Boolean),
       )),
       Expr::Range(_, _) => Some(TypeInferenceResult::Undefinable(
@@ -1475,6 +2027,147 @@ impl ExprNode {
   }
 }
 
+/// The inferred type of an operand, `None` if it is not known
+fn known_operand_type(
+  operand: &Option<Box<ExprNode>>,
+  traversing_path: &Vec<Base<'_>>,
+) -> Option<FirebaseType> {
+  match operand.as_ref()?.inferred_type(traversing_path)? {
+    TypeInferenceResult::Definable(_, Err(_)) => None,
+    res => Some(res.type_information().firebase_type()),
+  }
+  .filter(|operand_type| *operand_type != FirebaseType::Any)
+}
+
+fn is_numeric(firebase_type: FirebaseType) -> bool {
+  matches!(
+    firebase_type,
+    FirebaseType::Integer | FirebaseType::Float | FirebaseType::Number
+  )
+}
+
+/// The result type of an arithmetic operation, e.g. `int` for `1 + 2`, `float` for `1 + 2.0`,
+/// `string` for `'a' + 'b'` or `timestamp` for `request.time + duration.value(1, 'd')`.
+/// Operations on operands they do not apply to result in `Any`.
+fn arithmetic_type(
+  operation: &Operation,
+  left: Option<FirebaseType>,
+  right: Option<FirebaseType>,
+) -> FirebaseTypeInformation {
+  let numeric = |operand: Option<FirebaseType>| operand.is_none_or(is_numeric);
+
+  let result = match (operation, left, right) {
+    (_, Some(FirebaseType::Integer), Some(FirebaseType::Integer)) => FirebaseType::Integer,
+    (_, Some(FirebaseType::Float), Some(FirebaseType::Integer | FirebaseType::Float))
+    | (_, Some(FirebaseType::Integer), Some(FirebaseType::Float)) => FirebaseType::Float,
+    (Operation::Addition, Some(FirebaseType::String), Some(FirebaseType::String) | None)
+    | (Operation::Addition, None, Some(FirebaseType::String)) => FirebaseType::String,
+    (Operation::Addition, Some(FirebaseType::List), Some(FirebaseType::List) | None)
+    | (Operation::Addition, None, Some(FirebaseType::List)) => FirebaseType::List,
+    (
+      Operation::Addition | Operation::Substraction,
+      Some(FirebaseType::Duration),
+      Some(FirebaseType::Duration),
+    )
+    | (Operation::Substraction, Some(FirebaseType::Timestamp), Some(FirebaseType::Timestamp)) => {
+      FirebaseType::Duration
+    }
+    (Operation::Addition, Some(FirebaseType::Timestamp), Some(FirebaseType::Duration))
+    | (Operation::Addition, Some(FirebaseType::Duration), Some(FirebaseType::Timestamp))
+    | (Operation::Substraction, Some(FirebaseType::Timestamp), Some(FirebaseType::Duration)) => {
+      FirebaseType::Timestamp
+    }
+    // Unknown operands may still be durations or timestamps when added or subtracted
+    (Operation::Addition | Operation::Substraction, None, None) => FirebaseType::Any,
+    (_, left, right) if numeric(left) && numeric(right) => FirebaseType::Number,
+    _ => FirebaseType::Any,
+  };
+
+  FirebaseTypeInformation::new_undocumented(result)
+}
+
+/// The type all the expressions have in common, used as the element type of lists and maps
+fn common_type<'a>(
+  nodes: &'a [ExprNode],
+  traversing_path: &Vec<Base<'a>>,
+) -> Option<FirebaseTypeInformation> {
+  let mut types = nodes.iter().map(|node| {
+    node
+      .inferred_type(traversing_path)
+      .map(|res| res.type_information().undocumented())
+  });
+
+  let first = types.next()??;
+
+  if first.firebase_type() == FirebaseType::Any {
+    return None;
+  }
+
+  types
+    .all(|other| other.as_ref() == Some(&first))
+    .then_some(first)
+}
+
+/// The types expressions are known to have when the condition evaluates to `holds`, from
+/// `is` checks and comparisons with literals
+fn narrowing_facts(condition: &ExprNode, holds: bool) -> Vec<(&ExprNode, FirebaseType)> {
+  let literal_type = |node: &ExprNode| match node.expr() {
+    Expr::Literal(literal) => Some(literal.firebase_type().get()),
+    _ => None,
+  };
+
+  match condition.expr() {
+    Expr::ExprGroup(Some(inner)) => narrowing_facts(inner, holds),
+    Expr::Unary(Some(Operation::Negation), Some(operand)) => narrowing_facts(operand, !holds),
+    Expr::Binary(Some(Operation::And), Some(left), Some(right)) if holds => {
+      [narrowing_facts(left, true), narrowing_facts(right, true)].concat()
+    }
+    Expr::Binary(Some(Operation::Or), Some(left), Some(right)) if !holds => {
+      [narrowing_facts(left, false), narrowing_facts(right, false)].concat()
+    }
+    Expr::TypeComparison(Some(operand), Some(compared_type)) if holds => {
+      vec![(operand.as_ref(), *compared_type)]
+    }
+    Expr::Binary(Some(Operation::Relation(operator)), Some(left), Some(right))
+      if holds == (*operator == RelationOperator::Equal)
+        && matches!(
+          operator,
+          RelationOperator::Equal | RelationOperator::NotEqual
+        ) =>
+    {
+      match (literal_type(left), literal_type(right)) {
+        (None, Some(firebase_type)) => vec![(left.as_ref(), firebase_type)],
+        (Some(firebase_type), None) => vec![(right.as_ref(), firebase_type)],
+        _ => vec![],
+      }
+    }
+    _ => vec![],
+  }
+}
+
+/// Whether both expressions access the same value, e.g. `request.resource.data.age`
+fn same_access(a: &ExprNode, b: &ExprNode) -> bool {
+  let same = |a: &Option<Box<ExprNode>>, b: &Option<Box<ExprNode>>| match (a, b) {
+    (Some(a), Some(b)) => same_access(a, b),
+    _ => false,
+  };
+
+  match (a.expr(), b.expr()) {
+    (Expr::ExprGroup(Some(a)), _) => same_access(a, b),
+    (_, Expr::ExprGroup(Some(b))) => same_access(a, b),
+    (Expr::Variable(a), Expr::Variable(b)) | (Expr::MemberVariable(a), Expr::MemberVariable(b)) => {
+      a.value() == b.value()
+    }
+    (Expr::Literal(a), Expr::Literal(b)) => a.value() == b.value(),
+    (Expr::MemberObject(a), Expr::MemberObject(b)) => same(a, b),
+    (Expr::Member(a_object, a_member), Expr::Member(b_object, b_member))
+    | (Expr::Indexing(a_object, a_member), Expr::Indexing(b_object, b_member)) => {
+      same(a_object, b_object) && same(a_member, b_member)
+    }
+    _ => false,
+  }
+}
+
 fn find_variable_type<'a>(
   ident: &Identifier,
   traversing_path: &Vec<Base<'a>>,
@@ -1730,8 +2423,7 @@ fn infer_member_function_type<'a>(
     return None;
   }
 
-  let inferred_function_type =
-    infer_function_type(parent_type.unwrap().firebase_type, ident.value());
+  let inferred_function_type = infer_function_type(parent_type.unwrap(), ident.value());
   if inferred_function_type.is_none() {
     return None;
   }
@@ -1762,15 +2454,65 @@ fn infer_member_variable_type<'a>(
     return None;
   }
 
-  let inferred_field_type =
-    infer_variable_type(parent_type.unwrap().firebase_type(), ident.value());
+  let inferred_field_type = infer_variable_type(parent_type.unwrap(), ident.value());
   if inferred_field_type.is_none() {
     return None;
   }
 
-  Some(TypeInferenceResult::Undefinable(
-    inferred_field_type.unwrap(),
-  ))
+  let mut inferred_field_type = inferred_field_type.unwrap();
+
+  // The document data is typed by the schema declared for the match, if there is one
+  if ident.value() == "data"
+    && is_requested_resource(obj_exprnode)
+    && let Some(data_type) = document_data_type(traversing_path)
+  {
+    inferred_field_type = data_type
+      .clone()
+      .with_docstring(inferred_field_type.docstring());
+  }
+
+  Some(TypeInferenceResult::Undefinable(inferred_field_type))
+}
+
+/// Whether the expression is `resource` or `request.resource`, the documents of the match
+fn is_requested_resource(node: &ExprNode) -> bool {
+  match node.expr() {
+    Expr::MemberObject(Some(inner)) => is_requested_resource(inner),
+    Expr::Variable(ident) => ident.value() == "resource",
+    Expr::Member(Some(object), Some(member)) => {
+      let is_request = match object.expr() {
+        Expr::MemberObject(Some(inner)) => {
+          matches!(inner.expr(), Expr::Variable(ident) if ident.value() == "request")
+        }
+        _ => false,
+      };
+
+      is_request
+        && matches!(member.expr(), Expr::MemberVariable(ident) if ident.value() == "resource")
+    }
+    _ => false,
+  }
+}
+
+/// The declared schema of the document data of the match the expression is in
+pub fn document_data_type<'a>(
+  traversing_path: &Vec<Base<'a>>,
+) -> Option<&'a FirebaseTypeInformation> {
+  let schemas = traversing_path.iter().find_map(|el| match el {
+    Base::RulesTree(tree) => tree.document_schemas(),
+    _ => None,
+  })?;
+
+  let parts = traversing_path
+    .iter()
+    .filter_map(|el| match el {
+      Base::Match(m) => m.path(),
+      _ => None,
+    })
+    .flat_map(|path| path.path_parts())
+    .collect::<Vec<&MatchPathPart>>();
+
+  schemas.data_type(&parts)
 }
 
 fn direct_member_object_parent<'a>(traversing_path: Vec<Base<'_>>) -> Option<&Box<ExprNode>> {
@@ -1819,7 +2561,7 @@ impl<'a> HasChildren<'a> for ExprNode {
         .map(|n| n as &dyn HasChildren<'a>)
         .collect(),
       Expr::ExprGroup(expr_node) => resolve_expr_nest(vec![expr_node]),
-      Expr::TypeComparison(expr_node) => resolve_expr_nest(vec![expr_node]),
+      Expr::TypeComparison(expr_node, _) => resolve_expr_nest(vec![expr_node]),
       Expr::Range(expr_node, expr_node1) => resolve_expr_nest(vec![expr_node, expr_node1]),
     }
   }
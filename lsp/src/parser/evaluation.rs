@@ -8,19 +8,107 @@ macro_rules! sanitized_children {
   };
 }
 
+/// The rules tree of a previous evaluation together with the source it was evaluated from.
+/// Reused functions and matches are moved out of the previous rules tree.
+pub struct PreviousEvaluation<'p> {
+  rules_tree: &'p mut RulesTree,
+  old_source: &'p [u8],
+  old_line_starts: Vec<usize>,
+  new_source: &'p [u8],
+  new_line_starts: Vec<usize>,
+}
+
+impl<'p> PreviousEvaluation<'p> {
+  pub fn new(rules_tree: &'p mut RulesTree, old_source: &'p [u8], new_source: &'p [u8]) -> Self {
+    Self {
+      rules_tree,
+      old_source,
+      old_line_starts: line_starts(old_source),
+      new_source,
+      new_line_starts: line_starts(new_source),
+    }
+  }
+
+  /// Whether the previous source had the same text at the same position
+  fn is_unchanged(&self, span: (Point, Point)) -> bool {
+    let old_text = text_at(self.old_source, &self.old_line_starts, span);
+    let new_text = text_at(self.new_source, &self.new_line_starts, span);
+
+    old_text.is_some() && old_text == new_text
+  }
+
+  fn take_function(&mut self, span: (Point, Point)) -> Option<Function> {
+    if !self.is_unchanged(span) {
+      return None;
+    }
+
+    let mut function = self.rules_tree.take_function(span)?;
+    function.reset_type_caches();
+    Some(function)
+  }
+
+  fn take_match(&mut self, span: (Point, Point)) -> Option<Match> {
+    if !self.is_unchanged(span) {
+      return None;
+    }
+
+    let mut m = self.rules_tree.take_match(span)?;
+    m.reset_type_caches();
+    Some(m)
+  }
+}
+
+fn line_starts(source: &[u8]) -> Vec<usize> {
+  let line_ends = source
+    .iter()
+    .enumerate()
+    .filter(|(_, byte)| **byte == b'\n')
+    .map(|(i, _)| i + 1);
+
+  std::iter::once(0).chain(line_ends).collect()
+}
+
+fn text_at<'s>(source: &'s [u8], line_starts: &[usize], span: (Point, Point)) -> Option<&'s [u8]> {
+  let start = line_starts.get(span.0.row)? + span.0.column;
+  let end = line_starts.get(span.1.row)? + span.1.column;
+
+  source.get(start..end)
+}
+
 pub fn evaluate_tree(tree: Tree, source_bytes: &[u8]) -> RulesTree {
+  evaluate_edited_tree(tree, source_bytes, None)
+}
+
+/// Evaluates the tree of an edited document. Functions and matches that did not change keep
+/// their text and position, so they are taken over from the previous evaluation instead of
+/// being evaluated again.
+pub fn evaluate_edited_tree(
+  tree: Tree,
+  source_bytes: &[u8],
+  mut previous: Option<PreviousEvaluation<'_>>,
+) -> RulesTree {
+  let previous = &mut previous;
   let node = tree.root_node();
 
   if node.kind() != "source_file" {
-    return RulesTree::new(None, None, Vec::new(), Point::new(0, 0), Point::new(0, 0));
+    return RulesTree::new(
+      None,
+      None,
+      None,
+      Vec::new(),
+      Point::new(0, 0),
+      Point::new(0, 0),
+    );
   }
 
   let mut match_body = None;
   let mut functions = vec![];
 
   let mut service_type = None;
+  let mut rules_version = None;
 
   sanitized_children!(node).for_each(|child| match child.kind() {
+    "rules_version_def" => rules_version = parse_rules_version_def(child, source_bytes),
     "service_type" => {
       service_type = match child.utf8_text(source_bytes) {
         Ok(text) => match text {
@@ -31,15 +119,21 @@ pub fn evaluate_tree(tree: Tree, source_bytes: &[u8]) -> RulesTree {
         Err(_) => None,
       };
     }
-    "function_def" => functions.push(parse_function_def(child, source_bytes)),
+    "function_def" => functions.push(parse_function_def(child, source_bytes, previous)),
     "service_body" => {
-      match_body = Some(parse_service_body(child, source_bytes));
+      match_body = Some(parse_service_body(
+        child,
+        service_type.as_ref(),
+        source_bytes,
+        previous,
+      ));
     }
     _ => return,
   });
 
   RulesTree::new(
     service_type,
+    rules_version,
     match_body,
     functions,
     node.start_position(),
@@ -47,29 +141,60 @@ pub fn evaluate_tree(tree: Tree, source_bytes: &[u8]) -> RulesTree {
   )
 }
 
-fn parse_service_body<'a, 'b>(node: Node<'b>, source_bytes: &[u8]) -> ServiceBody {
+fn parse_rules_version_def<'b>(
+  node: Node<'b>,
+  source_bytes: &[u8],
+) -> Option<(RulesVersion, (Point, Point))> {
+  let version = sanitized_children!(node).find(|child| child.kind() == "string")?;
+
+  let rules_version = match version
+    .utf8_text(source_bytes)
+    .ok()?
+    .trim_matches(['\'', '"'])
+  {
+    "1" => RulesVersion::V1,
+    "2" => RulesVersion::V2,
+    _ => return None,
+  };
+
+  Some((
+    rules_version,
+    (version.start_position(), version.end_position()),
+  ))
+}
+
+fn parse_service_body<'a, 'b>(
+  node: Node<'b>,
+  service_type: Option<&ServiceType>,
+  source_bytes: &[u8],
+  previous: &mut Option<PreviousEvaluation<'_>>,
+) -> ServiceBody {
   let mut matches = vec![];
   let mut functions = vec![];
   let mut rules = vec![];
 
   sanitized_children!(node).for_each(|child| match child.kind() {
-    "match_def" => matches.push(parse_match_def(child, source_bytes)),
-    "function_def" => functions.push(parse_function_def(child, source_bytes)),
+    "match_def" => matches.push(parse_match_def(child, source_bytes, previous)),
+    "function_def" => functions.push(parse_function_def(child, source_bytes, previous)),
     "rule_def" => rules.push(parse_rule(child, source_bytes)),
     _ => return,
   });
 
-  ServiceBody::new(functions, matches, rules, node)
+  ServiceBody::new(functions, matches, rules, service_type, node)
 }
 
-fn parse_match_body<'a, 'b>(node: Node<'b>, source_bytes: &[u8]) -> MatchBody {
+fn parse_match_body<'a, 'b>(
+  node: Node<'b>,
+  source_bytes: &[u8],
+  previous: &mut Option<PreviousEvaluation<'_>>,
+) -> MatchBody {
   let mut matches = vec![];
   let mut functions = vec![];
   let mut rules = vec![];
 
   sanitized_children!(node).for_each(|child| match child.kind() {
-    "match_def" => matches.push(parse_match_def(child, source_bytes)),
-    "function_def" => functions.push(parse_function_def(child, source_bytes)),
+    "match_def" => matches.push(parse_match_def(child, source_bytes, previous)),
+    "function_def" => functions.push(parse_function_def(child, source_bytes, previous)),
     "rule_def" => rules.push(parse_rule(child, source_bytes)),
     _ => return,
   });
@@ -77,20 +202,49 @@ fn parse_match_body<'a, 'b>(node: Node<'b>, source_bytes: &[u8]) -> MatchBody {
   MatchBody::new(functions, matches, rules, node)
 }
 
-fn parse_match_def<'a, 'b>(node: Node<'b>, source_bytes: &[u8]) -> Match {
+fn parse_match_def<'a, 'b>(
+  node: Node<'b>,
+  source_bytes: &[u8],
+  previous: &mut Option<PreviousEvaluation<'_>>,
+) -> Match {
+  let span = (node.start_position(), node.end_position());
+
+  if let Some(m) = previous
+    .as_mut()
+    .and_then(|previous| previous.take_match(span))
+  {
+    return m;
+  }
+
   let mut path = None;
   let mut body = None;
 
   sanitized_children!(node).for_each(|child| match child.kind() {
     "match_path" => path = Some(parse_match_path(child, source_bytes)),
-    "match_body" => body = Some(parse_match_body(child, source_bytes)),
+    "match_body" => body = Some(parse_match_body(child, source_bytes, previous)),
     _ => return,
   });
 
   Match::new(path, body, node)
 }
 
-fn parse_function_def<'b>(node: Node<'b>, source_bytes: &[u8]) -> Function {
+fn parse_function_def<'b>(
+  node: Node<'b>,
+  source_bytes: &[u8],
+  previous: &mut Option<PreviousEvaluation<'_>>,
+) -> Function {
+  // Functions span from their name to the end of their body
+  let name_start = sanitized_children!(node)
+    .find(|child| child.kind() == "function_name")
+    .map_or(node.start_position(), |child| child.start_position());
+
+  if let Some(function) = previous
+    .as_mut()
+    .and_then(|previous| previous.take_function((name_start, node.end_position())))
+  {
+    return function;
+  }
+
   let mut name = None;
   let mut params = vec![];
   let mut body = None;
@@ -352,7 +506,11 @@ fn parse_path<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
     "path_segment" => {
       sanitized_children!(child).for_each(|child| match child.kind() {
         "path_part" => path_segments.push(ExprNode::new(
-          Expr::Literal(Literal::new(FirebaseType::Any, child)),
+          Expr::Literal(Literal::new(
+            FirebaseType::Any,
+            child.utf8_text(source_bytes).unwrap(),
+            child,
+          )),
           child,
         )),
         "expr_group" => {
@@ -378,20 +536,20 @@ fn parse_literal<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
   }
 
   let child = children[0];
+  let value = child.utf8_text(source_bytes).unwrap();
 
   let literal = match child.kind() {
     "number" => Some(Literal::new(
-      child
-        .utf8_text(source_bytes)
-        .unwrap()
-        .parse::<f32>()
-        .map_or(FirebaseType::Integer, |_| FirebaseType::Float),
+      value
+        .parse::<i64>()
+        .map_or(FirebaseType::Float, |_| FirebaseType::Integer),
+      value,
       child,
     )),
-    "true" => Some(Literal::new(FirebaseType::Boolean, node)),
-    "false" => Some(Literal::new(FirebaseType::Boolean, node)),
-    "null" => Some(Literal::new(FirebaseType::Null, child)),
-    "string" => Some(Literal::new(FirebaseType::String, child)),
+    "true" => Some(Literal::new(FirebaseType::Boolean, value, node)),
+    "false" => Some(Literal::new(FirebaseType::Boolean, value, node)),
+    "null" => Some(Literal::new(FirebaseType::Null, value, child)),
+    "string" => Some(Literal::new(FirebaseType::String, value, child)),
     _ => None,
   };
 
@@ -586,11 +744,54 @@ fn parse_member_field<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNod
         _ => None,
       }
     }
-    "field_indexing" => parse_indexing(node, source_bytes),
+    "field_indexing" => parse_field_indexing(*field_node, source_bytes),
     _ => None,
   }
 }
 
+/// Indexing directly on a member field, e.g. `data.tags[0]`. The indexed object
+/// is kept as a member field so it resolves against the parent member object.
+fn parse_field_indexing<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
+  let children: Vec<Node<'b>> = sanitized_children!(node).collect();
+
+  if children.len() != 4 {
+    return None;
+  }
+
+  let object_node = children[0];
+
+  let object = match object_node.kind() {
+    "variable" => parse_variable(object_node, source_bytes).and_then(|var| match var.expr() {
+      Expr::Variable(ident) => Some(ExprNode::new(
+        Expr::MemberVariable(ident.to_owned()),
+        object_node,
+      )),
+      _ => None,
+    }),
+    "function_call" => {
+      parse_function_call(object_node, source_bytes).and_then(|call| match call.expr() {
+        Expr::FunctionCall(ident, args) => Some(ExprNode::new(
+          Expr::MemberFunction(ident.to_owned(), args.to_owned()),
+          object_node,
+        )),
+        _ => None,
+      })
+    }
+    _ => None,
+  };
+
+  let field_node = children[2];
+  let field = match field_node.kind() {
+    "expr" => parse_expr(field_node, source_bytes),
+    "range" => parse_range(field_node, source_bytes),
+    _ => None,
+  };
+
+  let expr = Expr::Indexing(object.map(Box::new), field.map(Box::new));
+
+  Some(ExprNode::new(expr, node))
+}
+
 fn parse_ternary<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
   let children: Vec<Node<'b>> = sanitized_children!(node).collect();
 
@@ -618,13 +819,28 @@ fn parse_ternary<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
 fn parse_type_comparison<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
   let children: Vec<Node<'b>> = sanitized_children!(node).collect();
 
-  if children.len() != 3 {
+  if children.len() != 4 {
     return None;
   }
 
   let operator = parse_expr(children[0], source_bytes);
 
-  let expr = Expr::TypeComparison(operator.map(Box::new));
+  let compared_type = match children[3].utf8_text(source_bytes).unwrap() {
+    "bool" => Some(FirebaseType::Boolean),
+    "int" => Some(FirebaseType::Integer),
+    "float" => Some(FirebaseType::Float),
+    "number" => Some(FirebaseType::Number),
+    "string" => Some(FirebaseType::String),
+    "list" => Some(FirebaseType::List),
+    "map" => Some(FirebaseType::Map),
+    "timestamp" => Some(FirebaseType::Timestamp),
+    "duration" => Some(FirebaseType::Duration),
+    "path" => Some(FirebaseType::Path),
+    "latlng" => Some(FirebaseType::LatLng),
+    _ => None,
+  };
+
+  let expr = Expr::TypeComparison(operator.map(Box::new), compared_type);
 
   Some(ExprNode::new(expr, node))
 }
@@ -649,7 +865,12 @@ fn parse_binary<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
     "*" => Some(Operation::Multiplication),
     "/" => Some(Operation::Division),
     "%" => Some(Operation::Modulo),
-    "<" | "<=" | ">=" | ">" | "==" | "!=" => Some(Operation::Relation),
+    "==" => Some(Operation::Relation(RelationOperator::Equal)),
+    "!=" => Some(Operation::Relation(RelationOperator::NotEqual)),
+    "<" => Some(Operation::Relation(RelationOperator::Less)),
+    "<=" => Some(Operation::Relation(RelationOperator::LessEqual)),
+    ">" => Some(Operation::Relation(RelationOperator::Greater)),
+    ">=" => Some(Operation::Relation(RelationOperator::GreaterEqual)),
     "in" => Some(Operation::Contains),
     _ => None,
   };
@@ -685,23 +906,27 @@ fn parse_contains<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
 fn parse_unary<'b>(node: Node<'b>, source_bytes: &[u8]) -> Option<ExprNode> {
   let children: Vec<Node<'b>> = sanitized_children!(node).collect();
 
-  if children.len() != 2 {
+  if children.len() < 2 {
     return None;
   }
 
-  let expr_res = parse_expr(children[1], source_bytes);
-
-  let op_node = children[0];
+  let (operand_node, op_nodes) = children.split_last().unwrap();
 
-  let operation = match op_node.kind() {
-    "!" => Some(Operation::Negation),
-    "-" => Some(Operation::Substraction),
-    _ => None,
-  };
+  // Repeated operators such as `!!a` are nested from the inside out
+  op_nodes.iter().rev().fold(
+    parse_expr(*operand_node, source_bytes),
+    |expr_res, op_node| {
+      let operation = match op_node.kind() {
+        "!" => Some(Operation::Negation),
+        "-" => Some(Operation::Substraction),
+        _ => None,
+      };
 
-  let expr = Expr::Unary(operation.clone(), expr_res.map(Box::new));
+      let expr = Expr::Unary(operation, expr_res.map(Box::new));
 
-  Some(ExprNode::new(expr, node))
+      Some(ExprNode::new(expr, node))
+    },
+  )
 }
 
 fn parse_method<'b>(node: Node<'b>) -> Option<Method> {
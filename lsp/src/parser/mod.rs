@@ -1,3 +1,5 @@
 pub mod base;
 pub mod evaluation;
+pub mod schema;
 pub mod types;
+pub mod typescript;
@@ -0,0 +1,311 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+
+use super::{base::*, types::*, typescript};
+
+/// The config file declaring the document schemas, looked up from the directory of the
+/// rules file upwards
+pub const SCHEMA_CONFIG_FILE: &str = "firestore-rules-lsp.json";
+
+#[derive(Debug, Clone, PartialEq)]
+enum SchemaPathSegment {
+  Collection(String),
+  Wildcard,
+  RecursiveWildcard,
+}
+
+/// The types of the document data of collections, keyed by the match path of their documents
+#[derive(Debug, Clone, Default)]
+pub struct DocumentSchemas {
+  schemas: Vec<(Vec<SchemaPathSegment>, FirebaseTypeInformation)>,
+}
+
+impl DocumentSchemas {
+  /// Loads the schemas of the config file that applies to the rules file, if there is one
+  pub fn load_for(rules_file: &Path) -> Result<Option<Self>, String> {
+    let Some(config_file) = find_config_file(rules_file) else {
+      return Ok(None);
+    };
+
+    let config = read_json(&config_file)?;
+    let base_dir = config_file.parent().unwrap_or(Path::new("."));
+
+    Self::from_config(&config, base_dir)
+      .map(Some)
+      .map_err(|err| format!("{}: {}", config_file.display(), err))
+  }
+
+  /// Reads the `schemas` of the config. Each match path either maps to an inline schema, to
+  /// the path of a schema file relative to the config or to a TypeScript declaration like
+  /// `models/post.ts#Post`.
+  pub fn from_config(config: &Value, base_dir: &Path) -> Result<Self, String> {
+    let Some(schemas) = config.get("schemas") else {
+      return Ok(Self::default());
+    };
+
+    let schemas = schemas
+      .as_object()
+      .ok_or("`schemas` must be an object of match paths")?
+      .iter()
+      .map(|(path, schema)| {
+        let schema = match schema {
+          Value::String(file) => read_schema_file(base_dir, file)?,
+          schema => schema.clone(),
+        };
+
+        let data_type = schema_type(&schema).map_err(|err| format!("{}: {}", path, err))?;
+
+        Ok((parse_schema_path(path), data_type))
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Self { schemas })
+  }
+
+  /// The type of the document data at the match path made of the given parts
+  pub fn data_type(&self, parts: &[&MatchPathPart]) -> Option<&FirebaseTypeInformation> {
+    let segments = parts
+      .iter()
+      .map(|part| match part.pathpart_type() {
+        MatchPathPartType::Collection => {
+          SchemaPathSegment::Collection(part.value().trim_start_matches('/').to_owned())
+        }
+        MatchPathPartType::Document => SchemaPathSegment::Wildcard,
+        MatchPathPartType::MultiPath => SchemaPathSegment::RecursiveWildcard,
+      })
+      .collect::<Vec<_>>();
+
+    let segments = without_database_prefix(&segments);
+
+    self
+      .schemas
+      .iter()
+      .find(|(path, _)| without_database_prefix(path) == segments)
+      .map(|(_, data_type)| data_type)
+  }
+}
+
+/// Whether the changed file is the config file that applies to the rules file, a config file
+/// that may start to apply to it, or a schema file the applying config refers to
+pub fn schemas_depend_on(rules_file: &Path, changed_file: &Path) -> bool {
+  let changed_file = normalized(changed_file);
+
+  let is_config_above = changed_file
+    .file_name()
+    .is_some_and(|name| name == SCHEMA_CONFIG_FILE)
+    && changed_file
+      .parent()
+      .is_some_and(|dir| normalized(rules_file).starts_with(dir));
+
+  if is_config_above {
+    return true;
+  }
+
+  let Some(config_file) = find_config_file(rules_file) else {
+    return false;
+  };
+
+  let Ok(config) = read_json(&config_file) else {
+    return false;
+  };
+
+  let base_dir = config_file.parent().unwrap_or(Path::new("."));
+
+  config
+    .get("schemas")
+    .and_then(|schemas| schemas.as_object())
+    .into_iter()
+    .flat_map(|schemas| schemas.values())
+    .filter_map(|schema| schema.as_str())
+    .map(|reference| reference.split('#').next().unwrap_or(reference))
+    .any(|file| normalized(&base_dir.join(file)) == changed_file)
+}
+
+/// The canonical path of the file, or the path without `.` components if it does not exist
+fn normalized(file: &Path) -> PathBuf {
+  file
+    .canonicalize()
+    .unwrap_or_else(|_| file.components().collect())
+}
+
+fn find_config_file(rules_file: &Path) -> Option<PathBuf> {
+  let rules_file = rules_file
+    .canonicalize()
+    .unwrap_or(rules_file.to_path_buf());
+
+  rules_file
+    .ancestors()
+    .skip(1)
+    .map(|dir| dir.join(SCHEMA_CONFIG_FILE))
+    .find(|config_file| config_file.is_file())
+}
+
+fn read_schema_file(base_dir: &Path, reference: &str) -> Result<Value, String> {
+  let (file, declaration) = match reference.split_once('#') {
+    Some((file, declaration)) => (file, Some(declaration)),
+    None => (reference, None),
+  };
+
+  let file = base_dir.join(file);
+
+  if file.extension().is_none_or(|extension| extension != "ts") {
+    return read_json(&file);
+  }
+
+  let Some(declaration) = declaration else {
+    return Err(format!(
+      "{}: The interface or type to use must be named, e.g. `{}#Post`",
+      file.display(),
+      reference
+    ));
+  };
+
+  let source = fs::read_to_string(&file).map_err(|err| format!("{}: {}", file.display(), err))?;
+
+  typescript::declaration_schema(&source, declaration)
+    .map_err(|err| format!("{}: {}", file.display(), err))
+}
+
+fn read_json(file: &Path) -> Result<Value, String> {
+  let content = fs::read_to_string(file).map_err(|err| format!("{}: {}", file.display(), err))?;
+
+  serde_json::from_str(&content).map_err(|err| format!("{}: {}", file.display(), err))
+}
+
+/// Splits a match path like `/posts/{post}` into its segments. Wildcard names do not matter
+/// for matching the paths of the rules.
+fn parse_schema_path(path: &str) -> Vec<SchemaPathSegment> {
+  path
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .map(|segment| match segment.strip_prefix('{') {
+      Some(wildcard) if wildcard.ends_with("=**}") => SchemaPathSegment::RecursiveWildcard,
+      Some(_) => SchemaPathSegment::Wildcard,
+      None => SchemaPathSegment::Collection(segment.to_owned()),
+    })
+    .collect()
+}
+
+/// Document paths may be written with or without the `/databases/{database}/documents` prefix
+fn without_database_prefix(segments: &[SchemaPathSegment]) -> &[SchemaPathSegment] {
+  match segments {
+    [
+      SchemaPathSegment::Collection(databases),
+      SchemaPathSegment::Wildcard,
+      SchemaPathSegment::Collection(documents),
+      rest @ ..,
+    ] if databases == "databases" && documents == "documents" => rest,
+    segments => segments,
+  }
+}
+
+/// Converts a JSON Schema into a type. Besides the JSON Schema types, the type names of rules
+/// like `timestamp` or `latlng` can be used.
+fn schema_type(schema: &Value) -> Result<FirebaseTypeInformation, String> {
+  let Some(schema) = schema.as_object() else {
+    return Ok(FirebaseTypeInformation::new_undocumented(FirebaseType::Any));
+  };
+
+  let type_name = match schema.get("type") {
+    Some(Value::String(type_name)) => Some(type_name.as_str()),
+    // Nullable types like `["string", "null"]` are typed as their non null type
+    Some(Value::Array(type_names)) => {
+      let non_null = type_names
+        .iter()
+        .filter_map(|type_name| type_name.as_str())
+        .filter(|type_name| *type_name != "null")
+        .collect::<Vec<&str>>();
+
+      match non_null.as_slice() {
+        [type_name] => Some(*type_name),
+        _ => None,
+      }
+    }
+    Some(_) => return Err("`type` must be a string or a list of strings".to_owned()),
+    None if schema.contains_key("properties") => Some("object"),
+    None => None,
+  };
+
+  let firebase_type = match (type_name, schema.get("format").and_then(|f| f.as_str())) {
+    (None, _) => FirebaseType::Any,
+    (Some("string"), Some("date-time")) => FirebaseType::Timestamp,
+    (Some("string"), _) => FirebaseType::String,
+    (Some("integer" | "int"), _) => FirebaseType::Integer,
+    (Some("number"), _) => FirebaseType::Number,
+    (Some("float"), _) => FirebaseType::Float,
+    (Some("boolean" | "bool"), _) => FirebaseType::Boolean,
+    (Some("null"), _) => FirebaseType::Null,
+    (Some("array" | "list"), _) => FirebaseType::List,
+    (Some("object" | "map"), _) => FirebaseType::Map,
+    (Some("timestamp"), _) => FirebaseType::Timestamp,
+    (Some("duration"), _) => FirebaseType::Duration,
+    (Some("bytes"), _) => FirebaseType::Bytes,
+    (Some("latlng"), _) => FirebaseType::LatLng,
+    (Some("path"), _) => FirebaseType::Path,
+    (Some(type_name), _) => return Err(format!("Unknown type `{}`", type_name)),
+  };
+
+  let type_information = FirebaseTypeInformation::new_undocumented(firebase_type);
+
+  match firebase_type {
+    FirebaseType::List => match schema.get("items") {
+      Some(items) => Ok(type_information.with_type_arguments(vec![schema_type(items)?])),
+      None => Ok(type_information),
+    },
+    FirebaseType::Map => object_type(type_information, schema),
+    _ => Ok(type_information),
+  }
+}
+
+fn object_type(
+  type_information: FirebaseTypeInformation,
+  schema: &Map<String, Value>,
+) -> Result<FirebaseTypeInformation, String> {
+  let additional_properties = schema.get("additionalProperties");
+
+  let Some(properties) = schema.get("properties") else {
+    // Maps without declared properties can still declare the type of their values
+    return match additional_properties {
+      Some(value_schema @ Value::Object(_)) => Ok(type_information.with_type_arguments(vec![
+        FirebaseTypeInformation::new_undocumented(FirebaseType::String),
+        schema_type(value_schema)?,
+      ])),
+      _ => Ok(type_information),
+    };
+  };
+
+  let fields = properties
+    .as_object()
+    .ok_or("`properties` must be an object")?
+    .iter()
+    .map(|(name, field_schema)| {
+      schema_type(field_schema)
+        .map(|field_type| (name.to_owned(), field_type))
+        .map_err(|err| format!("{}: {}", name, err))
+    })
+    .collect::<Result<Vec<_>, String>>()?;
+
+  // Unlike in JSON Schema, declared properties close the map unless stated otherwise, so
+  // misspelled fields can be reported
+  let closed = additional_properties.is_none_or(|additional| *additional == Value::Bool(false));
+
+  let required = match schema.get("required") {
+    Some(Value::Array(required)) => required
+      .iter()
+      .map(|name| name.as_str().map(str::to_owned))
+      .collect::<Option<Vec<String>>>()
+      .ok_or("`required` must be a list of property names")?,
+    Some(_) => return Err("`required` must be a list of property names".to_owned()),
+    None => vec![],
+  };
+
+  Ok(
+    type_information
+      .with_fields(fields, closed)
+      .with_required_fields(required),
+  )
+}
@@ -20,6 +20,8 @@ pub enum FirebaseType {
   Duration,
   Request,
   Resource,
+  StorageRequest,
+  StorageResource,
   Set,
   String,
   Timestamp,
@@ -30,6 +32,7 @@ pub enum FirebaseType {
   MathModule,
   TimestampModule,
   DurationModule,
+  FirestoreModule,
 }
 
 pub struct BuiltInFunctionParameter {
@@ -66,6 +69,56 @@ pub trait FirebaseTypeTrait {
   fn docstring(&self) -> &'static str;
 }
 
+impl FirebaseType {
+  /// The name of the type as written in rules, e.g. in `value is string`
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      FirebaseType::Boolean => "bool",
+      FirebaseType::Bytes => "bytes",
+      FirebaseType::Float => "float",
+      FirebaseType::Integer => "int",
+      FirebaseType::LatLng => "latlng",
+      FirebaseType::List => "list",
+      FirebaseType::Map
+      | FirebaseType::Auth
+      | FirebaseType::Token
+      | FirebaseType::FirebaseMap
+      | FirebaseType::Request
+      | FirebaseType::Resource
+      | FirebaseType::StorageRequest
+      | FirebaseType::StorageResource => "map",
+      FirebaseType::MapDiff => "map_diff",
+      FirebaseType::Number => "number",
+      FirebaseType::Path => "path",
+      FirebaseType::Duration => "duration",
+      FirebaseType::Set => "set",
+      FirebaseType::String => "string",
+      FirebaseType::Timestamp => "timestamp",
+      FirebaseType::Null => "null",
+      FirebaseType::Any => "any",
+      FirebaseType::HashingModule
+      | FirebaseType::LatLngModule
+      | FirebaseType::MathModule
+      | FirebaseType::TimestampModule
+      | FirebaseType::DurationModule
+      | FirebaseType::FirestoreModule => "namespace",
+    }
+  }
+
+  /// Whether a value of the given type can be passed where this type is expected
+  pub fn accepts(&self, value_type: &FirebaseType) -> bool {
+    match (self, value_type) {
+      (FirebaseType::Any, _) | (_, FirebaseType::Any) => true,
+      (
+        FirebaseType::Number | FirebaseType::Integer | FirebaseType::Float,
+        FirebaseType::Number | FirebaseType::Integer | FirebaseType::Float,
+      ) => true,
+      (FirebaseType::List | FirebaseType::Set, FirebaseType::List | FirebaseType::Set) => true,
+      _ => self.type_name() == value_type.type_name(),
+    }
+  }
+}
+
 impl FirebaseTypeTrait for FirebaseType {
   fn properties(&self) -> Vec<(&'static str, FirebaseTypeInformation)> {
     match self {
@@ -132,6 +185,168 @@ impl FirebaseTypeTrait for FirebaseType {
             "When the request was received by the service. For Firestore write operations that include server-side timestamps, this time will be equal to the server timestamp.",
           ),
         ),
+        (
+          "writeFields",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::List,
+            "Deprecated, use `request.resource.data.diff(resource.data).affectedKeys()` instead.\nList of the fields being written by the request.",
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )])
+          .deprecated(),
+        ),
+      ],
+      FirebaseType::StorageRequest => vec![
+        (
+          "auth",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Auth,
+            "The request authentication context. For more see [docs/reference/security/storage#auth](https://firebase.google.com/docs/reference/security/storage#auth).",
+          ),
+        ),
+        (
+          "method",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The request method\n(for example, 'get', 'list', 'create', 'update', or 'delete').",
+          ),
+        ),
+        (
+          "params",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Map,
+            "A map of the query parameters of the request, e.g. `maxResults`, `delimiter` and `prefix` of list requests.",
+          ),
+        ),
+        (
+          "path",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Path,
+            "The path of the object being accessed.",
+          ),
+        ),
+        (
+          "resource",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::StorageResource,
+            "The new object metadata, present on write requests only.",
+          ),
+        ),
+        (
+          "time",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Timestamp,
+            "When the request was received by the service.",
+          ),
+        ),
+      ],
+      FirebaseType::StorageResource => vec![
+        (
+          "name",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The full name of the object, including its path in the bucket.",
+          ),
+        ),
+        (
+          "bucket",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The name of the Google Cloud Storage bucket the object resides in.",
+          ),
+        ),
+        (
+          "generation",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Integer,
+            "The Google Cloud Storage object generation of the object. Used for object versioning.",
+          ),
+        ),
+        (
+          "metageneration",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Integer,
+            "The Google Cloud Storage object metageneration of the object. Used for object versioning.",
+          ),
+        ),
+        (
+          "size",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Integer,
+            "The size of the object in bytes.",
+          ),
+        ),
+        (
+          "timeCreated",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Timestamp,
+            "When the object was created.",
+          ),
+        ),
+        (
+          "updated",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Timestamp,
+            "When the object was last updated.",
+          ),
+        ),
+        (
+          "md5Hash",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "A base64 encoded MD5 hash of the object.",
+          ),
+        ),
+        (
+          "crc32c",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "A base64 encoded CRC32C hash of the object.",
+          ),
+        ),
+        (
+          "etag",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The etag associated with the object.",
+          ),
+        ),
+        (
+          "contentDisposition",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The content disposition associated with the object.",
+          ),
+        ),
+        (
+          "contentEncoding",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The content encoding associated with the object.",
+          ),
+        ),
+        (
+          "contentLanguage",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The content language associated with the object.",
+          ),
+        ),
+        (
+          "contentType",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "The content type associated with the object, e.g. `image/png`.",
+          ),
+        ),
+        (
+          "metadata",
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Map,
+            "A map of the developer provided custom metadata of the object.",
+          ),
+        ),
       ],
       FirebaseType::Auth => vec![
         (
@@ -544,6 +759,43 @@ impl FirebaseTypeTrait for FirebaseType {
             "Returns a lowercase version of the input string.",
           ),
         ),
+        (
+          "matches",
+          vec![BuiltInFunctionParameter::new(
+            "re",
+            FirebaseTypeInformation::new_documented(
+              FirebaseType::String,
+              FirebaseType::String.docstring(),
+            ),
+          )],
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Boolean,
+            "Performs a regular expression match, returns true if the whole string matches the given regular expression.",
+          ),
+        ),
+        (
+          "replace",
+          vec![
+            BuiltInFunctionParameter::new(
+              "re",
+              FirebaseTypeInformation::new_documented(
+                FirebaseType::String,
+                FirebaseType::String.docstring(),
+              ),
+            ),
+            BuiltInFunctionParameter::new(
+              "sub",
+              FirebaseTypeInformation::new_documented(
+                FirebaseType::String,
+                FirebaseType::String.docstring(),
+              ),
+            ),
+          ],
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::String,
+            "Replaces all occurrences of substrings matching a regular expression with a user-supplied string.",
+          ),
+        ),
         (
           "size",
           vec![],
@@ -552,6 +804,23 @@ impl FirebaseTypeTrait for FirebaseType {
             "Returns the number of characters in the string.",
           ),
         ),
+        (
+          "split",
+          vec![BuiltInFunctionParameter::new(
+            "re",
+            FirebaseTypeInformation::new_documented(
+              FirebaseType::String,
+              FirebaseType::String.docstring(),
+            ),
+          )],
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::List,
+            "Splits a string according to a regular expression.",
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
+        ),
         (
           "toUtf8",
           vec![],
@@ -731,7 +1000,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::List,
             "Get the list of keys in the map.",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
         (
           "values",
@@ -749,7 +1021,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::Set,
             "Returns the set of keys that have been added.",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
         (
           "affectedKeys",
@@ -757,7 +1032,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::Set,
             "Returns the set of keys that have been affected (added, removed, or changed).",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
         (
           "changedKeys",
@@ -765,7 +1043,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::Set,
             "Returns the set of keys that have been changed.",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
         (
           "unchangedKeys",
@@ -773,7 +1054,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::List,
             "Returns the list of keys that remain unchanged.",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
         (
           "removedKeys",
@@ -781,7 +1065,10 @@ impl FirebaseTypeTrait for FirebaseType {
           FirebaseTypeInformation::new_documented(
             FirebaseType::Set,
             "Returns the set of keys that have been removed.",
-          ),
+          )
+          .with_type_arguments(vec![FirebaseTypeInformation::new_undocumented(
+            FirebaseType::String,
+          )]),
         ),
       ],
       FirebaseType::Path => vec![(
@@ -801,6 +1088,38 @@ impl FirebaseTypeTrait for FirebaseType {
       FirebaseType::Number => vec![],
       FirebaseType::Request => vec![],
       FirebaseType::Resource => vec![],
+      FirebaseType::StorageRequest => vec![],
+      FirebaseType::StorageResource => vec![],
+      FirebaseType::FirestoreModule => vec![
+        (
+          "get",
+          vec![BuiltInFunctionParameter::new(
+            "path",
+            FirebaseTypeInformation::new_documented(
+              FirebaseType::Path,
+              FirebaseType::Path.docstring(),
+            ),
+          )],
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Resource,
+            "Get the Firestore document at the given path, e.g. `/databases/(default)/documents/users/$(request.auth.uid)`.",
+          ),
+        ),
+        (
+          "exists",
+          vec![BuiltInFunctionParameter::new(
+            "path",
+            FirebaseTypeInformation::new_documented(
+              FirebaseType::Path,
+              FirebaseType::Path.docstring(),
+            ),
+          )],
+          FirebaseTypeInformation::new_documented(
+            FirebaseType::Boolean,
+            "Check whether a Firestore document exists at the given path.",
+          ),
+        ),
+      ],
       FirebaseType::Set => vec![
         (
           "difference",
@@ -1139,6 +1458,12 @@ impl FirebaseTypeTrait for FirebaseType {
       FirebaseType::Resource => {
         "An object representing the current state of the database resource being accessed.\n\nFor more information, see: [docs/reference/rules/rules.Resource](https://firebase.google.com/docs/reference/rules/rules.Resource)."
       }
+      FirebaseType::StorageRequest => {
+        "An object representing the incoming request to the storage bucket.\n\nFor more information, see: [docs/reference/security/storage#request](https://firebase.google.com/docs/reference/security/storage#request)."
+      }
+      FirebaseType::StorageResource => {
+        "An object representing the metadata of the storage object being accessed.\n\nFor more information, see: [docs/reference/security/storage#resource](https://firebase.google.com/docs/reference/security/storage#resource)."
+      }
       FirebaseType::Set => {
         "An unordered collection of unique values.\n\nFor more information, see: [docs/reference/rules/rules.Set](https://firebase.google.com/docs/reference/rules/rules.Set)."
       }
@@ -1166,6 +1491,9 @@ impl FirebaseTypeTrait for FirebaseType {
       FirebaseType::DurationModule => {
         "A module providing functions for creating and manipulating durations.\n\nFor more information, see: [docs/reference/rules/rules.duration](https://firebase.google.com/docs/reference/rules/rules.duration)."
       }
+      FirebaseType::FirestoreModule => {
+        "A module providing access to Firestore documents from Cloud Storage rules.\n\nFor more information, see: [docs/storage/security/rules-conditions#enhance_with_firestore](https://firebase.google.com/docs/storage/security/rules-conditions#enhance_with_firestore)."
+      }
       FirebaseType::Any => "",
     }
   }
@@ -1183,23 +1511,49 @@ pub static SPECIAL_KEYWORDS: [&str; 20] = [
 ];
 
 pub fn infer_function_type<'a>(
-  obj_type: FirebaseType,
+  obj_type: &FirebaseTypeInformation,
   fun_name: &'a str,
 ) -> Option<FirebaseTypeInformation> {
-  obj_type
+  let return_type = obj_type
+    .firebase_type()
     .methods()
     .iter()
     .find(|f| f.0 == fun_name)
-    .map(|to| to.2.clone())
+    .map(|to| to.2.clone())?;
+
+  let Some(element_type) = obj_type.element_type().cloned() else {
+    return Some(return_type);
+  };
+
+  // Methods returning collections keep the element or value type of the object
+  let return_type = match (obj_type.firebase_type(), fun_name) {
+    (FirebaseType::List, "concat" | "removeAll" | "toSet")
+    | (FirebaseType::Set, "difference" | "intersection" | "union") => {
+      return_type.with_type_arguments(vec![element_type])
+    }
+    (_, "values") if obj_type.firebase_type().type_name() == "map" => {
+      return_type.with_type_arguments(vec![element_type])
+    }
+    (_, "get") if obj_type.firebase_type().type_name() == "map" => element_type,
+    _ => return_type,
+  };
+
+  Some(return_type)
 }
 
 pub fn infer_variable_type<'a>(
-  obj_type: FirebaseType,
+  obj_type: &FirebaseTypeInformation,
   var_name: &'a str,
 ) -> Option<FirebaseTypeInformation> {
   obj_type
+    .firebase_type()
     .properties()
     .iter()
     .find(|f| f.0 == var_name)
-    .and_then(|to| Some(to.1.clone()))
+    .map(|to| to.1.clone())
+    .or_else(|| obj_type.field(var_name).cloned())
+    .or_else(|| match obj_type.firebase_type() {
+      FirebaseType::Map => obj_type.element_type().cloned(),
+      _ => None,
+    })
 }
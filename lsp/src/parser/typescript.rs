@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value, json};
+
+/// Interfaces and type aliases referencing each other deeper than this are not resolved
+const MAX_REFERENCE_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Identifier(String),
+  StringLiteral(String),
+  NumberLiteral,
+  /// The `=>` of function types, kept apart so its `>` is not taken as a closing bracket
+  Arrow,
+  Punctuation(char),
+}
+
+/// A declaration of the file, its tokens are resolved lazily so declarations can reference
+/// each other regardless of their order
+enum Declaration {
+  Interface {
+    extends: Vec<String>,
+    body: Vec<Token>,
+  },
+  Alias(Vec<Token>),
+}
+
+/// Converts the interface or type alias with the given name into a JSON Schema. Only the
+/// subset of TypeScript used to describe documents is understood: primitives, `Timestamp`,
+/// nested objects, arrays, records, optional fields and unions of literals. Anything else is
+/// typed as `any`.
+pub fn declaration_schema(source: &str, name: &str) -> Result<Value, String> {
+  let declarations = parse_declarations(&tokenize(source)?)?;
+
+  if !declarations.contains_key(name) {
+    return Err(format!("No interface or type named `{}`", name));
+  }
+
+  Ok(
+    Resolver {
+      declarations: &declarations,
+    }
+    .named_schema(name, 0),
+  )
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = vec![];
+  let mut chars = source.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => {}
+      '/' if chars.peek() == Some(&'/') => {
+        chars.by_ref().find(|c| *c == '\n');
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut previous = ' ';
+
+        for c in chars.by_ref() {
+          if previous == '*' && c == '/' {
+            break;
+          }
+
+          previous = c;
+        }
+      }
+      '\'' | '"' | '`' => {
+        let mut literal = String::new();
+        let mut closed = false;
+
+        while let Some(next) = chars.next() {
+          match next {
+            '\\' => literal.extend(chars.next()),
+            next if next == c => {
+              closed = true;
+              break;
+            }
+            next => literal.push(next),
+          }
+        }
+
+        if !closed {
+          return Err("Unterminated string literal".to_owned());
+        }
+
+        tokens.push(Token::StringLiteral(literal));
+      }
+      '=' if chars.peek() == Some(&'>') => {
+        chars.next();
+        tokens.push(Token::Arrow);
+      }
+      c if c.is_ascii_digit() => {
+        while chars
+          .peek()
+          .is_some_and(|next| next.is_ascii_alphanumeric() || *next == '.' || *next == '_')
+        {
+          chars.next();
+        }
+
+        tokens.push(Token::NumberLiteral);
+      }
+      c if c.is_alphabetic() || c == '_' || c == '$' => {
+        let mut identifier = c.to_string();
+
+        while let Some(next) =
+          chars.next_if(|next| next.is_alphanumeric() || *next == '_' || *next == '$')
+        {
+          identifier.push(next);
+        }
+
+        tokens.push(Token::Identifier(identifier));
+      }
+      c => tokens.push(Token::Punctuation(c)),
+    }
+  }
+
+  Ok(tokens)
+}
+
+/// Collects the top level `interface` and `type` declarations, skipping everything else
+fn parse_declarations(tokens: &[Token]) -> Result<HashMap<String, Declaration>, String> {
+  let mut declarations = HashMap::new();
+  let mut i = 0;
+
+  while i < tokens.len() {
+    let keyword = identifier(tokens.get(i));
+    let name = identifier(tokens.get(i + 1));
+
+    match (keyword, name) {
+      (Some("interface"), Some(name)) => {
+        let mut extends = vec![];
+        let unclosed = || unclosed_declaration(name);
+        i = skip_type_parameters(tokens, i + 2).ok_or_else(unclosed)?;
+
+        if identifier(tokens.get(i)) == Some("extends") {
+          i += 1;
+
+          while let Some(base) = identifier(tokens.get(i)) {
+            extends.push(base.to_owned());
+            i = skip_type_parameters(tokens, i + 1).ok_or_else(unclosed)?;
+
+            if tokens.get(i) != Some(&Token::Punctuation(',')) {
+              break;
+            }
+
+            i += 1;
+          }
+        }
+
+        let end = matching_close(tokens, i).ok_or_else(unclosed)?;
+        let body = tokens.get(i + 1..end).unwrap_or_default().to_vec();
+
+        declarations.insert(name.to_owned(), Declaration::Interface { extends, body });
+        i = end + 1;
+      }
+      (Some("type"), Some(name)) => {
+        let unclosed = || unclosed_declaration(name);
+        i = skip_type_parameters(tokens, i + 2).ok_or_else(unclosed)?;
+
+        if tokens.get(i) != Some(&Token::Punctuation('=')) {
+          continue;
+        }
+
+        let end = type_end(tokens, i + 1).ok_or_else(unclosed)?;
+        declarations.insert(
+          name.to_owned(),
+          Declaration::Alias(tokens[i + 1..end].to_vec()),
+        );
+        i = end;
+      }
+      _ => i += 1,
+    }
+  }
+
+  Ok(declarations)
+}
+
+fn unclosed_declaration(name: &str) -> String {
+  format!("Unclosed bracket in the declaration of `{}`", name)
+}
+
+fn identifier(token: Option<&Token>) -> Option<&str> {
+  match token {
+    Some(Token::Identifier(identifier)) => Some(identifier),
+    _ => None,
+  }
+}
+
+/// The index of the bracket closing the one at `open`, if it is closed
+fn matching_close(tokens: &[Token], open: usize) -> Option<usize> {
+  let mut depth = 0;
+
+  for (i, token) in tokens.iter().enumerate().skip(open) {
+    match token {
+      Token::Punctuation('{' | '[' | '(' | '<') => depth += 1,
+      Token::Punctuation('}' | ']' | ')' | '>') => {
+        depth -= 1;
+
+        if depth == 0 {
+          return Some(i);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  None
+}
+
+fn skip_type_parameters(tokens: &[Token], i: usize) -> Option<usize> {
+  match tokens.get(i) {
+    Some(Token::Punctuation('<')) => matching_close(tokens, i).map(|close| close + 1),
+    _ => Some(i),
+  }
+}
+
+/// The index after the type starting at `start`, which ends at a `;`, `,` or unbalanced
+/// closing bracket outside of nested brackets. None if a nested bracket is never closed
+fn type_end(tokens: &[Token], start: usize) -> Option<usize> {
+  let mut i = start;
+
+  while let Some(token) = tokens.get(i) {
+    match token {
+      Token::Punctuation('{' | '[' | '(' | '<') => i = matching_close(tokens, i)? + 1,
+      Token::Punctuation(';' | ',' | '}' | ']' | ')' | '>') => return Some(i),
+      // Declarations are not always terminated by a semicolon
+      Token::Identifier(keyword)
+        if i > start
+          && matches!(
+            keyword.as_str(),
+            "export" | "interface" | "type" | "import" | "const" | "declare"
+          )
+          && !matches!(tokens.get(i - 1), Some(Token::Punctuation('|' | '&' | '.'))) =>
+      {
+        return Some(i);
+      }
+      _ => i += 1,
+    }
+  }
+
+  Some(i)
+}
+
+struct Resolver<'d> {
+  declarations: &'d HashMap<String, Declaration>,
+}
+
+impl Resolver<'_> {
+  fn named_schema(&self, name: &str, depth: usize) -> Value {
+    if depth > MAX_REFERENCE_DEPTH {
+      return json!({});
+    }
+
+    match self.declarations.get(name) {
+      Some(Declaration::Interface { extends, body }) => {
+        let mut schema = self.object_schema(body, depth);
+
+        for base in extends {
+          let base = self.named_schema(base, depth + 1);
+
+          let Some(Value::Object(base_properties)) = base.get("properties") else {
+            continue;
+          };
+
+          // Fields redeclared by the interface override the ones of its bases
+          for (field, field_schema) in base_properties {
+            if schema["properties"].get(field).is_some() {
+              continue;
+            }
+
+            schema["properties"][field] = field_schema.clone();
+
+            let base_required = base["required"]
+              .as_array()
+              .is_some_and(|required| required.contains(&json!(field)));
+
+            if let (true, Some(required)) = (base_required, schema["required"].as_array_mut()) {
+              required.push(json!(field));
+            }
+          }
+        }
+
+        schema
+      }
+      Some(Declaration::Alias(tokens)) => self.type_schema(tokens, depth),
+      None => builtin_schema(name),
+    }
+  }
+
+  /// The schema of the members of an object type, e.g. `{ title: string; tags?: string[] }`
+  fn object_schema(&self, body: &[Token], depth: usize) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    let mut additional_properties = None;
+    let mut i = 0;
+
+    while i < body.len() {
+      if identifier(body.get(i)) == Some("readonly")
+        && matches!(
+          body.get(i + 1),
+          Some(Token::Identifier(_) | Token::StringLiteral(_))
+        )
+      {
+        i += 1;
+      }
+
+      match (body.get(i), body.get(i + 1), body.get(i + 2)) {
+        // Index signatures like `[key: string]: number` declare the type of all values
+        (Some(Token::Punctuation('[')), _, _) => {
+          let close = matching_close(body, i).unwrap_or(body.len());
+
+          if body.get(close + 1) == Some(&Token::Punctuation(':')) {
+            let end = type_end(body, close + 2).unwrap_or(body.len());
+            additional_properties = Some(self.type_schema(&body[close + 2..end], depth));
+            i = end;
+          } else {
+            i = close + 1;
+          }
+        }
+        (Some(Token::Identifier(field) | Token::StringLiteral(field)), Some(next), after) => {
+          let optional = *next == Token::Punctuation('?');
+          let colon = if optional { after } else { Some(next) };
+
+          if colon != Some(&Token::Punctuation(':')) {
+            // Methods and other members are not part of the document data
+            i = type_end(body, i + 1).unwrap_or(body.len());
+            continue;
+          }
+
+          let start = i + if optional { 3 } else { 2 };
+          let end = type_end(body, start).unwrap_or(body.len());
+
+          properties.insert(field.to_owned(), self.type_schema(&body[start..end], depth));
+
+          if !optional {
+            required.push(Value::String(field.to_owned()));
+          }
+
+          i = end;
+        }
+        _ => i += 1,
+      }
+
+      while matches!(body.get(i), Some(Token::Punctuation(';' | ','))) {
+        i += 1;
+      }
+    }
+
+    match additional_properties {
+      Some(additional_properties) if properties.is_empty() => {
+        json!({ "type": "object", "additionalProperties": additional_properties })
+      }
+      Some(additional_properties) => json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": additional_properties,
+      }),
+      None => json!({ "type": "object", "properties": properties, "required": required }),
+    }
+  }
+
+  /// The schema of a type, unions of different types are typed as `any`
+  fn type_schema(&self, tokens: &[Token], depth: usize) -> Value {
+    let alternatives = split_top_level(tokens, '|')
+      .into_iter()
+      .filter(|alternative| !alternative.is_empty())
+      .map(|alternative| self.primary_schema(alternative, depth))
+      .filter(|schema| schema.get("type") != Some(&json!("null")))
+      .collect::<Vec<Value>>();
+
+    let Some(first) = alternatives.first() else {
+      return json!({ "type": "null" });
+    };
+
+    if alternatives
+      .iter()
+      .all(|alternative| alternative.get("type") == first.get("type"))
+    {
+      let mut schema = first.clone();
+
+      // String literal unions like `'draft' | 'published'`
+      let values = alternatives
+        .iter()
+        .map(|alternative| alternative.get("const").cloned())
+        .collect::<Option<Vec<Value>>>();
+
+      if let (Some(values), Value::Object(object)) = (values, &mut schema) {
+        object.remove("const");
+        object.insert("enum".to_owned(), Value::Array(values));
+      }
+
+      return schema;
+    }
+
+    json!({})
+  }
+
+  fn primary_schema(&self, tokens: &[Token], depth: usize) -> Value {
+    // Array types like `string[]` or `(string | number)[][]`
+    if let [rest @ .., Token::Punctuation('['), Token::Punctuation(']')] = tokens {
+      return json!({ "type": "array", "items": self.primary_schema(rest, depth) });
+    }
+
+    match tokens {
+      [Token::Punctuation('('), .., Token::Punctuation(')')]
+        if matching_close(tokens, 0) == Some(tokens.len() - 1) =>
+      {
+        self.type_schema(&tokens[1..tokens.len() - 1], depth)
+      }
+      [Token::Punctuation('{'), .., Token::Punctuation('}')] => {
+        self.object_schema(&tokens[1..tokens.len() - 1], depth)
+      }
+      [Token::StringLiteral(literal)] => json!({ "type": "string", "const": literal }),
+      [Token::NumberLiteral] | [Token::Punctuation('-'), Token::NumberLiteral] => {
+        json!({ "type": "number" })
+      }
+      [
+        Token::Identifier(name),
+        Token::Punctuation('<'),
+        arguments @ ..,
+        Token::Punctuation('>'),
+      ] => {
+        let arguments = split_top_level(arguments, ',');
+
+        match (name.as_str(), arguments.as_slice()) {
+          ("Array" | "ReadonlyArray", [items]) => {
+            json!({ "type": "array", "items": self.type_schema(items, depth) })
+          }
+          ("Record", [_, values]) => {
+            json!({ "type": "object", "additionalProperties": self.type_schema(values, depth) })
+          }
+          _ => json!({}),
+        }
+      }
+      // Qualified names like `firestore.Timestamp` are resolved by their last segment
+      [.., Token::Identifier(name)]
+        if tokens.iter().enumerate().all(|(i, token)| match token {
+          Token::Identifier(_) => i % 2 == 0,
+          token => i % 2 == 1 && *token == Token::Punctuation('.'),
+        }) =>
+      {
+        self.named_schema(name, depth + 1)
+      }
+      _ => json!({}),
+    }
+  }
+}
+
+/// The schemas of the TypeScript and Firestore SDK types that are not declared in the file
+fn builtin_schema(name: &str) -> Value {
+  match name {
+    "string" | "String" => json!({ "type": "string" }),
+    "number" | "Number" => json!({ "type": "number" }),
+    "boolean" | "Boolean" | "true" | "false" => json!({ "type": "boolean" }),
+    "null" | "undefined" => json!({ "type": "null" }),
+    "Timestamp" | "Date" => json!({ "type": "timestamp" }),
+    "GeoPoint" => json!({ "type": "latlng" }),
+    "DocumentReference" => json!({ "type": "path" }),
+    "Bytes" | "Uint8Array" => json!({ "type": "bytes" }),
+    "object" | "Object" => json!({ "type": "object", "additionalProperties": true }),
+    _ => json!({}),
+  }
+}
+
+/// Splits the tokens at the separator, ignoring separators inside of brackets
+fn split_top_level(tokens: &[Token], separator: char) -> Vec<&[Token]> {
+  let mut parts = vec![];
+  let mut start = 0;
+  let mut i = 0;
+
+  while i < tokens.len() {
+    match &tokens[i] {
+      Token::Punctuation('{' | '[' | '(' | '<') => {
+        i = matching_close(tokens, i).map_or(tokens.len(), |close| close + 1)
+      }
+      Token::Punctuation(c) if *c == separator => {
+        parts.push(&tokens[start..i]);
+        start = i + 1;
+        i += 1;
+      }
+      _ => i += 1,
+    }
+  }
+
+  parts.push(&tokens[start..tokens.len().min(i)]);
+  parts
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn optional_fields_are_not_required() {
+    let schema = declaration_schema(
+      "export interface Post { title: string; subtitle?: string; createdAt: Timestamp }",
+      "Post",
+    );
+
+    assert_eq!(
+      schema,
+      Ok(json!({
+        "type": "object",
+        "properties": {
+          "title": { "type": "string" },
+          "subtitle": { "type": "string" },
+          "createdAt": { "type": "timestamp" },
+        },
+        "required": ["title", "createdAt"],
+      }))
+    );
+  }
+
+  #[test]
+  fn literal_unions_become_enums() {
+    let schema = declaration_schema(
+      "type Status = 'draft' | 'published'; interface Post { status: Status; note: string | null; id: string | number }",
+      "Post",
+    )
+    .unwrap();
+
+    assert_eq!(
+      schema["properties"]["status"],
+      json!({ "type": "string", "enum": ["draft", "published"] })
+    );
+    assert_eq!(schema["properties"]["note"], json!({ "type": "string" }));
+    assert_eq!(schema["properties"]["id"], json!({}));
+  }
+
+  #[test]
+  fn arrays_and_records() {
+    let schema = declaration_schema(
+      "interface Post { tags: string[]; authors: Array<{ name: string }>; likes: Record<string, number> }",
+      "Post",
+    )
+    .unwrap();
+
+    assert_eq!(
+      schema["properties"]["tags"],
+      json!({ "type": "array", "items": { "type": "string" } })
+    );
+    assert_eq!(
+      schema["properties"]["authors"]["items"]["properties"]["name"],
+      json!({ "type": "string" })
+    );
+    assert_eq!(
+      schema["properties"]["likes"],
+      json!({ "type": "object", "additionalProperties": { "type": "number" } })
+    );
+  }
+
+  #[test]
+  fn extended_interfaces_inherit_fields() {
+    let schema = declaration_schema(
+      "interface Post extends Base<string> { title: number } interface Base<T> { id: T; title: string; deleted?: boolean }",
+      "Post",
+    )
+    .unwrap();
+
+    assert_eq!(schema["properties"]["title"], json!({ "type": "number" }));
+    assert_eq!(
+      schema["properties"]["deleted"],
+      json!({ "type": "boolean" })
+    );
+    assert_eq!(schema["required"], json!(["title", "id"]));
+  }
+
+  #[test]
+  fn function_types_do_not_end_the_object() {
+    let schema = declaration_schema(
+      "interface Post { onSave: () => void; title: string }",
+      "Post",
+    )
+    .unwrap();
+
+    assert_eq!(schema["properties"]["title"], json!({ "type": "string" }));
+  }
+
+  #[test]
+  fn malformed_declarations_are_errors() {
+    assert!(declaration_schema("export type Post = { title: string", "Post").is_err());
+    assert!(declaration_schema("export interface Post { title: string", "Post").is_err());
+    assert!(declaration_schema("interface Post { title: 'draft }", "Post").is_err());
+    assert!(declaration_schema("interface Post { title: string }", "Comment").is_err());
+  }
+}
@@ -21,6 +21,13 @@ pub fn to_position(point: Point) -> Position {
   )
 }
 
+pub fn to_range(span: (Point, Point)) -> Range {
+  Range {
+    start: to_position(span.0),
+    end: to_position(span.1),
+  }
+}
+
 pub fn try_see_if_typable<'a>(
   traversing_path: &Vec<Base<'a>>,
 ) -> Option<(Option<&'a TypeInferenceResult>, Base<'a>)> {
@@ -171,13 +178,14 @@ pub fn get_possible_completions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<Comp
     return vec![];
   }
 
-  let _type = typable.as_ref().unwrap().type_information().firebase_type();
+  let type_information = typable.as_ref().unwrap().type_information();
+  let _type = type_information.firebase_type();
 
   let properties = _type.properties();
   let props = properties.iter().map(|p| CompletionItem {
     label: p.0.to_owned(),
     label_details: Some(CompletionItemLabelDetails {
-      detail: Some(format!(" {:?}", p.1.firebase_type())),
+      detail: Some(format!(" {}", p.1.label())),
       description: None,
     }),
     insert_text: Some(p.0.to_owned()),
@@ -194,16 +202,17 @@ pub fn get_possible_completions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<Comp
     let params_markdown = p
       .1
       .iter()
-      .map(|param| format!("{}: {:?}", param.name(), param.param_type().firebase_type()))
+      .map(|param| format!("{}: {}", param.name(), param.param_type().label()))
       .collect::<Vec<String>>()
       .join(", ");
 
     let method_doc = p.2.docstring().unwrap_or("").to_owned();
+    let return_type = infer_function_type(type_information, p.0).unwrap_or(p.2.clone());
 
     CompletionItem {
       label: format!("{}", p.0.to_owned(),),
       label_details: Some(CompletionItemLabelDetails {
-        detail: Some(format!("({}) → {:?}", params_markdown, p.2.firebase_type())),
+        detail: Some(format!("({}) → {}", params_markdown, return_type.label())),
         description: None,
       }),
       insert_text: Some(p.0.to_owned()),
@@ -219,10 +228,27 @@ pub fn get_possible_completions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<Comp
     }
   });
 
-  return Vec::from_iter(props.chain(methods));
+  // Fields declared by a document schema
+  let fields = type_information
+    .fields()
+    .iter()
+    .map(|(name, field_type)| CompletionItem {
+      label: name.to_owned(),
+      label_details: Some(CompletionItemLabelDetails {
+        detail: Some(format!(" {}", field_type.label())),
+        description: None,
+      }),
+      insert_text: Some(name.to_owned()),
+      kind: Some(CompletionItemKind::FIELD),
+      ..Default::default()
+    });
+
+  Vec::from_iter(props.chain(fields).chain(methods))
 }
 
-fn get_scoped_variables<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<(String, bool, VariableType)> {
+pub fn get_scoped_variables<'a>(
+  traversing_path: &Vec<Base<'a>>,
+) -> Vec<(String, bool, VariableType)> {
   let mut scoped_vars = vec![];
 
   for node in traversing_path.iter().rev() {
@@ -233,6 +259,20 @@ fn get_scoped_variables<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<(String, boo
           .iter()
           .map(|def| (def.name().to_owned(), false, VariableType::Variable)),
       ),
+      Base::Function(fun) => scoped_vars.extend(
+        fun
+          .parameters()
+          .iter()
+          .map(|param| (param.name().to_owned(), false, VariableType::Variable)),
+      ),
+      Base::Match(m) => scoped_vars.extend(
+        m.path()
+          .map(|path| path.path_parts())
+          .unwrap_or_default()
+          .iter()
+          .filter(|part| part.pathpart_type() != &MatchPathPartType::Collection)
+          .map(|part| (part.value().to_owned(), false, VariableType::Variable)),
+      ),
       Base::ServiceBody(body) => scoped_vars.extend(
         body
           .service_global_variables()
@@ -500,7 +540,179 @@ pub fn get_references_of<'a>(
   locations.collect()
 }
 
-fn get_scoped_functions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<(String, bool)> {
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionKind {
+  Function,
+  Variable,
+  Parameter,
+  PathWildcard,
+}
+
+/// All the places a user defined symbol is written in the document
+#[derive(Debug, Clone)]
+pub struct SymbolOccurrences {
+  name: String,
+  kind: DefinitionKind,
+  definition: (Point, Point),
+  usages: Vec<(Point, Point)>,
+}
+
+impl SymbolOccurrences {
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn kind(&self) -> &DefinitionKind {
+    &self.kind
+  }
+
+  pub fn definition(&self) -> (Point, Point) {
+    self.definition
+  }
+
+  pub fn usages(&self) -> &[(Point, Point)] {
+    &self.usages
+  }
+
+  /// The definition span followed by all usage spans
+  pub fn all_spans(&self) -> Vec<(Point, Point)> {
+    let mut spans = vec![self.definition];
+    spans.extend(self.usages.iter().cloned());
+    spans
+  }
+}
+
+pub enum SymbolResolution {
+  Defined(SymbolOccurrences),
+  BuiltIn(String),
+  Unresolved,
+}
+
+/// Resolves the symbol under the given position to its definition and all of its usages.
+/// Usages are matched by resolving each of them to their definition, so shadowed
+/// symbols with the same name in other scopes are not included.
+pub fn resolve_symbol(position: Position, tree: &RulesTree) -> SymbolResolution {
+  let traversing_path = get_path_traversal(position, tree);
+
+  let last = traversing_path.last();
+
+  if last.is_none() {
+    return SymbolResolution::Unresolved;
+  }
+
+  let definition = match last.unwrap() {
+    Base::ExprNode(node) => {
+      let ident = match node.expr() {
+        Expr::Variable(ident) | Expr::FunctionCall(ident, _) => ident,
+        _ => return SymbolResolution::Unresolved,
+      };
+
+      let traversal_to_node = traversing_path[..traversing_path.len() - 1].to_vec();
+
+      match node.inferred_type(&traversal_to_node) {
+        Some(TypeInferenceResult::Definable(_, Ok(location))) => {
+          bfs_execute_at(tree, &vec![], &vec![symbol_definitions])
+            .into_iter()
+            .find(|(_, _, definition_key, _)| definition_key == location)
+        }
+        Some(TypeInferenceResult::HiddenDefinition(_)) => {
+          return SymbolResolution::BuiltIn(ident.value().to_owned());
+        }
+        _ => None,
+      }
+    }
+    _ => symbol_definitions(&traversing_path).and_then(|mut defs| defs.pop()),
+  };
+
+  if definition.is_none() {
+    return SymbolResolution::Unresolved;
+  }
+
+  let (name, kind, definition_key, definition) = definition.unwrap();
+
+  let usages = bfs_execute_at(tree, &vec![], &vec![resolved_identifier_usages])
+    .into_iter()
+    .filter(|(ident, resolved_key)| ident.value() == name && *resolved_key == definition_key)
+    .map(|(ident, _)| ident.span())
+    .collect();
+
+  SymbolResolution::Defined(SymbolOccurrences {
+    name,
+    kind,
+    definition,
+    usages,
+  })
+}
+
+/// (name, kind, span used by type inference to locate it, span of the name)
+pub type SymbolDefinition = (String, DefinitionKind, (Point, Point), (Point, Point));
+
+/// The symbol defined by the last node of the traversal
+pub fn symbol_definitions<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<SymbolDefinition>> {
+  let definition = match traversing_path.last()? {
+    Base::Function(func) => {
+      let ident = func.name()?;
+
+      (
+        ident.value().to_owned(),
+        DefinitionKind::Function,
+        ident.span(),
+        ident.span(),
+      )
+    }
+    Base::VariableDefinition(def) => (
+      def.name().to_owned(),
+      DefinitionKind::Variable,
+      def.span(),
+      name_span(def.span().0, 0, def.name()),
+    ),
+    Base::FunctionParameter(param) => (
+      param.name().to_owned(),
+      DefinitionKind::Parameter,
+      param.span(),
+      param.span(),
+    ),
+    Base::MatchPathPart(part) if part.pathpart_type() != &MatchPathPartType::Collection => (
+      part.value().to_owned(),
+      DefinitionKind::PathWildcard,
+      part.span(),
+      // Skip the leading "/{" of the path segment
+      name_span(part.span().0, 2, part.value()),
+    ),
+    _ => return None,
+  };
+
+  Some(vec![definition])
+}
+
+fn name_span(start: Point, offset: usize, name: &str) -> (Point, Point) {
+  let name_start = Point::new(start.row, start.column + offset);
+
+  (
+    name_start,
+    Point::new(name_start.row, name_start.column + name.len()),
+  )
+}
+
+pub fn resolved_identifier_usages<'a>(
+  traversing_path: &Vec<Base<'a>>,
+) -> Option<Vec<(&'a Identifier, (Point, Point))>> {
+  let node = traversing_path.last()?.as_expr_node()?;
+
+  let ident = match node.expr() {
+    Expr::Variable(ident) | Expr::FunctionCall(ident, _) => ident,
+    _ => return None,
+  };
+
+  let traversal_to_node = traversing_path[..traversing_path.len() - 1].to_vec();
+
+  match node.inferred_type(&traversal_to_node) {
+    Some(TypeInferenceResult::Definable(_, Ok(location))) => Some(vec![(ident, *location)]),
+    _ => None,
+  }
+}
+
+pub fn get_scoped_functions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<(String, bool)> {
   let mut scoped_funs: Vec<(String, bool)> = vec![];
 
   for node in traversing_path.iter().rev() {
@@ -546,6 +758,38 @@ fn get_scoped_functions<'a>(traversing_path: &Vec<Base<'a>>) -> Vec<(String, boo
   scoped_funs
 }
 
+/// The definition of the function visible under the name at the end of the traversal, together
+/// with the index of the scope in the traversal it was defined in
+pub fn find_function_definition<'a>(
+  name: &str,
+  traversing_path: &Vec<Base<'a>>,
+) -> Option<(usize, &'a Function)> {
+  let named = |func: &&Function| func.name().is_some_and(|ident| ident.value() == name);
+
+  traversing_path
+    .iter()
+    .enumerate()
+    .rev()
+    .find_map(|(i, node)| {
+      let func = match node {
+        Base::MatchBody(body) => body.functions().iter().find(named),
+        Base::ServiceBody(body) => body
+          .functions()
+          .iter()
+          .find(named)
+          .or_else(|| body.service_global_functions().iter().find(named)),
+        Base::RulesTree(tree) => tree
+          .functions()
+          .iter()
+          .find(named)
+          .or_else(|| tree.global_functions().iter().find(named)),
+        _ => None,
+      };
+
+      func.map(|func| (i, func))
+    })
+}
+
 type TraversableConsuming<'a, T> = fn(&Vec<Base<'a>>) -> Option<Vec<T>>;
 
 pub fn bfs_execute_at<'a, T>(
@@ -582,13 +826,13 @@ pub fn get_hover_result<'a>(traversing_path: &Vec<Base<'a>>) -> Option<MarkupCon
     .and_then(|res| res.0)
     .and_then(|t| match t {
       TypeInferenceResult::Definable(inferenced_type, Ok(_)) => {
-        Some((inferenced_type.firebase_type(), inferenced_type.docstring()))
+        Some((inferenced_type.label(), inferenced_type.docstring()))
       }
       TypeInferenceResult::Undefinable(inferenced_type) => {
-        Some((inferenced_type.firebase_type(), inferenced_type.docstring()))
+        Some((inferenced_type.label(), inferenced_type.docstring()))
       }
       TypeInferenceResult::HiddenDefinition(firebase_type_information) => Some((
-        firebase_type_information.firebase_type(),
+        firebase_type_information.label(),
         firebase_type_information.docstring(),
       )),
       TypeInferenceResult::Definable(_, Err(_)) => None,
@@ -602,6 +846,6 @@ pub fn get_hover_result<'a>(traversing_path: &Vec<Base<'a>>) -> Option<MarkupCon
 
   Some(MarkupContent {
     kind: MarkupKind::Markdown,
-    value: format!("`{:?}`\n\n---\n{}", fir_type, docstr.unwrap_or("")),
+    value: format!("`{}`\n\n---\n{}", fir_type, docstr.unwrap_or("")),
   })
 }
@@ -130,7 +130,7 @@ impl Into<String> for &MatchPath {
       .map(|p| match p.pathpart_type() {
         MatchPathPartType::Collection => p.value().to_string(),
         MatchPathPartType::Document => format!("{{{:}}}", p.value()),
-        MatchPathPartType::MultiPath => format!("{{{:}}}", p.value()),
+        MatchPathPartType::MultiPath => format!("{{{:}=**}}", p.value()),
       })
       .map(|s| s.replace("/", ""))
       .collect::<Vec<_>>()
@@ -0,0 +1,233 @@
+use lsp_types::{
+  CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, Position, Range,
+  SymbolKind, Uri,
+};
+
+use super::analysis::*;
+use crate::parser::base::*;
+
+/// A call of a user defined function from either another function or a rule condition
+struct CallSite<'a> {
+  caller: Base<'a>,
+  call: &'a Identifier,
+  callee: &'a Function,
+}
+
+/// The function whose name is under the position
+pub fn prepare_call_hierarchy(
+  position: Position,
+  tree: &RulesTree,
+  tree_file_uri: &Uri,
+) -> Option<Vec<CallHierarchyItem>> {
+  let occurrences = match resolve_symbol(position, tree) {
+    SymbolResolution::Defined(occurrences) => occurrences,
+    _ => return None,
+  };
+
+  if *occurrences.kind() != DefinitionKind::Function {
+    return None;
+  }
+
+  let definition = occurrences.definition();
+
+  let func = get_path_traversal(to_position(definition.0), tree)
+    .into_iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::Function(func) if func.name().is_some_and(|name| name.span() == definition) => {
+        Some(func)
+      }
+      _ => None,
+    })?;
+
+  Some(vec![function_item(func, tree, tree_file_uri)?])
+}
+
+/// The functions and rules calling the function of the item, with the ranges of the calls
+pub fn incoming_calls(
+  item: &CallHierarchyItem,
+  tree: &RulesTree,
+  tree_file_uri: &Uri,
+) -> Vec<CallHierarchyIncomingCall> {
+  let Some(Base::Function(target)) = find_item_node(item, tree) else {
+    return vec![];
+  };
+
+  let mut incoming: Vec<(Base<'_>, Vec<Range>)> = vec![];
+
+  for call_site in all_call_sites(tree) {
+    if !std::ptr::eq(call_site.callee, target) {
+      continue;
+    }
+
+    let call_range = to_range(call_site.call.span());
+
+    match incoming
+      .iter_mut()
+      .find(|(caller, _)| same_node(caller, &call_site.caller))
+    {
+      Some((_, ranges)) => ranges.push(call_range),
+      None => incoming.push((call_site.caller, vec![call_range])),
+    }
+  }
+
+  incoming
+    .into_iter()
+    .filter_map(|(caller, from_ranges)| {
+      let from = match caller {
+        Base::Function(func) => function_item(func, tree, tree_file_uri),
+        Base::Rule(rule) => Some(rule_item(rule, tree, tree_file_uri)),
+        _ => None,
+      }?;
+
+      Some(CallHierarchyIncomingCall { from, from_ranges })
+    })
+    .collect()
+}
+
+/// The functions called from the function or rule of the item, with the ranges of the calls
+pub fn outgoing_calls(
+  item: &CallHierarchyItem,
+  tree: &RulesTree,
+  tree_file_uri: &Uri,
+) -> Vec<CallHierarchyOutgoingCall> {
+  let Some(source) = find_item_node(item, tree) else {
+    return vec![];
+  };
+
+  let mut outgoing: Vec<(&Function, Vec<Range>)> = vec![];
+
+  for call_site in all_call_sites(tree) {
+    if !same_node(&call_site.caller, &source) {
+      continue;
+    }
+
+    let call_range = to_range(call_site.call.span());
+
+    match outgoing
+      .iter_mut()
+      .find(|(callee, _)| std::ptr::eq(*callee, call_site.callee))
+    {
+      Some((_, ranges)) => ranges.push(call_range),
+      None => outgoing.push((call_site.callee, vec![call_range])),
+    }
+  }
+
+  outgoing
+    .into_iter()
+    .filter_map(|(callee, from_ranges)| {
+      Some(CallHierarchyOutgoingCall {
+        to: function_item(callee, tree, tree_file_uri)?,
+        from_ranges,
+      })
+    })
+    .collect()
+}
+
+fn all_call_sites(tree: &RulesTree) -> Vec<CallSite<'_>> {
+  bfs_execute_at(tree, &vec![], &vec![user_function_calls])
+}
+
+/// Calls that resolve to a function defined in the document, built-ins are left out
+fn user_function_calls<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<CallSite<'a>>> {
+  let Expr::FunctionCall(ident, _) = traversing_path.last()?.as_expr_node()?.expr() else {
+    return None;
+  };
+
+  let traversal_to_call = traversing_path[..traversing_path.len() - 1].to_vec();
+
+  let (_, is_global) = get_scoped_functions(&traversal_to_call)
+    .into_iter()
+    .find(|(name, _)| name == ident.value())?;
+
+  if is_global {
+    return None;
+  }
+
+  let (_, callee) = find_function_definition(ident.value(), &traversal_to_call)?;
+
+  let caller = traversal_to_call
+    .iter()
+    .rev()
+    .find(|el| matches!(el, Base::Function(_) | Base::Rule(_)))?
+    .clone();
+
+  Some(vec![CallSite {
+    caller,
+    call: ident,
+    callee,
+  }])
+}
+
+fn same_node(a: &Base<'_>, b: &Base<'_>) -> bool {
+  match (a, b) {
+    (Base::Function(a), Base::Function(b)) => std::ptr::eq(*a, *b),
+    (Base::Rule(a), Base::Rule(b)) => std::ptr::eq(*a, *b),
+    _ => false,
+  }
+}
+
+/// The function or rule an item was created for, found again by its selection range
+fn find_item_node<'a>(item: &CallHierarchyItem, tree: &'a RulesTree) -> Option<Base<'a>> {
+  let start = to_point(item.selection_range.start);
+
+  get_path_traversal(item.selection_range.start, tree)
+    .into_iter()
+    .rev()
+    .find(|el| match el {
+      Base::Function(func) => func.name().is_some_and(|name| name.span().0 == start),
+      Base::Rule(rule) => rule.span().0 == start,
+      _ => false,
+    })
+}
+
+/// The path of the innermost match around the position, shown as detail of the items
+fn enclosing_match_path(position: Position, tree: &RulesTree) -> Option<String> {
+  get_path_traversal(position, tree)
+    .into_iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::Match(m) => m.path().map(|path| path.into()),
+      _ => None,
+    })
+    .map(|path: String| format!("/{}", path))
+}
+
+fn function_item(
+  func: &Function,
+  tree: &RulesTree,
+  tree_file_uri: &Uri,
+) -> Option<CallHierarchyItem> {
+  let name = func.name()?;
+
+  Some(CallHierarchyItem {
+    name: name.value().to_owned(),
+    kind: SymbolKind::FUNCTION,
+    tags: None,
+    detail: enclosing_match_path(to_position(name.span().0), tree),
+    uri: tree_file_uri.clone(),
+    range: to_range(func.span()),
+    selection_range: to_range(name.span()),
+    data: None,
+  })
+}
+
+fn rule_item(rule: &Rule, tree: &RulesTree, tree_file_uri: &Uri) -> CallHierarchyItem {
+  let methods = rule
+    .methods()
+    .iter()
+    .map(|m| format!("{:?}", m.method_type()).to_lowercase())
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  CallHierarchyItem {
+    name: format!("allow {}", methods),
+    kind: SymbolKind::INTERFACE,
+    tags: None,
+    detail: enclosing_match_path(to_position(rule.span().0), tree),
+    uri: tree_file_uri.clone(),
+    range: to_range(rule.span()),
+    selection_range: to_range(rule.span()),
+    data: None,
+  }
+}
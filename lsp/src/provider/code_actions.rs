@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use lsp_types::{
+  CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, NumberOrString, Position, Range,
+  TextEdit, Uri, WorkspaceEdit,
+};
+use tree_sitter::Point;
+
+use super::{analysis::*, diagnoser::*};
+use crate::parser::base::*;
+
+const DEFAULT_INDENT: &str = "  ";
+
+/// Quick fixes for the linting diagnostics reported for the document
+pub fn get_code_actions(
+  tree_file_uri: Uri,
+  diagnostics: &[Diagnostic],
+  tree: &RulesTree,
+  source: &str,
+) -> Vec<CodeActionOrCommand> {
+  let mut actions = vec![];
+
+  for diagnostic in diagnostics {
+    let code = match &diagnostic.code {
+      Some(NumberOrString::String(code)) => code.as_str(),
+      _ => continue,
+    };
+
+    let fixes: Vec<(String, Vec<TextEdit>)> = match code {
+      UNUSED_VARIABLE => remove_unused_variable(diagnostic, tree, source)
+        .into_iter()
+        .collect(),
+      UNUSED_PARAMETER => [
+        prefix_unused_parameter(diagnostic, tree),
+        remove_unused_parameter(diagnostic, tree),
+      ]
+      .into_iter()
+      .flatten()
+      .collect(),
+      UNDEFINED_FUNCTION => create_function_stub(diagnostic, tree, source)
+        .into_iter()
+        .collect(),
+      NON_BOOLEAN_RULE => compare_rule_with_null(diagnostic, tree)
+        .into_iter()
+        .collect(),
+      RULES_VERSION_2_REQUIRED => vec![use_rules_version_2(tree)],
+      _ => vec![],
+    };
+
+    for (i, (title, edits)) in fixes.into_iter().enumerate() {
+      actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+          changes: Some(HashMap::from([(tree_file_uri.clone(), edits)])),
+          ..Default::default()
+        }),
+        is_preferred: Some(i == 0),
+        ..Default::default()
+      }));
+    }
+  }
+
+  actions
+}
+
+/// The name of the symbol the diagnostic was reported for
+fn diagnostic_name(diagnostic: &Diagnostic) -> Option<&str> {
+  diagnostic.data.as_ref()?.get("name")?.as_str()
+}
+
+fn remove_unused_variable(
+  diagnostic: &Diagnostic,
+  tree: &RulesTree,
+  source: &str,
+) -> Option<(String, Vec<TextEdit>)> {
+  let name = diagnostic_name(diagnostic)?;
+  let start = to_point(diagnostic.range.start);
+
+  let def = get_path_traversal(diagnostic.range.start, tree)
+    .into_iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::VariableDefinition(def) if def.name() == name && def.span().0 == start => Some(def),
+      _ => None,
+    })?;
+
+  let (start, end) = def.span();
+
+  let start_line = line_at(source, start.row);
+  let end_line = line_at(source, end.row);
+
+  // The terminating semicolon is not part of the definition
+  let after_def = end_line.get(end.column..).unwrap_or("");
+  let statement_end = match after_def.trim_start().strip_prefix(';') {
+    Some(rest) => end_line.len() - rest.len(),
+    None => end.column,
+  };
+
+  // Neither is the `let` keyword, the definition starts at the variable name
+  let before_name = start_line.get(..start.column).unwrap_or("");
+  let before = before_name
+    .trim_end()
+    .strip_suffix("let")
+    .unwrap_or(before_name);
+  let start = Point::new(start.row, before.len());
+
+  let after = end_line.get(statement_end..).unwrap_or("");
+
+  let range = if before.trim().is_empty() && after.trim().is_empty() {
+    // Drop the whole lines when the definition is the only statement on them
+    Range {
+      start: Position::new(start.row as u32, 0),
+      end: Position::new(end.row as u32 + 1, 0),
+    }
+  } else if after.trim().is_empty() {
+    // Last statement on the line, drop the whitespace separating it from the previous one
+    to_range((
+      Point::new(start.row, before.trim_end().len()),
+      Point::new(end.row, end_line.len()),
+    ))
+  } else {
+    let trailing_whitespace = after.len() - after.trim_start().len();
+
+    to_range((
+      start,
+      Point::new(end.row, statement_end + trailing_whitespace),
+    ))
+  };
+
+  Some((
+    format!("Remove unused variable `{}`", name),
+    vec![TextEdit {
+      range,
+      new_text: String::new(),
+    }],
+  ))
+}
+
+fn prefix_unused_parameter(
+  diagnostic: &Diagnostic,
+  tree: &RulesTree,
+) -> Option<(String, Vec<TextEdit>)> {
+  let (_, param) = find_parameter(diagnostic, tree)?;
+
+  Some((
+    format!("Prefix `{}` with an underscore", param.name()),
+    vec![TextEdit {
+      range: to_range((param.span().0, param.span().0)),
+      new_text: "_".to_owned(),
+    }],
+  ))
+}
+
+/// Removes the parameter from the function definition and the matching argument from every call
+fn remove_unused_parameter(
+  diagnostic: &Diagnostic,
+  tree: &RulesTree,
+) -> Option<(String, Vec<TextEdit>)> {
+  let (func, param) = find_parameter(diagnostic, tree)?;
+
+  let param_spans = func
+    .parameters()
+    .iter()
+    .map(|p| p.span())
+    .collect::<Vec<(Point, Point)>>();
+
+  let index = param_spans.iter().position(|span| *span == param.span())?;
+
+  let mut edits = vec![TextEdit {
+    range: to_range(list_element_removal(&param_spans, index)),
+    new_text: String::new(),
+  }];
+
+  let call_spans = match resolve_symbol(to_position(func.name()?.span().0), tree) {
+    SymbolResolution::Defined(occurrences) => occurrences.all_spans().split_off(1),
+    _ => vec![],
+  };
+
+  for call_span in call_spans {
+    let args = get_path_traversal(to_position(call_span.0), tree)
+      .into_iter()
+      .rev()
+      .find_map(|el| match el.as_expr_node()?.expr() {
+        Expr::FunctionCall(ident, args) if ident.span() == call_span => Some(args),
+        _ => None,
+      });
+
+    let arg_spans = args
+      .map(|args| args.iter().map(|arg| arg.span()).collect::<Vec<_>>())
+      .unwrap_or_default();
+
+    if index < arg_spans.len() {
+      edits.push(TextEdit {
+        range: to_range(list_element_removal(&arg_spans, index)),
+        new_text: String::new(),
+      });
+    }
+  }
+
+  let title = match edits.len() {
+    1 => format!("Remove unused parameter `{}`", param.name()),
+    _ => format!(
+      "Remove unused parameter `{}` and its arguments",
+      param.name()
+    ),
+  };
+
+  Some((title, edits))
+}
+
+fn find_parameter<'a>(
+  diagnostic: &Diagnostic,
+  tree: &'a RulesTree,
+) -> Option<(&'a Function, &'a FunctionParameter)> {
+  let name = diagnostic_name(diagnostic)?;
+  let start = to_point(diagnostic.range.start);
+
+  let func = get_path_traversal(diagnostic.range.start, tree)
+    .into_iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::Function(func) => Some(func),
+      _ => None,
+    })?;
+
+  let param = func
+    .parameters()
+    .iter()
+    .find(|param| param.name() == name && param.span().0 == start)?;
+
+  Some((func, param))
+}
+
+/// The span covering a comma separated list element together with one of its separators
+fn list_element_removal(spans: &[(Point, Point)], index: usize) -> (Point, Point) {
+  if index > 0 {
+    (spans[index - 1].1, spans[index].1)
+  } else if spans.len() > 1 {
+    (spans[0].0, spans[1].0)
+  } else {
+    spans[0]
+  }
+}
+
+/// Inserts a function denying access at the start of the nearest match body of the call
+fn create_function_stub(
+  diagnostic: &Diagnostic,
+  tree: &RulesTree,
+  source: &str,
+) -> Option<(String, Vec<TextEdit>)> {
+  let name = diagnostic_name(diagnostic)?;
+  let start = to_point(diagnostic.range.start);
+
+  let traversal = get_path_traversal(diagnostic.range.start, tree);
+
+  let args = traversal.iter().rev().find_map(|el| {
+    let node = el.as_expr_node()?;
+
+    match node.expr() {
+      Expr::FunctionCall(ident, args) if ident.value() == name && node.span().0 == start => {
+        Some(args)
+      }
+      _ => None,
+    }
+  })?;
+
+  let body = traversal.iter().rev().find_map(|el| match el {
+    Base::MatchBody(body) => Some(*body),
+    _ => None,
+  })?;
+
+  let mut params: Vec<String> = vec![];
+
+  for (i, arg) in args.iter().enumerate() {
+    let param = match arg.expr() {
+      Expr::Variable(ident) if !params.iter().any(|p| p == ident.value()) => {
+        ident.value().to_owned()
+      }
+      _ => format!("arg{}", i),
+    };
+
+    params.push(param);
+  }
+
+  let (body_start, _) = body.span();
+  let body_indent = indentation(line_at(source, body_start.row));
+
+  // Indent like the existing statements of the body if there are any on their own lines
+  let statement_indent = body
+    .children()
+    .iter()
+    .map(|child| child.to_base_model().span().0)
+    .find(|child_start| child_start.row > body_start.row)
+    .map(|child_start| indentation(line_at(source, child_start.row)).to_owned())
+    .filter(|indent| indent.len() > body_indent.len())
+    .unwrap_or(format!("{}{}", body_indent, DEFAULT_INDENT));
+
+  let unit = statement_indent
+    .strip_prefix(body_indent)
+    .filter(|unit| !unit.is_empty())
+    .unwrap_or(DEFAULT_INDENT);
+
+  let stub = format!(
+    "\n{indent}function {name}({params}) {{\n{indent}{unit}return false;\n{indent}}}\n",
+    indent = statement_indent,
+    name = name,
+    params = params.join(", "),
+    unit = unit,
+  );
+
+  let insert_at = Point::new(body_start.row, body_start.column + 1);
+
+  Some((
+    format!("Create function `{}`", name),
+    vec![TextEdit {
+      range: to_range((insert_at, insert_at)),
+      new_text: stub,
+    }],
+  ))
+}
+
+fn compare_rule_with_null(
+  diagnostic: &Diagnostic,
+  tree: &RulesTree,
+) -> Option<(String, Vec<TextEdit>)> {
+  let span = (
+    to_point(diagnostic.range.start),
+    to_point(diagnostic.range.end),
+  );
+
+  let condition = get_path_traversal(diagnostic.range.start, tree)
+    .into_iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::Rule(rule) => rule.condition().filter(|cond| cond.span() == span),
+      _ => None,
+    })?;
+
+  let needs_group = matches!(
+    condition.expr(),
+    Expr::Binary(_, _, _) | Expr::Ternary(_, _, _) | Expr::TypeComparison(_, _) | Expr::Range(_, _)
+  );
+
+  let mut edits = vec![];
+
+  if needs_group {
+    edits.push(TextEdit {
+      range: to_range((span.0, span.0)),
+      new_text: "(".to_owned(),
+    });
+  }
+
+  edits.push(TextEdit {
+    range: to_range((span.1, span.1)),
+    new_text: format!("{} != null", if needs_group { ")" } else { "" }),
+  });
+
+  Some(("Compare with `null`".to_owned(), edits))
+}
+
+/// Changes the declared version, or declares it at the top of the document
+fn use_rules_version_2(tree: &RulesTree) -> (String, Vec<TextEdit>) {
+  let edit = match tree.rules_version_span() {
+    Some(span) => TextEdit {
+      range: to_range(span),
+      new_text: "'2'".to_owned(),
+    },
+    None => TextEdit {
+      range: to_range((Point::new(0, 0), Point::new(0, 0))),
+      new_text: "rules_version = '2';\n".to_owned(),
+    },
+  };
+
+  ("Use `rules_version = '2'`".to_owned(), vec![edit])
+}
+
+fn line_at(source: &str, row: usize) -> &str {
+  source
+    .split('\n')
+    .nth(row)
+    .unwrap_or("")
+    .trim_end_matches('\r')
+}
+
+fn indentation(line: &str) -> &str {
+  &line[..line.len() - line.trim_start().len()]
+}
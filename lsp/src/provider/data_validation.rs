@@ -0,0 +1,177 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{analysis::*, document_accesses::*};
+use crate::{
+  parser::base::*,
+  simulator::{interpreter::fold_constant, value::Value},
+};
+
+/// The document data a write request wants to store
+const INCOMING_DATA: &str = "request.resource.data";
+
+/// Methods on the keys of the data that whitelist fields
+const KEY_CONSTRAINING_METHODS: [&str; 2] = ["hasOnly", "hasAll"];
+
+/// How a rule condition validates the incoming document data
+#[derive(Debug, Default)]
+pub struct DataValidation<'a> {
+  constrains_keys: bool,
+  listed_fields: Vec<(String, &'a ExprNode)>,
+  type_checked_fields: BTreeSet<String>,
+  changeable_fields: Option<BTreeSet<String>>,
+}
+
+impl<'a> DataValidation<'a> {
+  /// Whether the keys of the data are constrained by `hasOnly` or `hasAll`, either directly
+  /// or through the keys affected by an update
+  pub fn constrains_keys(&self) -> bool {
+    self.constrains_keys
+  }
+
+  /// The field names listed in the list literals passed to `hasOnly` or `hasAll`, together
+  /// with the string literals naming them
+  pub fn listed_fields(&self) -> &[(String, &'a ExprNode)] {
+    &self.listed_fields
+  }
+
+  /// Whether the type of the top level field is checked with `is`
+  pub fn checks_type_of(&self, field: &str) -> bool {
+    self.type_checked_fields.contains(field)
+  }
+
+  /// Whether the field may be changed, which is only restricted by updates whose affected
+  /// keys are whitelisted like `diff(resource.data).affectedKeys().hasOnly(['likes'])`
+  pub fn may_change(&self, field: &str) -> bool {
+    self
+      .changeable_fields
+      .as_ref()
+      .is_none_or(|fields| fields.contains(field))
+  }
+}
+
+/// Collects how the condition validates `request.resource.data`. Calls are followed through
+/// user defined functions, with parameters and `let` variables bound to their values like for
+/// document accesses, so validations in helper functions like `isValidPost(request.resource.data)`
+/// are found.
+pub fn validate_incoming_data<'a>(
+  condition: &'a ExprNode,
+  traversal: &Vec<Base<'a>>,
+) -> DataValidation<'a> {
+  let mut validation = DataValidation::default();
+  collect_validations(condition, traversal, &HashMap::new(), 0, &mut validation);
+  validation
+}
+
+fn collect_validations<'a>(
+  node: &'a ExprNode,
+  traversal: &Vec<Base<'a>>,
+  bindings: &HashMap<String, String>,
+  depth: usize,
+  validation: &mut DataValidation<'a>,
+) {
+  match node.expr() {
+    Expr::FunctionCall(ident, args) if depth < MAX_CALL_DEPTH => {
+      if let Some((scope_index, func)) = find_function_definition(ident.value(), traversal) {
+        let arguments = func
+          .parameters()
+          .iter()
+          .zip(args.iter())
+          .map(|(param, arg)| (param.name().to_owned(), canonical_text(arg, bindings)))
+          .collect::<HashMap<String, String>>();
+
+        let traversal_to_scope = traversal[..=scope_index].to_vec();
+        collect_function_validations(func, &traversal_to_scope, arguments, depth + 1, validation);
+      }
+    }
+    Expr::Member(Some(object), Some(member)) => {
+      if let Expr::MemberFunction(ident, args) = member.expr()
+        && KEY_CONSTRAINING_METHODS.contains(&ident.value())
+      {
+        let keys = canonical_text(object, bindings);
+
+        let affected_keys = keys.starts_with(&format!("{}.diff(", INCOMING_DATA))
+          && keys.ends_with(".affectedKeys()");
+
+        if keys == format!("{}.keys()", INCOMING_DATA) || affected_keys {
+          validation.constrains_keys = true;
+
+          if let Some(Expr::List(elements)) = args.first().map(|list| list.expr()) {
+            let fields = elements
+              .iter()
+              .filter_map(|element| match fold_constant(element) {
+                Some(Value::String(field)) => Some((field, element)),
+                _ => None,
+              })
+              .collect::<Vec<(String, &ExprNode)>>();
+
+            if affected_keys && ident.value() == "hasOnly" {
+              let changeable = fields.iter().map(|(field, _)| field.to_owned());
+              validation.changeable_fields = Some(changeable.collect());
+            }
+
+            validation.listed_fields.extend(fields);
+          }
+        }
+      }
+    }
+    Expr::TypeComparison(Some(operand), _) => {
+      let accessed = canonical_text(operand, bindings);
+
+      if let Some(field) = top_level_field(&accessed) {
+        validation.type_checked_fields.insert(field);
+      }
+    }
+    _ => {}
+  }
+
+  for child in node.children() {
+    if let Some(child) = child.to_base_model().as_expr_node() {
+      collect_validations(child, traversal, bindings, depth, validation);
+    }
+  }
+}
+
+fn collect_function_validations<'a>(
+  func: &'a Function,
+  traversal_to_scope: &Vec<Base<'a>>,
+  mut bindings: HashMap<String, String>,
+  depth: usize,
+  validation: &mut DataValidation<'a>,
+) {
+  let Some(body) = func.body() else {
+    return;
+  };
+
+  for def in body.variable_defs() {
+    if let Some(expr) = def.definition() {
+      collect_validations(expr, traversal_to_scope, &bindings, depth, validation);
+
+      let value = canonical_text(expr, &bindings);
+      bindings.insert(def.name().to_owned(), value);
+    }
+  }
+
+  if let Some(ret) = body.ret() {
+    collect_validations(ret, traversal_to_scope, &bindings, depth, validation);
+  }
+}
+
+/// The name of the field of the incoming data accessed by the text, e.g. `title` for
+/// `request.resource.data.title` or `request.resource.data['title']`
+fn top_level_field(accessed: &str) -> Option<String> {
+  let access = accessed.strip_prefix(INCOMING_DATA)?;
+
+  let field = match access.strip_prefix('.') {
+    Some(field) => field,
+    None => access
+      .strip_prefix('[')?
+      .strip_suffix(']')?
+      .strip_prefix(['\'', '"'])?
+      .strip_suffix(['\'', '"'])?,
+  };
+
+  // Nested fields and method calls are not top level fields
+  let is_plain = !field.is_empty() && !field.contains(['.', '[', '(']);
+
+  is_plain.then(|| field.to_owned())
+}
@@ -1,10 +1,42 @@
 use std::str::FromStr;
 
-use lsp_types::{Diagnostic, DiagnosticSeverity, Range, Uri};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Uri};
+use serde_json::json;
 use tree_sitter::{Node, Point, Tree};
 
-use super::analysis::*;
-use crate::parser::{base::*, types::*};
+use super::{analysis::*, data_validation::*, document_accesses::*};
+use crate::{
+  parser::{base::*, types::*},
+  simulator::{
+    interpreter::{fold_constant, method_name},
+    value::Value,
+  },
+};
+
+// Codes of the diagnostics, used to offer quick fixes for them
+pub const SYNTAX_ERROR: &str = "syntax-error";
+pub const UNDEFINED_VARIABLE: &str = "undefined-variable";
+pub const UNDEFINED_FUNCTION: &str = "undefined-function";
+pub const CIRCULAR_DEFINITION: &str = "circular-definition";
+pub const NON_BOOLEAN_RULE: &str = "non-boolean-rule";
+pub const UNUSED_VARIABLE: &str = "unused-variable";
+pub const UNUSED_FUNCTION: &str = "unused-function";
+pub const UNUSED_PARAMETER: &str = "unused-parameter";
+pub const TOO_MANY_VARIABLES: &str = "too-many-variables";
+pub const RECURSIVE_FUNCTION: &str = "recursive-function";
+pub const ALWAYS_TRUE_RULE: &str = "always-true-rule";
+pub const RULES_VERSION_2_REQUIRED: &str = "rules-version-2-required";
+pub const TOO_MANY_DOCUMENT_ACCESSES: &str = "too-many-document-accesses";
+pub const WRONG_ARGUMENT_COUNT: &str = "wrong-argument-count";
+pub const ARGUMENT_TYPE_MISMATCH: &str = "argument-type-mismatch";
+pub const OPERATOR_TYPE_MISMATCH: &str = "operator-type-mismatch";
+pub const UNKNOWN_FIELD: &str = "unknown-field";
+pub const UNCONSTRAINED_FIELDS: &str = "unconstrained-fields";
+pub const UNCHECKED_FIELD_TYPE: &str = "unchecked-field-type";
+
+fn lint_code(code: &str) -> Option<NumberOrString> {
+  Some(NumberOrString::String(code.to_owned()))
+}
 
 pub fn diagnose_syntax_errors<'a>(node: Node<'a>) -> Vec<Diagnostic> {
   let mut errors: Vec<Diagnostic> = vec![];
@@ -34,7 +66,7 @@ pub fn diagnose_syntax_errors<'a>(node: Node<'a>) -> Vec<Diagnostic> {
               end: to_position(err_node.end_position()),
             },
             severity: Some(DiagnosticSeverity::ERROR),
-            code: None,
+            code: lint_code(SYNTAX_ERROR),
             code_description: None,
             source: None,
             message: get_err_msg(node),
@@ -83,8 +115,8 @@ fn is_parse_error<'a>(node: Node<'a>) -> Option<Node<'a>> {
 type Diagnoser = fn(&Vec<Base<'_>>) -> Option<Vec<Diagnostic>>;
 
 pub fn diagnose_linting_errors<'a>(tree: &'a RulesTree) -> Vec<Diagnostic> {
-  if tree.service_type() != Some(&ServiceType::Firestore) {
-    // Disable linting for non-firestore rules for now
+  if tree.service_type().is_none() {
+    // Without a known service the built-in variables and functions are unknown
     return vec![];
   }
 
@@ -94,6 +126,13 @@ pub fn diagnose_linting_errors<'a>(tree: &'a RulesTree) -> Vec<Diagnostic> {
     find_unused_elements,
     max_ten_let_bindings,
     functions_may_not_recurse,
+    find_always_true_rules,
+    recursive_wildcards_need_version_2,
+    document_accesses_within_limits,
+    check_function_arguments,
+    check_operand_types,
+    fields_declared_in_schema,
+    written_data_validated,
   ];
 
   bfs_execute_at(tree, &vec![], &diagnosers)
@@ -123,7 +162,7 @@ fn functions_may_not_recurse<'a>(traversal_list: &Vec<Base<'a>>) -> Option<Vec<D
               end: to_position(node.span().1),
             },
             severity: Some(DiagnosticSeverity::ERROR),
-            code: None,
+            code: lint_code(RECURSIVE_FUNCTION),
             code_description: None,
             source: None,
             message: format!(
@@ -170,7 +209,7 @@ fn max_ten_let_bindings<'a>(traversal_list: &Vec<Base<'a>>) -> Option<Vec<Diagno
             end: to_position(span.1),
           },
           severity: Some(DiagnosticSeverity::ERROR),
-          code: None,
+          code: lint_code(TOO_MANY_VARIABLES),
           code_description: None,
           source: None,
           message: format!("Function body has more than allowed 10 variable definitions"),
@@ -221,13 +260,13 @@ fn find_unused_elements<'a>(traversal_list: &Vec<Base<'a>>) -> Option<Vec<Diagno
             end: to_position(def.span().1),
           },
           severity: Some(DiagnosticSeverity::WARNING),
-          code: None,
+          code: lint_code(UNUSED_VARIABLE),
           code_description: None,
           source: None,
           message: format!("Variable `{}` is defined but never used", def.name()),
           related_information: None,
           tags: None,
-          data: None,
+          data: Some(json!({ "name": def.name() })),
         }]);
       }
 
@@ -267,25 +306,20 @@ fn find_unused_elements<'a>(traversal_list: &Vec<Base<'a>>) -> Option<Vec<Diagno
             end: to_position(ident.span().1),
           },
           severity: Some(DiagnosticSeverity::WARNING),
-          code: None,
+          code: lint_code(UNUSED_FUNCTION),
           code_description: None,
           source: None,
-          message: format!(
-            "Function `{}` is defined but never used",
-            func
-              .name()
-              .and_then(|ident| Some(ident.value()))
-              .unwrap_or("")
-          ),
+          message: format!("Function `{}` is defined but never used", ident.value()),
           related_information: None,
           tags: None,
-          data: None,
+          data: Some(json!({ "name": ident.value() })),
         }]);
       }
 
       return None;
     }
-    Base::FunctionParameter(param) => {
+    // Parameters prefixed with an underscore are unused on purpose
+    Base::FunctionParameter(param) if !param.name().starts_with('_') => {
       let references = traversal_list
         .iter()
         .rev()
@@ -309,13 +343,13 @@ fn find_unused_elements<'a>(traversal_list: &Vec<Base<'a>>) -> Option<Vec<Diagno
             end: to_position(param.span().1),
           },
           severity: Some(DiagnosticSeverity::WARNING),
-          code: None,
+          code: lint_code(UNUSED_PARAMETER),
           code_description: None,
           source: None,
           message: format!("Parameter `{}` is defined but never used", param.name()),
           related_information: None,
           tags: None,
-          data: None,
+          data: Some(json!({ "name": param.name() })),
         }]);
       }
 
@@ -342,7 +376,7 @@ fn find_if_rule_expr_not_bool(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diag
           end: to_position(span.1),
         },
         severity: Some(DiagnosticSeverity::WARNING),
-        code: None,
+        code: lint_code(NON_BOOLEAN_RULE),
         code_description: None,
         source: None,
         message: "Expected a boolean expression".to_owned(),
@@ -357,7 +391,7 @@ fn find_if_rule_expr_not_bool(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diag
           end: to_position(span.1),
         },
         severity: Some(DiagnosticSeverity::WARNING),
-        code: None,
+        code: lint_code(NON_BOOLEAN_RULE),
         code_description: None,
         source: None,
         message: format!(
@@ -395,6 +429,708 @@ fn find_if_rule_expr_not_bool(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diag
   }
 }
 
+/// Flags operators applied to operands whose inferred types they can not be applied to
+fn check_operand_types(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let node = traversal_list.last()?.as_expr_node()?;
+
+  let message = match node.expr() {
+    Expr::Unary(Some(operation), Some(operand)) => {
+      let operand_type = operand_type(operand, traversal_list)?;
+
+      let applicable = match operation {
+        Operation::Negation => operand_type == FirebaseType::Boolean,
+        _ => is_numeric(operand_type),
+      };
+
+      if applicable {
+        return None;
+      }
+
+      format!(
+        "Operator `{}` can not be applied to `{}`",
+        operation.symbol(),
+        operand_type.type_name()
+      )
+    }
+    Expr::Binary(Some(operation), Some(left), Some(right)) => {
+      let left_type = operand_type(left, traversal_list);
+      let right_type = operand_type(right, traversal_list);
+
+      let applicable = match (operation, left_type, right_type) {
+        (Operation::And | Operation::Or, _, _) => [left_type, right_type]
+          .iter()
+          .flatten()
+          .all(|operand_type| *operand_type == FirebaseType::Boolean),
+        (Operation::Contains, _, Some(collection)) => {
+          matches!(collection.type_name(), "list" | "set" | "map")
+        }
+        (_, Some(left_type), Some(right_type)) => {
+          binary_operation_applicable(operation, left_type, right_type)
+        }
+        _ => true,
+      };
+
+      if applicable {
+        return None;
+      }
+
+      let type_name = |operand_type: Option<FirebaseType>| {
+        operand_type.map_or("any", |operand_type| operand_type.type_name())
+      };
+
+      format!(
+        "Operator `{}` can not be applied to `{}` and `{}`",
+        operation.symbol(),
+        type_name(left_type),
+        type_name(right_type)
+      )
+    }
+    _ => return None,
+  };
+
+  Some(vec![Diagnostic {
+    range: Range {
+      start: to_position(node.span().0),
+      end: to_position(node.span().1),
+    },
+    severity: Some(DiagnosticSeverity::ERROR),
+    code: lint_code(OPERATOR_TYPE_MISMATCH),
+    code_description: None,
+    source: None,
+    message,
+    related_information: None,
+    tags: None,
+    data: None,
+  }])
+}
+
+/// The inferred type of an operand, if it is known
+fn operand_type(operand: &ExprNode, traversal_list: &Vec<Base<'_>>) -> Option<FirebaseType> {
+  match operand.inferred_type(traversal_list)? {
+    TypeInferenceResult::Definable(_, Err(_)) => None,
+    res => Some(res.type_information().firebase_type()),
+  }
+  .filter(|operand_type| *operand_type != FirebaseType::Any)
+}
+
+fn is_numeric(firebase_type: FirebaseType) -> bool {
+  matches!(
+    firebase_type,
+    FirebaseType::Integer | FirebaseType::Float | FirebaseType::Number
+  )
+}
+
+fn binary_operation_applicable(
+  operation: &Operation,
+  left: FirebaseType,
+  right: FirebaseType,
+) -> bool {
+  let numeric = is_numeric(left) && is_numeric(right);
+  let same = |type_names: &[&str]| {
+    left.type_name() == right.type_name() && type_names.contains(&left.type_name())
+  };
+
+  match operation {
+    Operation::Relation(RelationOperator::Equal | RelationOperator::NotEqual) => true,
+    Operation::Relation(_) => {
+      numeric || same(&["string", "bytes", "timestamp", "duration", "list"])
+    }
+    Operation::Addition => {
+      numeric
+        || same(&["string", "list", "duration"])
+        || matches!(
+          (left, right),
+          (FirebaseType::Timestamp, FirebaseType::Duration)
+            | (FirebaseType::Duration, FirebaseType::Timestamp)
+        )
+    }
+    Operation::Substraction => {
+      numeric
+        || same(&["timestamp", "duration"])
+        || (left == FirebaseType::Timestamp && right == FirebaseType::Duration)
+    }
+    Operation::Multiplication | Operation::Division | Operation::Modulo => numeric,
+    Operation::And | Operation::Or | Operation::Contains | Operation::Negation => true,
+  }
+}
+
+/// Checks the number of arguments of function calls against the parameters of the called
+/// function, and the argument types of calls to built-in functions against their parameter types
+fn check_function_arguments(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let node = traversal_list.last()?.as_expr_node()?;
+
+  match node.expr() {
+    Expr::FunctionCall(ident, args) => {
+      let (_, func) = find_function_definition(ident.value(), traversal_list)?;
+
+      let param_types = func
+        .parameters()
+        .iter()
+        .map(|param| {
+          param.param_type().map_or(
+            FirebaseTypeInformation::new_undocumented(FirebaseType::Any),
+            |res| res.type_information().clone(),
+          )
+        })
+        .collect::<Vec<FirebaseTypeInformation>>();
+
+      if param_types.len() != args.len() {
+        return Some(vec![wrong_argument_count(
+          ident.value(),
+          node.span(),
+          &[param_types.len()],
+          args.len(),
+        )]);
+      }
+
+      argument_type_mismatch(&param_types, args, traversal_list).map(|diagnostic| vec![diagnostic])
+    }
+    Expr::Member(Some(object), Some(member)) => {
+      let Expr::MemberFunction(ident, args) = member.expr() else {
+        return None;
+      };
+
+      let object_type = object
+        .inferred_type(traversal_list)
+        .filter(|res| !matches!(res, TypeInferenceResult::Definable(_, Err(_))))
+        .map(|res| res.type_information())
+        .filter(|object_type| object_type.firebase_type() != FirebaseType::Any)?;
+
+      // Collection parameters of collection methods expect the element type of the object
+      let specialize = |param_type: &FirebaseTypeInformation| match object_type.element_type() {
+        Some(element_type) if matches!(param_type.type_name().as_str(), "list" | "set") => {
+          param_type
+            .clone()
+            .with_type_arguments(vec![element_type.clone()])
+        }
+        _ => param_type.clone(),
+      };
+
+      let overloads = object_type
+        .firebase_type()
+        .methods()
+        .into_iter()
+        .filter(|(name, _, _)| *name == ident.value())
+        .map(|(_, params, _)| {
+          params
+            .iter()
+            .map(|param| specialize(param.param_type()))
+            .collect::<Vec<FirebaseTypeInformation>>()
+        })
+        .collect::<Vec<Vec<FirebaseTypeInformation>>>();
+
+      if overloads.is_empty() {
+        return None;
+      }
+
+      let matching_arity = overloads
+        .iter()
+        .filter(|param_types| param_types.len() == args.len())
+        .collect::<Vec<_>>();
+
+      if matching_arity.is_empty() {
+        let mut counts = overloads.iter().map(|p| p.len()).collect::<Vec<usize>>();
+        counts.sort();
+        counts.dedup();
+
+        return Some(vec![wrong_argument_count(
+          ident.value(),
+          member.span(),
+          &counts,
+          args.len(),
+        )]);
+      }
+
+      let mut traversal_to_args = traversal_list.clone();
+      traversal_to_args.push(member.to_base_model());
+
+      let mismatches = matching_arity
+        .iter()
+        .map(|param_types| argument_type_mismatch(param_types, args, &traversal_to_args))
+        .collect::<Vec<Option<Diagnostic>>>();
+
+      if mismatches.iter().any(|mismatch| mismatch.is_none()) {
+        return None;
+      }
+
+      mismatches
+        .into_iter()
+        .next()
+        .flatten()
+        .map(|diagnostic| vec![diagnostic])
+    }
+    _ => None,
+  }
+}
+
+fn wrong_argument_count(
+  name: &str,
+  span: (Point, Point),
+  expected: &[usize],
+  found: usize,
+) -> Diagnostic {
+  let expected = expected
+    .iter()
+    .map(|count| count.to_string())
+    .collect::<Vec<String>>()
+    .join(" or ");
+
+  Diagnostic {
+    range: Range {
+      start: to_position(span.0),
+      end: to_position(span.1),
+    },
+    severity: Some(DiagnosticSeverity::ERROR),
+    code: lint_code(WRONG_ARGUMENT_COUNT),
+    code_description: None,
+    source: None,
+    message: format!(
+      "Function `{}` expects {} argument(s) but {} were given",
+      name, expected, found
+    ),
+    related_information: None,
+    tags: None,
+    data: None,
+  }
+}
+
+/// A diagnostic on the first argument whose inferred type contradicts the parameter type
+fn argument_type_mismatch(
+  param_types: &[FirebaseTypeInformation],
+  args: &[ExprNode],
+  traversal_to_args: &Vec<Base<'_>>,
+) -> Option<Diagnostic> {
+  param_types
+    .iter()
+    .zip(args.iter())
+    .find_map(|(param_type, arg)| {
+      let arg_type = match arg.inferred_type(traversal_to_args)? {
+        TypeInferenceResult::Definable(_, Err(_)) => return None,
+        res => res.type_information(),
+      };
+
+      if param_type.accepts(arg_type) {
+        return None;
+      }
+
+      Some(Diagnostic {
+        range: Range {
+          start: to_position(arg.span().0),
+          end: to_position(arg.span().1),
+        },
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: lint_code(ARGUMENT_TYPE_MISMATCH),
+        code_description: None,
+        source: None,
+        message: format!(
+          "Expected `{}` but found `{}`",
+          param_type.type_name(),
+          arg_type.type_name()
+        ),
+        related_information: None,
+        tags: None,
+        data: None,
+      })
+    })
+}
+
+/// Warns on fields that are not declared in the document schema of the accessed map
+fn fields_declared_in_schema(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let node = traversal_list.last()?.as_expr_node()?;
+
+  let Expr::Member(Some(object), Some(member)) = node.expr() else {
+    return None;
+  };
+
+  let Expr::MemberVariable(ident) = member.expr() else {
+    return None;
+  };
+
+  let object_type = object.inferred_type(traversal_list)?.type_information();
+
+  let is_declared = object_type.field(ident.value()).is_some()
+    || object_type
+      .firebase_type()
+      .properties()
+      .iter()
+      .any(|(name, _)| *name == ident.value());
+
+  if !object_type.is_closed() || is_declared {
+    return None;
+  }
+
+  Some(vec![unknown_field(
+    ident.value(),
+    member.span(),
+    object_type,
+  )])
+}
+
+fn unknown_field(
+  name: &str,
+  span: (Point, Point),
+  data_type: &FirebaseTypeInformation,
+) -> Diagnostic {
+  let suggestion = data_type
+    .fields()
+    .iter()
+    .map(|(field, _)| (edit_distance(field, name), field))
+    .filter(|(distance, _)| *distance <= 2)
+    .min_by_key(|(distance, _)| *distance)
+    .map_or(String::new(), |(_, field)| {
+      format!(", did you mean `{}`?", field)
+    });
+
+  Diagnostic {
+    range: Range {
+      start: to_position(span.0),
+      end: to_position(span.1),
+    },
+    severity: Some(DiagnosticSeverity::WARNING),
+    code: lint_code(UNKNOWN_FIELD),
+    code_description: None,
+    source: None,
+    message: format!(
+      "Field `{}` is not declared in the document schema{}",
+      name, suggestion
+    ),
+    related_information: None,
+    tags: None,
+    data: None,
+  }
+}
+
+/// Warns on write rules of matches with a document schema that do not whitelist the fields of
+/// `request.resource.data`, whitelist fields missing in the schema or never check the types of
+/// required fields with `is`
+fn written_data_validated(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let Some(Base::Match(m)) = traversal_list.last() else {
+    return None;
+  };
+
+  let data_type = document_data_type(traversal_list)?;
+
+  if data_type.fields().is_empty() {
+    return None;
+  }
+
+  let body = m.body()?;
+  let mut traversal = traversal_list.clone();
+  traversal.push(Base::MatchBody(body));
+
+  let mut diagnostics: Vec<Diagnostic> = vec![];
+
+  for rule in body.rules() {
+    let writes = rule
+      .methods()
+      .iter()
+      .filter(|method| {
+        matches!(
+          method.method_type(),
+          MethodType::Write | MethodType::Create | MethodType::Update
+        )
+      })
+      .collect::<Vec<&Method>>();
+
+    let (Some(first), Some(last), Some(condition)) =
+      (writes.first(), writes.last(), rule.condition())
+    else {
+      continue;
+    };
+
+    let range = Range {
+      start: to_position(first.span().0),
+      end: to_position(last.span().1),
+    };
+
+    let methods = writes
+      .iter()
+      .map(|method| method_name(method.method_type()))
+      .collect::<Vec<&str>>()
+      .join(", ");
+
+    let validation = validate_incoming_data(condition, &traversal);
+
+    if !validation.constrains_keys() {
+      diagnostics.push(Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: lint_code(UNCONSTRAINED_FIELDS),
+        code_description: None,
+        source: None,
+        message: format!(
+          "Rule allows {} without constraining the fields of `request.resource.data`, e.g. with `request.resource.data.keys().hasOnly([...])`",
+          methods
+        ),
+        related_information: None,
+        tags: None,
+        data: None,
+      });
+    }
+
+    let unchecked = data_type
+      .required_fields()
+      .iter()
+      .filter(|field| validation.may_change(field) && !validation.checks_type_of(field))
+      .map(|field| format!("`{}`", field))
+      .collect::<Vec<String>>();
+
+    if !unchecked.is_empty() {
+      let message = match unchecked.as_slice() {
+        [field] => format!(
+          "Required field {} is never type checked with `is` when allowing {}",
+          field, methods
+        ),
+        fields => format!(
+          "Required fields {} are never type checked with `is` when allowing {}",
+          fields.join(", "),
+          methods
+        ),
+      };
+
+      diagnostics.push(Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: lint_code(UNCHECKED_FIELD_TYPE),
+        code_description: None,
+        source: None,
+        message,
+        related_information: None,
+        tags: None,
+        data: None,
+      });
+    }
+
+    // Lists in functions shared by several rules are only reported once
+    for (field, node) in validation.listed_fields() {
+      let range = to_range(node.span());
+
+      let reported = diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.range == range);
+
+      if data_type.field(field).is_none() && !reported {
+        diagnostics.push(unknown_field(field, node.span(), data_type));
+      }
+    }
+  }
+
+  Some(diagnostics)
+}
+
+/// The number of single character edits needed to turn one word into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<char>>();
+  let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+
+  for (i, a_char) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a_char != *b_char);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+
+    previous = current;
+  }
+
+  previous[b.len()]
+}
+
+/// Warns on match blocks whose rules can access more documents per request than allowed
+fn document_accesses_within_limits(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let Some(Base::Match(m)) = traversal_list.last() else {
+    return None;
+  };
+
+  let traversal_to_match = traversal_list[..traversal_list.len() - 1].to_vec();
+  let accesses = worst_case_document_accesses(&traversal_to_match, m);
+
+  let exceeding = |writes: bool, more_than: usize, up_to: usize| {
+    accesses
+      .iter()
+      .filter(|(method, count)| {
+        MethodType::Write.covers(method) == writes && *count > more_than && *count <= up_to
+      })
+      .map(|(method, count)| format!("{} up to {}", method_name(method), count))
+      .collect::<Vec<String>>()
+  };
+
+  let limits = [
+    (
+      exceeding(false, MAX_DOCUMENT_ACCESSES, usize::MAX),
+      format!("the limit of {} per request", MAX_DOCUMENT_ACCESSES),
+    ),
+    // Writes between the two limits only fail outside of batched writes and transactions
+    (
+      exceeding(true, MAX_DOCUMENT_ACCESSES, MAX_BATCHED_DOCUMENT_ACCESSES),
+      format!(
+        "the limit of {} per single document write, only batched writes and transactions allow {}",
+        MAX_DOCUMENT_ACCESSES, MAX_BATCHED_DOCUMENT_ACCESSES
+      ),
+    ),
+    (
+      exceeding(true, MAX_BATCHED_DOCUMENT_ACCESSES, usize::MAX),
+      format!(
+        "the limit of {} per batched write or transaction",
+        MAX_BATCHED_DOCUMENT_ACCESSES
+      ),
+    ),
+  ];
+
+  let messages = limits
+    .into_iter()
+    .filter(|(methods, _)| !methods.is_empty())
+    .map(|(methods, limit)| {
+      format!(
+        "Rules can access more documents than {} ({})",
+        limit,
+        methods.join(", ")
+      )
+    });
+
+  let span = m.path()?.span();
+
+  let diagnostics = messages
+    .map(|message| Diagnostic {
+      range: Range {
+        start: to_position(span.0),
+        end: to_position(span.1),
+      },
+      severity: Some(DiagnosticSeverity::WARNING),
+      code: lint_code(TOO_MANY_DOCUMENT_ACCESSES),
+      code_description: None,
+      source: None,
+      message,
+      related_information: None,
+      tags: None,
+      data: None,
+    })
+    .collect::<Vec<Diagnostic>>();
+
+  Some(diagnostics)
+}
+
+/// Before version 2, recursive wildcards may only end the full path of a match, which
+/// also excludes collection group patterns like `/{path=**}/posts/{post}`
+fn recursive_wildcards_need_version_2(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let Some(Base::RulesTree(tree)) = traversal_list.first() else {
+    return None;
+  };
+
+  let Some(Base::Match(m)) = traversal_list.last() else {
+    return None;
+  };
+
+  if tree.rules_version() != RulesVersion::V1 {
+    return None;
+  }
+
+  let parts = m.path()?.path_parts();
+  let has_nested_matches = m.body().is_some_and(|body| !body.matches().is_empty());
+
+  let diagnostics = parts
+    .iter()
+    .enumerate()
+    .filter(|(_, part)| *part.pathpart_type() == MatchPathPartType::MultiPath)
+    .filter_map(|(i, part)| {
+      let message = if i + 1 < parts.len() {
+        "Recursive wildcards in the middle of a path require rules_version = '2'"
+      } else if has_nested_matches {
+        "Recursive wildcards followed by nested matches require rules_version = '2'"
+      } else {
+        return None;
+      };
+
+      Some(Diagnostic {
+        range: Range {
+          start: to_position(part.span().0),
+          end: to_position(part.span().1),
+        },
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: lint_code(RULES_VERSION_2_REQUIRED),
+        code_description: None,
+        source: None,
+        message: message.to_owned(),
+        related_information: None,
+        tags: None,
+        data: None,
+      })
+    })
+    .collect::<Vec<Diagnostic>>();
+
+  Some(diagnostics)
+}
+
+/// Rules without a condition or with one that is true regardless of the request,
+/// e.g. `if true` or `if !false || request.auth != null`
+fn find_always_true_rules(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
+  let Some(Base::Rule(rule)) = traversal_list.last() else {
+    return None;
+  };
+
+  let span = match rule.condition() {
+    Some(condition) => match fold_constant(condition) {
+      Some(Value::Boolean(true)) => condition.span(),
+      _ => return None,
+    },
+    None => rule.span(),
+  };
+
+  let grants_write = rule.methods().iter().any(|method| {
+    matches!(
+      method.method_type(),
+      MethodType::Write | MethodType::Create | MethodType::Update | MethodType::Delete
+    )
+  });
+
+  // A recursive wildcard opens up every document below the match
+  let recursive = traversal_list.iter().any(|el| match el {
+    Base::Match(m) => m.path().is_some_and(|path| {
+      path
+        .path_parts()
+        .iter()
+        .any(|part| *part.pathpart_type() == MatchPathPartType::MultiPath)
+    }),
+    _ => false,
+  });
+
+  let methods = rule
+    .methods()
+    .iter()
+    .map(|m| method_name(m.method_type()))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  let message = match recursive {
+    true => format!(
+      "Rule always allows {} on every document matched by the recursive wildcard",
+      methods
+    ),
+    false => format!("Rule always allows {}", methods),
+  };
+
+  let severity = match grants_write || recursive {
+    true => DiagnosticSeverity::ERROR,
+    false => DiagnosticSeverity::WARNING,
+  };
+
+  Some(vec![Diagnostic {
+    range: Range {
+      start: to_position(span.0),
+      end: to_position(span.1),
+    },
+    severity: Some(severity),
+    code: lint_code(ALWAYS_TRUE_RULE),
+    code_description: None,
+    source: None,
+    message,
+    related_information: None,
+    tags: None,
+    data: None,
+  }])
+}
+
 fn find_missing_definitions(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagnostic>> {
   let typable = try_see_if_typable(traversal_list);
 
@@ -449,25 +1185,52 @@ fn find_missing_definitions(traversal_list: &Vec<Base<'_>>) -> Option<Vec<Diagno
   }
 
   if let TypeInferenceResult::Definable(_, Err(str)) = inference_result {
+    let (code, data) = match last.as_expr_node().map(|node| node.expr()) {
+      Some(Expr::FunctionCall(ident, _) | Expr::Variable(ident))
+        if is_defining_itself(ident, traversal_list) =>
+      {
+        (CIRCULAR_DEFINITION, None)
+      }
+      Some(Expr::FunctionCall(ident, _)) => {
+        (UNDEFINED_FUNCTION, Some(json!({ "name": ident.value() })))
+      }
+      Some(Expr::Variable(ident)) => (UNDEFINED_VARIABLE, Some(json!({ "name": ident.value() }))),
+      // The error spread up from an unresolved identifier, which is reported on its own
+      _ => return None,
+    };
+
     return Some(vec![Diagnostic {
       range: Range {
         start: to_position(last.span().0),
         end: to_position(last.span().1),
       },
       severity: Some(DiagnosticSeverity::ERROR),
-      code: None,
+      code: lint_code(code),
       code_description: None,
       source: None,
       message: str.to_owned(),
       related_information: None,
       tags: None,
-      data: None,
+      data,
     }]);
   }
 
   None
 }
 
+/// Whether the identifier is used within the function or variable definition of the same name
+fn is_defining_itself(ident: &Identifier, traversal_list: &Vec<Base<'_>>) -> bool {
+  traversal_list
+    .iter()
+    .rev()
+    .find_map(|el| match el {
+      Base::VariableDefinition(def) => Some(def.name() == ident.value()),
+      Base::Function(func) => Some(func.name().map_or("", |name| name.value()) == ident.value()),
+      _ => None,
+    })
+    .unwrap_or(false)
+}
+
 pub fn build_diagnostics(tree: &Tree, firestore_tree: &RulesTree) -> Vec<Diagnostic> {
   let mut diagnostics: Vec<Diagnostic> = vec![];
 
@@ -479,3 +1242,54 @@ pub fn build_diagnostics(tree: &Tree, firestore_tree: &RulesTree) -> Vec<Diagnos
 
   diagnostics
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulator::parse_rules;
+
+  fn lint_codes(source: &str) -> Vec<String> {
+    diagnose_linting_errors(&parse_rules(source))
+      .into_iter()
+      .filter_map(|diagnostic| match diagnostic.code {
+        Some(NumberOrString::String(code)) => Some(code),
+        _ => None,
+      })
+      .collect()
+  }
+
+  #[test]
+  fn member_of_undefined_call_is_reported_once() {
+    let codes = lint_codes(
+      "service cloud.firestore {
+        match /databases/{database}/documents {
+          match /posts/{id} {
+            allow read: if nope(1).data.a == 1;
+          }
+        }
+      }",
+    );
+
+    assert_eq!(codes, vec![UNDEFINED_FUNCTION]);
+  }
+
+  #[test]
+  fn self_referencing_variable_is_circular() {
+    let codes = lint_codes(
+      "service cloud.firestore {
+        match /databases/{database}/documents {
+          function isOwner() {
+            let owner = owner.id;
+            return owner == request.auth.uid;
+          }
+
+          match /posts/{id} {
+            allow read: if isOwner();
+          }
+        }
+      }",
+    );
+
+    assert_eq!(codes, vec![CIRCULAR_DEFINITION]);
+  }
+}
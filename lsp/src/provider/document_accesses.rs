@@ -0,0 +1,197 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::analysis::*;
+use crate::parser::base::*;
+
+/// Document accesses allowed per single document or query request
+pub const MAX_DOCUMENT_ACCESSES: usize = 10;
+
+/// Document accesses allowed per batched write or transaction
+pub const MAX_BATCHED_DOCUMENT_ACCESSES: usize = 20;
+
+/// Maximum depth of nested function calls, both followed here and allowed by the simulator
+pub const MAX_CALL_DEPTH: usize = 20;
+
+const DOCUMENT_ACCESS_FUNCTIONS: [&str; 4] = ["get", "exists", "getAfter", "existsAfter"];
+
+const CONCRETE_METHODS: [MethodType; 5] = [
+  MethodType::Get,
+  MethodType::List,
+  MethodType::Create,
+  MethodType::Update,
+  MethodType::Delete,
+];
+
+/// The worst case number of distinct documents the rules of the match can access for each
+/// method, assuming every condition is evaluated completely. Calls are followed through
+/// user defined functions and identical paths only count once, like they do at evaluation.
+pub fn worst_case_document_accesses<'a>(
+  traversal_to_match: &[Base<'a>],
+  m: &'a Match,
+) -> Vec<(MethodType, usize)> {
+  let Some(body) = m.body() else {
+    return vec![];
+  };
+
+  let mut traversal = traversal_to_match.to_vec();
+  traversal.push(Base::MatchBody(body));
+
+  let rule_accesses = body
+    .rules()
+    .iter()
+    .map(|rule| {
+      let mut paths = BTreeSet::new();
+
+      if let Some(condition) = rule.condition() {
+        collect_accesses(condition, &traversal, &HashMap::new(), 0, &mut paths);
+      }
+
+      (rule, paths)
+    })
+    .collect::<Vec<_>>();
+
+  CONCRETE_METHODS
+    .iter()
+    .map(|method| {
+      let paths = rule_accesses
+        .iter()
+        .filter(|(rule, _)| {
+          rule
+            .methods()
+            .iter()
+            .any(|m| m.method_type().covers(method))
+        })
+        .flat_map(|(_, paths)| paths.iter())
+        .collect::<BTreeSet<&String>>();
+
+      (*method, paths.len())
+    })
+    .collect()
+}
+
+/// Collects the paths of all document accesses in the expression. The bindings map
+/// parameters and `let` variables of the functions called along the way to the text of
+/// their value, so paths built from them can be compared.
+fn collect_accesses<'a>(
+  node: &'a ExprNode,
+  traversal: &Vec<Base<'a>>,
+  bindings: &HashMap<String, String>,
+  depth: usize,
+  paths: &mut BTreeSet<String>,
+) {
+  if let Expr::FunctionCall(ident, args) = node.expr() {
+    let is_global = get_scoped_functions(traversal)
+      .into_iter()
+      .find(|(name, _)| name == ident.value())
+      .map(|(_, is_global)| is_global);
+
+    match is_global {
+      Some(true) if DOCUMENT_ACCESS_FUNCTIONS.contains(&ident.value()) => {
+        if let Some(path) = args.first() {
+          paths.insert(canonical_text(path, bindings));
+        }
+      }
+      Some(false) if depth < MAX_CALL_DEPTH => {
+        if let Some((scope_index, func)) = find_function_definition(ident.value(), traversal) {
+          let arguments = func
+            .parameters()
+            .iter()
+            .zip(args.iter())
+            .map(|(param, arg)| (param.name().to_owned(), canonical_text(arg, bindings)))
+            .collect::<HashMap<String, String>>();
+
+          let traversal_to_scope = traversal[..=scope_index].to_vec();
+          collect_function_accesses(func, &traversal_to_scope, arguments, depth + 1, paths);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  for child in node.children() {
+    if let Some(child) = child.to_base_model().as_expr_node() {
+      collect_accesses(child, traversal, bindings, depth, paths);
+    }
+  }
+}
+
+fn collect_function_accesses<'a>(
+  func: &'a Function,
+  traversal_to_scope: &Vec<Base<'a>>,
+  mut bindings: HashMap<String, String>,
+  depth: usize,
+  paths: &mut BTreeSet<String>,
+) {
+  let Some(body) = func.body() else {
+    return;
+  };
+
+  for def in body.variable_defs() {
+    if let Some(expr) = def.definition() {
+      collect_accesses(expr, traversal_to_scope, &bindings, depth, paths);
+
+      let value = canonical_text(expr, &bindings);
+      bindings.insert(def.name().to_owned(), value);
+    }
+  }
+
+  if let Some(ret) = body.ret() {
+    collect_accesses(ret, traversal_to_scope, &bindings, depth, paths);
+  }
+}
+
+/// A normalized text of the expression with bound variables replaced by their values
+pub fn canonical_text(node: &ExprNode, bindings: &HashMap<String, String>) -> String {
+  let text = |operand: &Option<Box<ExprNode>>| {
+    operand
+      .as_deref()
+      .map_or(String::new(), |operand| canonical_text(operand, bindings))
+  };
+
+  let list = |nodes: &[ExprNode]| {
+    nodes
+      .iter()
+      .map(|node| canonical_text(node, bindings))
+      .collect::<Vec<String>>()
+      .join(", ")
+  };
+
+  match node.expr() {
+    Expr::Literal(literal) => literal.value().to_owned(),
+    Expr::Variable(ident) => bindings
+      .get(ident.value())
+      .cloned()
+      .unwrap_or(ident.value().to_owned()),
+    Expr::Path(segments) => segments
+      .iter()
+      .map(|segment| match segment.expr() {
+        Expr::Literal(literal) => format!("/{}", literal.value()),
+        _ => format!("/$({})", canonical_text(segment, bindings)),
+      })
+      .collect(),
+    Expr::Unary(operation, operand) => format!("{:?}({})", operation, text(operand)),
+    Expr::Binary(operation, left, right) => {
+      format!("({} {:?} {})", text(left), operation, text(right))
+    }
+    Expr::Ternary(condition, on_true, on_false) => format!(
+      "({} ? {} : {})",
+      text(condition),
+      text(on_true),
+      text(on_false)
+    ),
+    Expr::Member(object, member) => format!("{}.{}", text(object), text(member)),
+    Expr::MemberObject(inner) | Expr::ExprGroup(inner) => text(inner),
+    Expr::MemberVariable(ident) => ident.value().to_owned(),
+    Expr::MemberFunction(ident, args) | Expr::FunctionCall(ident, args) => {
+      format!("{}({})", ident.value(), list(args))
+    }
+    Expr::Indexing(object, index) => format!("{}[{}]", text(object), text(index)),
+    Expr::List(elements) => format!("[{}]", list(elements)),
+    Expr::Map(entries) => format!("{{{}}}", list(entries)),
+    Expr::MapEntry(key, value) => format!("{}: {}", text(key), text(value)),
+    Expr::TypeComparison(operand, compared_type) => {
+      format!("({} is {:?})", text(operand), compared_type)
+    }
+    Expr::Range(from, to) => format!("{}:{}", text(from), text(to)),
+  }
+}
@@ -0,0 +1,101 @@
+use lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::{Node, Point, Tree};
+
+use super::analysis::*;
+use crate::parser::base::*;
+
+/// Foldable bodies, multi-line list and map literals and blocks of consecutive line comments
+pub fn get_folding_ranges(tree: &Tree, rules_tree: &RulesTree, source: &str) -> Vec<FoldingRange> {
+  let spans = bfs_execute_at(rules_tree, &vec![], &vec![foldable_spans]);
+
+  let mut ranges = spans
+    .into_iter()
+    .filter_map(|(start, end)| {
+      // Keep the closing bracket visible when it is on a line of its own
+      let closing_line = source.split('\n').nth(end.row).unwrap_or("").trim_start();
+      let end_line = match closing_line.starts_with(['}', ']']) {
+        true => end.row.saturating_sub(1),
+        false => end.row,
+      };
+
+      if end_line <= start.row {
+        return None;
+      }
+
+      Some(FoldingRange {
+        start_line: start.row as u32,
+        start_character: None,
+        end_line: end_line as u32,
+        end_character: None,
+        kind: None,
+        collapsed_text: None,
+      })
+    })
+    .collect::<Vec<FoldingRange>>();
+
+  ranges.append(&mut comment_blocks(tree));
+
+  ranges.sort_by_key(|range| (range.start_line, range.end_line));
+  ranges.dedup_by_key(|range| (range.start_line, range.end_line));
+
+  ranges
+}
+
+fn foldable_spans<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<(Point, Point)>> {
+  let span = match traversing_path.last()? {
+    Base::ServiceBody(body) => body.span(),
+    Base::MatchBody(body) => body.span(),
+    Base::FunctionBody(body) => body.span(),
+    Base::ExprNode(node) => match node.expr() {
+      Expr::List(_) | Expr::Map(_) => node.span(),
+      _ => return None,
+    },
+    _ => return None,
+  };
+
+  Some(vec![span])
+}
+
+/// Groups comments on consecutive lines into one range each
+fn comment_blocks(tree: &Tree) -> Vec<FoldingRange> {
+  let mut comment_rows = vec![];
+  collect_comment_rows(&tree.root_node(), &mut comment_rows);
+
+  comment_rows.sort();
+  comment_rows.dedup();
+
+  let mut blocks: Vec<(usize, usize)> = vec![];
+
+  for row in comment_rows {
+    match blocks.last_mut() {
+      Some((_, end)) if *end + 1 == row => *end = row,
+      _ => blocks.push((row, row)),
+    }
+  }
+
+  blocks
+    .into_iter()
+    .filter(|(start, end)| end > start)
+    .map(|(start, end)| FoldingRange {
+      start_line: start as u32,
+      start_character: None,
+      end_line: end as u32,
+      end_character: None,
+      kind: Some(FoldingRangeKind::Comment),
+      collapsed_text: None,
+    })
+    .collect()
+}
+
+fn collect_comment_rows(node: &Node<'_>, rows: &mut Vec<usize>) {
+  if node.kind() == "comment" {
+    rows.push(node.start_position().row);
+    return;
+  }
+
+  let mut cursor = node.walk();
+
+  for child in node.children(&mut cursor) {
+    collect_comment_rows(&child, rows);
+  }
+}
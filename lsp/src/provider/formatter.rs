@@ -0,0 +1,772 @@
+use lsp_types::{FormattingOptions, FormattingProperty, Position, Range, TextEdit};
+use tree_sitter::{Node, Point, Tree};
+
+const DEFAULT_MAX_LINE_LENGTH: usize = 100;
+
+/// Node kinds that are written on their own line inside a block
+const STATEMENT_KINDS: [&str; 7] = [
+  "rules_version_def",
+  "function_def",
+  "match_def",
+  "rule_def",
+  "variable_def",
+  "fun_return",
+  "service_type",
+];
+
+/// Node kinds that open a new indentation level
+const BLOCK_KINDS: [&str; 3] = ["service_body", "match_body", "function_body"];
+
+#[derive(Debug, Clone)]
+pub struct FormatterOptions {
+  indent_width: usize,
+  insert_spaces: bool,
+  max_line_length: usize,
+}
+
+impl FormatterOptions {
+  pub fn new(indent_width: usize, insert_spaces: bool, max_line_length: usize) -> Self {
+    Self {
+      indent_width,
+      insert_spaces,
+      max_line_length,
+    }
+  }
+}
+
+impl Default for FormatterOptions {
+  fn default() -> Self {
+    Self::new(2, true, DEFAULT_MAX_LINE_LENGTH)
+  }
+}
+
+impl From<&FormattingOptions> for FormatterOptions {
+  fn from(options: &FormattingOptions) -> Self {
+    let max_line_length = match options.properties.get("maxLineLength") {
+      Some(FormattingProperty::Number(length)) if *length > 0 => *length as usize,
+      _ => DEFAULT_MAX_LINE_LENGTH,
+    };
+
+    Self::new(
+      options.tab_size as usize,
+      options.insert_spaces,
+      max_line_length,
+    )
+  }
+}
+
+/// Pretty prints the whole document. Returns `None` when the document
+/// has syntax errors, as these can not be formatted without losing content.
+pub fn format_document(tree: &Tree, source: &str, options: &FormatterOptions) -> Option<String> {
+  let root = tree.root_node();
+
+  if root.has_error() {
+    return None;
+  }
+
+  let formatter = Formatter::new(source, options);
+
+  let mut formatted = formatter.source_file(root);
+  formatted.push('\n');
+
+  Some(formatted)
+}
+
+/// Formatting edits replacing the whole document
+pub fn format_document_edits(
+  tree: &Tree,
+  source: &str,
+  options: &FormatterOptions,
+) -> Option<Vec<TextEdit>> {
+  let formatted = format_document(tree, source, options)?;
+
+  if formatted == source {
+    return Some(vec![]);
+  }
+
+  Some(vec![TextEdit {
+    range: Range {
+      start: Position::new(0, 0),
+      end: end_of_document(source),
+    },
+    new_text: formatted,
+  }])
+}
+
+/// Formatting edits for the statements on the lines of the given range. The statements are
+/// formatted exactly as they would be when formatting the whole document. Returns `None` when
+/// the statements can not be formatted without changing text outside of the range.
+pub fn format_range_edits(
+  tree: &Tree,
+  source: &str,
+  range: Range,
+  options: &FormatterOptions,
+) -> Option<Vec<TextEdit>> {
+  let root = tree.root_node();
+
+  if root.has_error() {
+    return None;
+  }
+
+  let formatter = Formatter::new(source, options);
+
+  let start_row = range.start.line as usize;
+  let end_row = range.end.line as usize;
+
+  let mut block = root;
+  let mut depth = 0;
+
+  // Descend to the innermost block containing the whole range
+  loop {
+    let inner = sanitized_children(block)
+      .into_iter()
+      .flat_map(|child| block_of_statement(child))
+      .find(|inner| inner.start_position().row < start_row && end_row < inner.end_position().row);
+
+    match inner {
+      Some(inner) => {
+        block = inner;
+        depth += 1;
+      }
+      None => break,
+    }
+  }
+
+  // Only statements on the lines of the range are formatted
+  let items: Vec<Node<'_>> = all_children(block)
+    .into_iter()
+    .filter(|child| is_statement(*child) || child.kind() == "comment" || child.kind() == ";")
+    .filter(|child| {
+      let (start, end) = statement_extent(*child);
+      start.row >= start_row && end.row <= end_row
+    })
+    .collect();
+
+  let first = items.first()?;
+  let last = items.last()?;
+
+  let (first_start, _) = statement_extent(*first);
+  let (_, last_end) = statement_extent(*last);
+
+  let first_row = first_start.row;
+  let last_row = last_end.row;
+
+  let lines: Vec<&str> = source.split('\n').collect();
+
+  let owns_lines = lines[first_row][..first_start.column].trim().is_empty()
+    && last.kind() != "comment"
+    && lines[last_row][last_end.column..].trim().is_empty();
+
+  if !owns_lines {
+    return None;
+  }
+
+  let mut formatted = formatter.statements(&items, depth);
+  formatted.truncate(formatted.trim_end().len());
+
+  let original = lines[first_row..=last_row].join("\n");
+
+  if original == formatted {
+    return Some(vec![]);
+  }
+
+  let edit = changed_part(&original, &formatted, first_row);
+
+  // Whole lines are formatted, so the changes may reach past the columns of the range
+  if edit.range.start < range.start || range.end < edit.range.end {
+    return None;
+  }
+
+  Some(vec![edit])
+}
+
+/// The start and end of a statement. At the top level the service declaration spans from the
+/// `service` keyword over its type to the end of its body.
+fn statement_extent(node: Node<'_>) -> (Point, Point) {
+  if node.kind() != "service_type" {
+    return (node.start_position(), node.end_position());
+  }
+
+  let start = node
+    .prev_sibling()
+    .filter(|sibling| sibling.kind() == "service")
+    .unwrap_or(node);
+
+  let end = node
+    .next_sibling()
+    .filter(|sibling| sibling.kind() == "service_body")
+    .unwrap_or(node);
+
+  (start.start_position(), end.end_position())
+}
+
+/// An edit replacing only the part of the original lines that differs from the formatted text
+fn changed_part(original: &str, formatted: &str, first_row: usize) -> TextEdit {
+  let prefix = original
+    .char_indices()
+    .zip(formatted.chars())
+    .find(|((_, a), b)| a != b)
+    .map_or(original.len().min(formatted.len()), |((i, _), _)| i);
+
+  let suffix = original[prefix..]
+    .chars()
+    .rev()
+    .zip(formatted[prefix..].chars().rev())
+    .take_while(|(a, b)| a == b)
+    .map(|(a, _)| a.len_utf8())
+    .sum::<usize>();
+
+  let position_at = |offset: usize| {
+    let before = &original[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    Position::new(
+      (first_row + before.matches('\n').count()) as u32,
+      utf16_len(&before[line_start..]) as u32,
+    )
+  };
+
+  TextEdit {
+    range: Range {
+      start: position_at(prefix),
+      end: position_at(original.len() - suffix),
+    },
+    new_text: formatted[prefix..formatted.len() - suffix].to_owned(),
+  }
+}
+
+fn block_of_statement<'t>(node: Node<'t>) -> Option<Node<'t>> {
+  match node.kind() {
+    "service_body" | "match_body" | "function_body" => Some(node),
+    "function_def" | "match_def" => sanitized_children(node)
+      .into_iter()
+      .find(|child| BLOCK_KINDS.contains(&child.kind())),
+    _ => None,
+  }
+}
+
+fn is_statement(node: Node<'_>) -> bool {
+  STATEMENT_KINDS.contains(&node.kind())
+}
+
+fn all_children<'t>(node: Node<'t>) -> Vec<Node<'t>> {
+  node.children(&mut node.walk()).collect()
+}
+
+fn sanitized_children<'t>(node: Node<'t>) -> Vec<Node<'t>> {
+  node
+    .children(&mut node.walk())
+    .filter(|n| !n.is_extra())
+    .collect()
+}
+
+fn contains_comment(node: Node<'_>) -> bool {
+  node.kind() == "comment" || all_children(node).into_iter().any(contains_comment)
+}
+
+fn utf16_len(line: &str) -> usize {
+  line.chars().map(|c| c.len_utf16()).sum()
+}
+
+fn end_of_document(source: &str) -> Position {
+  let last_line = source.split('\n').next_back().unwrap_or("");
+
+  Position::new(
+    source.matches('\n').count() as u32,
+    utf16_len(last_line) as u32,
+  )
+}
+
+struct Formatter<'s> {
+  source: &'s str,
+  options: &'s FormatterOptions,
+}
+
+impl<'s> Formatter<'s> {
+  fn new(source: &'s str, options: &'s FormatterOptions) -> Self {
+    Self { source, options }
+  }
+
+  fn text(&self, node: Node<'_>) -> &'s str {
+    &self.source[node.start_byte()..node.end_byte()]
+  }
+
+  fn indent(&self, depth: usize) -> String {
+    if self.options.insert_spaces {
+      " ".repeat(self.options.indent_width * depth)
+    } else {
+      "\t".repeat(depth)
+    }
+  }
+
+  fn indent_width(&self, depth: usize) -> usize {
+    self.options.indent_width * depth
+  }
+
+  fn comment(&self, node: Node<'_>) -> String {
+    self.text(node).trim_end().to_owned()
+  }
+
+  fn source_file(&self, node: Node<'_>) -> String {
+    let children = all_children(node);
+
+    // The service declaration spans several children at the top level,
+    // which are folded into a single statement here
+    let mut statements = vec![];
+    let mut iter = children.into_iter().peekable();
+
+    while let Some(child) = iter.next() {
+      match child.kind() {
+        "service" => continue,
+        "service_type" => statements.push(child),
+        "service_body" => continue,
+        _ => statements.push(child),
+      }
+
+      if child.kind() == "rules_version_def" && iter.peek().is_some_and(|n| n.kind() == ";") {
+        statements.push(iter.next().unwrap());
+      }
+    }
+
+    let mut out = self.statements(&statements, 0);
+    out.truncate(out.trim_end().len());
+    out
+  }
+
+  /// Writes block items on their own lines, keeping semicolons and trailing comments on the
+  /// line of the item they follow and preserving single blank lines between items
+  fn statements(&self, items: &[Node<'_>], depth: usize) -> String {
+    let mut out = String::new();
+    let mut prev_end_row: Option<usize> = None;
+    let mut prev_kind = "";
+
+    for item in items {
+      match item.kind() {
+        ";" => {
+          out.truncate(out.trim_end_matches('\n').len());
+          out.push_str(";\n");
+          continue;
+        }
+        "comment" if prev_end_row == Some(item.start_position().row) => {
+          out.truncate(out.trim_end_matches('\n').len());
+          out.push(' ');
+          out.push_str(&self.comment(*item));
+          out.push('\n');
+          prev_end_row = Some(item.end_position().row.saturating_sub(1));
+          continue;
+        }
+        _ => {}
+      }
+
+      if let Some(prev_end) = prev_end_row {
+        let blank_line_in_source = item.start_position().row > prev_end + 1;
+
+        if blank_line_in_source || prev_kind == "rules_version_def" {
+          out.push('\n');
+        }
+      }
+
+      out.push_str(&self.indent(depth));
+
+      if item.kind() == "comment" {
+        out.push_str(&self.comment(*item));
+        // Comments include their line break
+        prev_end_row = Some(item.end_position().row.saturating_sub(1));
+      } else {
+        out.push_str(&self.statement(*item, depth));
+        prev_end_row = Some(item.end_position().row);
+      }
+
+      out.push('\n');
+      prev_kind = item.kind();
+    }
+
+    out
+  }
+
+  fn statement(&self, node: Node<'_>, depth: usize) -> String {
+    match node.kind() {
+      "rules_version_def" => self.rules_version_def(node),
+      "service_type" => self.service(node, depth),
+      "function_def" => self.function_def(node, depth),
+      "match_def" => self.match_def(node, depth),
+      "rule_def" => self.rule_def(node, depth),
+      "variable_def" => self.keyword_expr_statement(node, depth, "let"),
+      "fun_return" => self.keyword_expr_statement(node, depth, "return"),
+      _ => self.text(node).trim().to_owned(),
+    }
+  }
+
+  fn rules_version_def(&self, node: Node<'_>) -> String {
+    match sanitized_children(node)
+      .into_iter()
+      .find(|child| child.kind() == "string")
+    {
+      Some(version) => format!("rules_version = {}", self.text(version)),
+      None => self.text(node).to_owned(),
+    }
+  }
+
+  fn service(&self, service_type: Node<'_>, depth: usize) -> String {
+    let body = service_type
+      .next_sibling()
+      .filter(|sibling| sibling.kind() == "service_body");
+
+    match body {
+      Some(body) => format!(
+        "service {} {}",
+        self.text(service_type),
+        self.block(body, depth)
+      ),
+      None => format!("service {}", self.text(service_type)),
+    }
+  }
+
+  fn block(&self, node: Node<'_>, depth: usize) -> String {
+    let items: Vec<Node<'_>> = all_children(node)
+      .into_iter()
+      .filter(|child| child.kind() != "{" && child.kind() != "}")
+      .collect();
+
+    if items.is_empty() {
+      return "{}".to_owned();
+    }
+
+    let mut items_with_header_comment = vec![];
+    let mut out = "{".to_owned();
+
+    for item in items {
+      // A comment directly after the opening brace stays on the brace line
+      if items_with_header_comment.is_empty()
+        && item.kind() == "comment"
+        && item.start_position().row == node.start_position().row
+      {
+        out.push(' ');
+        out.push_str(&self.comment(item));
+        continue;
+      }
+
+      items_with_header_comment.push(item);
+    }
+
+    out.push('\n');
+    out.push_str(&self.statements(&items_with_header_comment, depth + 1));
+    out.push_str(&self.indent(depth));
+    out.push('}');
+    out
+  }
+
+  fn header_has_comment(&self, node: Node<'_>) -> bool {
+    all_children(node)
+      .into_iter()
+      .filter(|child| !BLOCK_KINDS.contains(&child.kind()))
+      .any(contains_comment)
+  }
+
+  fn function_def(&self, node: Node<'_>, depth: usize) -> String {
+    if self.header_has_comment(node) {
+      return self.text(node).to_owned();
+    }
+
+    let mut name = "";
+    let mut params = vec![];
+    let mut body = None;
+
+    for child in sanitized_children(node) {
+      match child.kind() {
+        "function_name" => name = self.text(child),
+        "param_list" => {
+          params = sanitized_children(child)
+            .into_iter()
+            .filter(|param| param.kind() == "identifier")
+            .map(|param| self.text(param))
+            .collect()
+        }
+        "function_body" => body = Some(child),
+        _ => continue,
+      }
+    }
+
+    let header = format!("function {}({})", name, params.join(", "));
+
+    match body {
+      Some(body) => format!("{} {}", header, self.block(body, depth)),
+      None => header,
+    }
+  }
+
+  fn match_def(&self, node: Node<'_>, depth: usize) -> String {
+    if self.header_has_comment(node) {
+      return self.text(node).to_owned();
+    }
+
+    let mut path = String::new();
+    let mut body = None;
+
+    for child in sanitized_children(node) {
+      match child.kind() {
+        "match_path" => {
+          path = sanitized_children(child)
+            .into_iter()
+            .map(|seg| self.text(seg))
+            .collect()
+        }
+        "match_body" => body = Some(child),
+        _ => continue,
+      }
+    }
+
+    match body {
+      Some(body) => format!("match {} {}", path, self.block(body, depth)),
+      None => format!("match {}", path),
+    }
+  }
+
+  fn rule_def(&self, node: Node<'_>, depth: usize) -> String {
+    if contains_comment(node) {
+      return self.text(node).to_owned();
+    }
+
+    let children = sanitized_children(node);
+
+    let methods: Vec<&str> = children
+      .iter()
+      .filter(|child| child.kind() == "method")
+      .map(|method| self.text(*method))
+      .collect();
+
+    let mut out = format!("allow {}", methods.join(", "));
+
+    if let Some(condition) = children.iter().find(|child| child.kind() == "expr") {
+      out.push_str(": if ");
+
+      let prefix_width = self.indent_width(depth) + out.len();
+      out.push_str(&self.expr_lines(*condition, depth, prefix_width));
+    }
+
+    out
+  }
+
+  /// `let name = expr` and `return expr`
+  fn keyword_expr_statement(&self, node: Node<'_>, depth: usize, keyword: &str) -> String {
+    if contains_comment(node) {
+      return self.text(node).to_owned();
+    }
+
+    let children = sanitized_children(node);
+
+    let mut out = keyword.to_owned();
+    out.push(' ');
+
+    if let Some(variable) = children.iter().find(|child| child.kind() == "variable") {
+      out.push_str(self.text(*variable));
+      out.push_str(" = ");
+    }
+
+    if let Some(expr) = children.iter().find(|child| child.kind() == "expr") {
+      let prefix_width = self.indent_width(depth) + out.len();
+      out.push_str(&self.expr_lines(*expr, depth, prefix_width));
+    }
+
+    out
+  }
+
+  /// Formats an expression, breaking long `&&` / `||` chains, parenthesized
+  /// chains and ternaries over several lines when they exceed the max line length
+  fn expr_lines(&self, node: Node<'_>, depth: usize, prefix_width: usize) -> String {
+    let flat = self.expr(node);
+
+    // Account for the trailing semicolon
+    if prefix_width + flat.len() < self.options.max_line_length || contains_comment(node) {
+      return flat;
+    }
+
+    let inner = unwrap_expr(node);
+
+    match inner.kind() {
+      "conditional_and" | "conditional_or" => {
+        let operator = if inner.kind() == "conditional_and" {
+          "&&"
+        } else {
+          "||"
+        };
+
+        let operands = flatten_chain(inner, inner.kind());
+        let continuation = self.indent(depth + 1);
+        let continuation_width = self.indent_width(depth + 1) + operator.len() + 1;
+
+        let mut out = self.expr_lines(operands[0], depth, prefix_width);
+
+        for operand in &operands[1..] {
+          out.push('\n');
+          out.push_str(&continuation);
+          out.push_str(operator);
+          out.push(' ');
+          out.push_str(&self.expr_lines(*operand, depth + 1, continuation_width));
+        }
+
+        out
+      }
+      "expr_group" => {
+        let grouped = sanitized_children(inner)
+          .into_iter()
+          .find(|child| child.kind() == "expr");
+
+        match grouped {
+          Some(grouped) if is_breakable(unwrap_expr(grouped)) => format!(
+            "(\n{}{}\n{})",
+            self.indent(depth + 1),
+            self.expr_lines(grouped, depth + 1, self.indent_width(depth + 1)),
+            self.indent(depth)
+          ),
+          _ => flat,
+        }
+      }
+      "ternary" => {
+        let operands: Vec<Node<'_>> = sanitized_children(inner)
+          .into_iter()
+          .filter(|child| child.kind() == "expr")
+          .collect();
+
+        if operands.len() != 3 {
+          return flat;
+        }
+
+        let continuation = self.indent(depth + 1);
+        let continuation_width = self.indent_width(depth + 1) + 2;
+
+        format!(
+          "{}\n{}? {}\n{}: {}",
+          self.expr_lines(operands[0], depth, prefix_width),
+          continuation,
+          self.expr_lines(operands[1], depth + 1, continuation_width),
+          continuation,
+          self.expr_lines(operands[2], depth + 1, continuation_width)
+        )
+      }
+      _ => flat,
+    }
+  }
+
+  /// Formats an expression on a single line
+  fn expr(&self, node: Node<'_>) -> String {
+    if contains_comment(node) {
+      return self.text(node).to_owned();
+    }
+
+    let children = sanitized_children(node);
+
+    match node.kind() {
+      "expr"
+      | "map_key"
+      | "map_value"
+      | "primary"
+      | "member_object"
+      | "function_argument"
+      | "function_calling_name"
+      | "variable" => match children.first() {
+        Some(child) if children.len() == 1 => self.expr(*child),
+        _ => self.text(node).to_owned(),
+      },
+      "ternary" if children.len() == 5 => format!(
+        "{} ? {} : {}",
+        self.expr(children[0]),
+        self.expr(children[2]),
+        self.expr(children[4])
+      ),
+      "conditional_or" | "conditional_and" | "relation" | "addition" | "multiplication"
+        if children.len() == 3 =>
+      {
+        format!(
+          "{} {} {}",
+          self.expr(children[0]),
+          self.text(children[1]),
+          self.expr(children[2])
+        )
+      }
+      "contains" | "type_comparison" if children.len() == 4 => format!(
+        "{} {} {}",
+        self.expr(children[0]),
+        self.text(children[1]),
+        self.expr(children[3])
+      ),
+      "unary" => children
+        .iter()
+        .map(|child| match child.kind() {
+          "!" | "-" => self.text(*child).to_owned(),
+          _ => self.expr(*child),
+        })
+        .collect(),
+      "member" | "member_field" | "indexing" | "field_indexing" | "expr_group" | "path"
+      | "path_segment" => children.iter().map(|child| self.expr(*child)).collect(),
+      "function_call" => {
+        let name = children
+          .first()
+          .map(|name| self.expr(*name))
+          .unwrap_or_default();
+
+        let args: Vec<String> = children
+          .iter()
+          .filter(|child| child.kind() == "function_argument")
+          .map(|arg| self.expr(*arg))
+          .collect();
+
+        format!("{}({})", name, args.join(", "))
+      }
+      "list" => {
+        let elements: Vec<String> = children
+          .iter()
+          .filter(|child| child.kind() == "expr")
+          .map(|el| self.expr(*el))
+          .collect();
+
+        format!("[{}]", elements.join(", "))
+      }
+      "map" => {
+        let entries: Vec<String> = children
+          .iter()
+          .filter(|child| child.kind() == "map_entry")
+          .map(|entry| self.expr(*entry))
+          .collect();
+
+        format!("{{{}}}", entries.join(", "))
+      }
+      "map_entry" if children.len() == 3 => {
+        format!("{}: {}", self.expr(children[0]), self.expr(children[2]))
+      }
+      _ => self.text(node).trim().to_owned(),
+    }
+  }
+}
+
+fn unwrap_expr(node: Node<'_>) -> Node<'_> {
+  let children = sanitized_children(node);
+
+  match node.kind() {
+    "expr" | "primary" if children.len() == 1 => unwrap_expr(children[0]),
+    _ => node,
+  }
+}
+
+fn is_breakable(node: Node<'_>) -> bool {
+  matches!(
+    node.kind(),
+    "conditional_and" | "conditional_or" | "ternary" | "expr_group"
+  )
+}
+
+/// Operands of a chain of the same boolean operator, e.g. `a && b && (c || d)`
+/// results in `a`, `b` and `(c || d)`
+fn flatten_chain<'t>(node: Node<'t>, chain_kind: &str) -> Vec<Node<'t>> {
+  let inner = unwrap_expr(node);
+
+  if inner.kind() != chain_kind {
+    return vec![node];
+  }
+
+  sanitized_children(inner)
+    .into_iter()
+    .filter(|child| child.kind() == "expr")
+    .flat_map(|operand| flatten_chain(operand, chain_kind))
+    .collect()
+}
@@ -0,0 +1,25 @@
+use lsp_types::{DocumentHighlight, DocumentHighlightKind, Position};
+
+use super::analysis::*;
+use crate::parser::base::*;
+
+/// All occurrences of the symbol under the position. Definitions, i.e. function names,
+/// `let` variables, parameters and path wildcards, are written, everything else read.
+pub fn get_document_highlights(position: Position, tree: &RulesTree) -> Vec<DocumentHighlight> {
+  let occurrences = match resolve_symbol(position, tree) {
+    SymbolResolution::Defined(occurrences) => occurrences,
+    _ => return vec![],
+  };
+
+  let mut highlights = vec![DocumentHighlight {
+    range: to_range(occurrences.definition()),
+    kind: Some(DocumentHighlightKind::WRITE),
+  }];
+
+  highlights.extend(occurrences.usages().iter().map(|usage| DocumentHighlight {
+    range: to_range(*usage),
+    kind: Some(DocumentHighlightKind::READ),
+  }));
+
+  highlights
+}
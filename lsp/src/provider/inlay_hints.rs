@@ -0,0 +1,208 @@
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, InlayHintTooltip, Range};
+use serde::Deserialize;
+use tree_sitter::Point;
+
+use super::{analysis::*, document_accesses::worst_case_document_accesses};
+use crate::{
+  parser::{base::*, types::*},
+  simulator::interpreter::method_name,
+};
+
+/// Which kinds of inlay hints are shown, configured through the `inlayHints` initialization option
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintOptions {
+  variable_types: bool,
+  return_types: bool,
+  parameter_names: bool,
+  document_accesses: bool,
+}
+
+impl Default for InlayHintOptions {
+  fn default() -> Self {
+    Self {
+      variable_types: true,
+      return_types: true,
+      parameter_names: false,
+      document_accesses: true,
+    }
+  }
+}
+
+const DOCUMENT_ACCESSES_TOOLTIP: &str = "Worst case number of documents the rules of each \
+  method can access with get(), exists(), getAfter() and existsAfter()";
+
+enum HintCandidate<'a> {
+  VariableType(&'a VariableDefinition, FirebaseTypeInformation),
+  ReturnType(&'a Function, FirebaseTypeInformation),
+  ParameterName(Point, String),
+  DocumentAccesses(&'a Match, Vec<(MethodType, usize)>),
+}
+
+type HintCollector<'a> = fn(&Vec<Base<'a>>) -> Option<Vec<HintCandidate<'a>>>;
+
+pub fn get_inlay_hints<'a>(
+  range: Range,
+  tree: &'a RulesTree,
+  source: &str,
+  options: &InlayHintOptions,
+) -> Vec<InlayHint> {
+  let collectors: Vec<HintCollector<'a>> = vec![
+    variable_types as HintCollector<'a>,
+    return_types as HintCollector<'a>,
+    parameter_names as HintCollector<'a>,
+    document_accesses as HintCollector<'a>,
+  ];
+
+  let candidates = bfs_execute_at(tree, &vec![], &collectors);
+
+  candidates
+    .into_iter()
+    .filter_map(|candidate| match candidate {
+      HintCandidate::VariableType(def, type_information) if options.variable_types => {
+        let name_end = Point::new(def.span().0.row, def.span().0.column + def.name().len());
+
+        Some(type_hint(name_end, type_information))
+      }
+      HintCandidate::ReturnType(func, type_information) if options.return_types => {
+        parameter_list_end(func, source).map(|end| type_hint(end, type_information))
+      }
+      HintCandidate::ParameterName(position, name) if options.parameter_names => Some(InlayHint {
+        position: to_position(position),
+        label: InlayHintLabel::String(format!("{}:", name)),
+        kind: Some(InlayHintKind::PARAMETER),
+        text_edits: None,
+        tooltip: None,
+        padding_left: None,
+        padding_right: Some(true),
+        data: None,
+      }),
+      HintCandidate::DocumentAccesses(m, accesses) if options.document_accesses => {
+        let counts = accesses
+          .iter()
+          .map(|(method, count)| format!("{} {}", method_name(method), count))
+          .collect::<Vec<String>>();
+
+        Some(InlayHint {
+          position: to_position(m.path()?.span().1),
+          label: InlayHintLabel::String(format!("document accesses: {}", counts.join(", "))),
+          kind: None,
+          text_edits: None,
+          tooltip: Some(InlayHintTooltip::String(
+            DOCUMENT_ACCESSES_TOOLTIP.to_owned(),
+          )),
+          padding_left: Some(true),
+          padding_right: None,
+          data: None,
+        })
+      }
+      _ => None,
+    })
+    .filter(|hint| hint.position >= range.start && hint.position <= range.end)
+    .collect()
+}
+
+fn type_hint(position: Point, type_information: FirebaseTypeInformation) -> InlayHint {
+  InlayHint {
+    position: to_position(position),
+    label: InlayHintLabel::String(format!(": {}", type_information.type_name())),
+    kind: Some(InlayHintKind::TYPE),
+    text_edits: None,
+    tooltip: None,
+    padding_left: None,
+    padding_right: None,
+    data: None,
+  }
+}
+
+/// The point after the closing parenthesis of the function's parameter list
+fn parameter_list_end(func: &Function, source: &str) -> Option<Point> {
+  let after = func
+    .parameters()
+    .last()
+    .map(|param| param.span().1)
+    .or(func.name().map(|name| name.span().1))?;
+
+  for (row, line) in source.split('\n').enumerate().skip(after.row) {
+    let from = if row == after.row { after.column } else { 0 };
+
+    if let Some(column) = line.get(from..).and_then(|rest| rest.find(')')) {
+      return Some(Point::new(row, from + column + 1));
+    }
+  }
+
+  None
+}
+
+/// Inferred types are only worth showing if they are more specific than `any`
+fn known_type(inference: Option<&TypeInferenceResult>) -> Option<FirebaseTypeInformation> {
+  inference
+    .map(|res| res.type_information().clone())
+    .filter(|info| info.firebase_type() != FirebaseType::Any)
+}
+
+fn variable_types<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<HintCandidate<'a>>> {
+  let Some(Base::VariableDefinition(def)) = traversing_path.last().cloned() else {
+    return None;
+  };
+
+  let traversal_to_def = traversing_path[..traversing_path.len() - 1].to_vec();
+  let type_information = known_type(def.variable_type(&traversal_to_def))?;
+
+  Some(vec![HintCandidate::VariableType(def, type_information)])
+}
+
+fn return_types<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<HintCandidate<'a>>> {
+  let Some(Base::Function(func)) = traversing_path.last().cloned() else {
+    return None;
+  };
+
+  let traversal_to_scope = traversing_path[..traversing_path.len() - 1].to_vec();
+  let type_information = known_type(func.return_type(&traversal_to_scope))?;
+
+  Some(vec![HintCandidate::ReturnType(func, type_information)])
+}
+
+fn parameter_names<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<HintCandidate<'a>>> {
+  let Expr::FunctionCall(ident, args) = traversing_path.last()?.as_expr_node()?.expr() else {
+    return None;
+  };
+
+  let traversal_to_call = traversing_path[..traversing_path.len() - 1].to_vec();
+  let (scope_index, func) = find_function_definition(ident.value(), &traversal_to_call)?;
+
+  // Built-in functions have a single obvious parameter
+  let traversal_to_scope = traversal_to_call[..=scope_index].to_vec();
+  if let Some(TypeInferenceResult::HiddenDefinition(_)) = func.return_type(&traversal_to_scope) {
+    return None;
+  }
+
+  let hints = func
+    .parameters()
+    .iter()
+    .zip(args.iter())
+    .filter(|(param, arg)| match arg.expr() {
+      Expr::Variable(var) => var.value() != param.name(),
+      _ => true,
+    })
+    .map(|(param, arg)| HintCandidate::ParameterName(arg.span().0, param.name().to_owned()))
+    .collect();
+
+  Some(hints)
+}
+
+/// The worst case document accesses of each method, for matches whose rules access documents
+fn document_accesses<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<HintCandidate<'a>>> {
+  let Some(Base::Match(m)) = traversing_path.last().cloned() else {
+    return None;
+  };
+
+  let traversal_to_match = &traversing_path[..traversing_path.len() - 1];
+  let accesses = worst_case_document_accesses(traversal_to_match, m);
+
+  if accesses.iter().all(|(_, count)| *count == 0) {
+    return None;
+  }
+
+  Some(vec![HintCandidate::DocumentAccesses(m, accesses)])
+}
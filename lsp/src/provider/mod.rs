@@ -1,6 +1,18 @@
 pub mod analysis;
+pub mod call_hierarchy;
+pub mod code_actions;
+pub mod data_validation;
 pub mod diagnoser;
+pub mod document_accesses;
+pub mod folding_ranges;
+pub mod formatter;
+pub mod highlights;
+pub mod inlay_hints;
+pub mod rename;
+pub mod selection_ranges;
+pub mod signature_help;
 pub mod tokenizer;
+pub mod workspace_symbols;
 
 #[allow(deprecated)]
 mod analysis_doc_symbols;
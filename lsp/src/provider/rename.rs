@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use lsp_types::{Position, PrepareRenameResponse, TextEdit, Uri, WorkspaceEdit};
+use tree_sitter::Point;
+
+use super::analysis::*;
+use crate::parser::{base::*, types::*};
+
+pub fn prepare_rename(
+  position: Position,
+  tree: &RulesTree,
+) -> Result<Option<PrepareRenameResponse>, String> {
+  let occurrences = match resolve_symbol(position, tree) {
+    SymbolResolution::Defined(occurrences) => occurrences,
+    SymbolResolution::BuiltIn(name) => {
+      return Err(format!("Built-in `{}` cannot be renamed", name));
+    }
+    SymbolResolution::Unresolved => return Ok(None),
+  };
+
+  let point = to_point(position);
+
+  let hit = occurrences
+    .all_spans()
+    .into_iter()
+    .find(|span| span.contains(point));
+
+  Ok(hit.map(|span| PrepareRenameResponse::RangeWithPlaceholder {
+    range: to_range(span),
+    placeholder: occurrences.name().to_owned(),
+  }))
+}
+
+pub fn rename(
+  tree_file_uri: Uri,
+  position: Position,
+  new_name: &str,
+  tree: &RulesTree,
+) -> Result<Option<WorkspaceEdit>, String> {
+  let occurrences = match resolve_symbol(position, tree) {
+    SymbolResolution::Defined(occurrences) => occurrences,
+    SymbolResolution::BuiltIn(name) => {
+      return Err(format!("Built-in `{}` cannot be renamed", name));
+    }
+    SymbolResolution::Unresolved => return Ok(None),
+  };
+
+  let point = to_point(position);
+
+  // Only occurrences of the symbol can be renamed, like in `prepare_rename`
+  let on_occurrence = occurrences
+    .all_spans()
+    .into_iter()
+    .any(|span| span.contains(point));
+
+  if !on_occurrence {
+    return Ok(None);
+  }
+
+  if !is_valid_identifier(new_name) {
+    return Err(format!("`{}` is not a valid identifier", new_name));
+  }
+
+  if SPECIAL_KEYWORDS.contains(&new_name) {
+    return Err(format!("`{}` is a reserved keyword", new_name));
+  }
+
+  if new_name == occurrences.name() {
+    return Ok(None);
+  }
+
+  let conflicts = find_conflicts(&occurrences, new_name, tree);
+
+  if !conflicts.is_empty() {
+    let lines = conflicts
+      .iter()
+      .map(|span| (span.0.row + 1).to_string())
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    return Err(format!(
+      "Renaming `{}` to `{}` conflicts with an existing definition in scope (line {})",
+      occurrences.name(),
+      new_name,
+      lines
+    ));
+  }
+
+  let edits = occurrences
+    .all_spans()
+    .into_iter()
+    .map(|span| TextEdit {
+      range: to_range(span),
+      new_text: new_name.to_owned(),
+    })
+    .collect();
+
+  Ok(Some(WorkspaceEdit {
+    changes: Some(HashMap::from([(tree_file_uri, edits)])),
+    ..Default::default()
+  }))
+}
+
+/// Every site of the symbol at which the new name is already taken by another
+/// definition visible from that site
+fn find_conflicts(
+  occurrences: &SymbolOccurrences,
+  new_name: &str,
+  tree: &RulesTree,
+) -> Vec<(Point, Point)> {
+  occurrences
+    .all_spans()
+    .into_iter()
+    .filter(|span| {
+      let traversal = get_path_traversal(to_position(span.0), tree);
+
+      match occurrences.kind() {
+        DefinitionKind::Function => get_scoped_functions(&traversal)
+          .iter()
+          .any(|(name, _)| name == new_name),
+        _ => get_scoped_variables(&traversal)
+          .iter()
+          .any(|(name, _, _)| name == new_name),
+      }
+    })
+    .collect()
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+
+  match chars.next() {
+    Some(first) if first == '_' || first.is_ascii_alphabetic() => {
+      chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+    }
+    _ => false,
+  }
+}
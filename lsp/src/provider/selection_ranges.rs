@@ -0,0 +1,37 @@
+use lsp_types::{Position, SelectionRange};
+
+use super::analysis::*;
+use crate::parser::base::*;
+
+/// For each position, the chain of enclosing nodes from the innermost one outwards,
+/// e.g. identifier → member → expression → rule → match
+pub fn get_selection_ranges(positions: &[Position], tree: &RulesTree) -> Vec<SelectionRange> {
+  positions
+    .iter()
+    .map(|position| {
+      let mut selection: Option<SelectionRange> = None;
+
+      for el in get_path_traversal(*position, tree) {
+        let range = to_range(el.span());
+
+        // Nodes spanning the same text as their parent would be a no-op expansion
+        if selection
+          .as_ref()
+          .is_some_and(|parent| parent.range == range)
+        {
+          continue;
+        }
+
+        selection = Some(SelectionRange {
+          range,
+          parent: selection.map(Box::new),
+        });
+      }
+
+      selection.unwrap_or(SelectionRange {
+        range: to_range((to_point(*position), to_point(*position))),
+        parent: None,
+      })
+    })
+    .collect()
+}
@@ -0,0 +1,235 @@
+use lsp_types::{
+  Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, Position,
+  SignatureHelp, SignatureInformation,
+};
+use tree_sitter::Point;
+
+use super::analysis::*;
+use crate::parser::{base::*, types::*};
+
+/// The signatures of the function whose argument list the position is in
+pub fn get_signature_help(
+  position: Position,
+  tree: &RulesTree,
+  source: &str,
+) -> Option<SignatureHelp> {
+  let (name, name_start, active_parameter) = find_open_call(source, to_point(position))?;
+
+  let traversal = get_path_traversal(to_position(name_start), tree);
+
+  let (index, call) = traversal.iter().enumerate().rev().find_map(|(i, el)| {
+    let node = el.as_expr_node()?;
+
+    match node.expr() {
+      Expr::FunctionCall(ident, _) | Expr::MemberFunction(ident, _)
+        if ident.value() == name && ident.span().0 == name_start =>
+      {
+        Some((i, node))
+      }
+      _ => None,
+    }
+  })?;
+
+  let traversal_to_call = traversal[..index].to_vec();
+
+  let signatures = match call.expr() {
+    Expr::FunctionCall(_, _) => function_signature(&name, &traversal_to_call)
+      .into_iter()
+      .collect(),
+    _ => member_function_signatures(&name, &traversal_to_call),
+  };
+
+  if signatures.is_empty() {
+    return None;
+  }
+
+  // Prefer the first overload that takes enough parameters for the active argument
+  let active_signature = signatures
+    .iter()
+    .position(|signature| {
+      signature
+        .parameters
+        .as_ref()
+        .is_some_and(|params| params.len() > active_parameter as usize)
+    })
+    .unwrap_or(0);
+
+  Some(SignatureHelp {
+    signatures,
+    active_signature: Some(active_signature as u32),
+    active_parameter: Some(active_parameter),
+  })
+}
+
+/// Finds the unclosed argument list the point is in by scanning the source backwards.
+/// Returns the function name, its start and the index of the argument at the point.
+fn find_open_call(source: &str, point: Point) -> Option<(String, Point, u32)> {
+  let line_start = source
+    .split('\n')
+    .take(point.row)
+    .map(|line| line.len() + 1)
+    .sum::<usize>();
+
+  let offset = (line_start + point.column).min(source.len());
+  let before = source.get(..offset)?.as_bytes();
+
+  let mut depth = 0;
+  let mut commas = 0;
+  let mut quote: Option<u8> = None;
+  let mut open_paren = None;
+
+  for (i, c) in before.iter().enumerate().rev() {
+    if let Some(q) = quote {
+      if *c == q && (i == 0 || before[i - 1] != b'\\') {
+        quote = None;
+      }
+      continue;
+    }
+
+    match c {
+      b'\'' | b'"' => quote = Some(*c),
+      b')' | b']' | b'}' => depth += 1,
+      b'[' | b'{' if depth == 0 => commas = 0,
+      b'(' if depth == 0 => {
+        open_paren = Some(i);
+        break;
+      }
+      b'(' | b'[' | b'{' => depth -= 1,
+      b',' if depth == 0 => commas += 1,
+      // Argument lists do not span over statements
+      b';' if depth == 0 => return None,
+      _ => {}
+    }
+  }
+
+  let name_end = before[..open_paren?]
+    .iter()
+    .rposition(|c| !c.is_ascii_whitespace())?
+    + 1;
+
+  let name_start = before[..name_end]
+    .iter()
+    .rposition(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+    .map_or(0, |i| i + 1);
+
+  if name_start == name_end {
+    return None;
+  }
+
+  let name = String::from_utf8_lossy(&before[name_start..name_end]).into_owned();
+
+  let row = before[..name_start].iter().filter(|c| **c == b'\n').count();
+  let column = name_start
+    - before[..name_start]
+      .iter()
+      .rposition(|c| *c == b'\n')
+      .map_or(0, |i| i + 1);
+
+  Some((name, Point::new(row, column), commas))
+}
+
+fn function_signature(name: &str, traversal: &Vec<Base<'_>>) -> Option<SignatureInformation> {
+  let (scope_index, func) = find_function_definition(name, traversal)?;
+
+  let traversal_to_scope = traversal[..=scope_index].to_vec();
+
+  let return_type = func
+    .return_type(&traversal_to_scope)
+    .map(|res| res.type_information());
+
+  let params = func
+    .parameters()
+    .iter()
+    .map(|param| {
+      let param_type = param
+        .param_type()
+        .map(|res| res.type_information())
+        .filter(|info| info.firebase_type() != FirebaseType::Any);
+
+      match param_type {
+        Some(info) => format!("{}: {}", param.name(), info.type_name()),
+        None => param.name().to_owned(),
+      }
+    })
+    .collect::<Vec<String>>();
+
+  Some(build_signature(
+    name,
+    &params,
+    return_type,
+    return_type.and_then(|info| info.docstring()),
+  ))
+}
+
+fn member_function_signatures(name: &str, traversal: &Vec<Base<'_>>) -> Vec<SignatureInformation> {
+  let object = traversal
+    .last()
+    .and_then(|el| match el.as_expr_node()?.expr() {
+      Expr::Member(Some(object), _) => Some(object),
+      _ => None,
+    });
+
+  let object_type = object
+    .and_then(|object| object.inferred_type(traversal))
+    .map(|res| res.type_information().firebase_type());
+
+  let Some(object_type) = object_type else {
+    return vec![];
+  };
+
+  object_type
+    .methods()
+    .iter()
+    .filter(|(method_name, _, _)| *method_name == name)
+    .map(|(_, params, return_type)| {
+      let params = params
+        .iter()
+        .map(|param| format!("{}: {}", param.name(), param.param_type().type_name()))
+        .collect::<Vec<String>>();
+
+      build_signature(name, &params, Some(return_type), return_type.docstring())
+    })
+    .collect()
+}
+
+fn build_signature(
+  name: &str,
+  params: &[String],
+  return_type: Option<&FirebaseTypeInformation>,
+  docstring: Option<&str>,
+) -> SignatureInformation {
+  let mut label = format!("{}(", name);
+  let mut parameters = vec![];
+
+  for (i, param) in params.iter().enumerate() {
+    if i > 0 {
+      label.push_str(", ");
+    }
+
+    let start = label.len() as u32;
+    label.push_str(param);
+
+    parameters.push(ParameterInformation {
+      label: ParameterLabel::LabelOffsets([start, label.len() as u32]),
+      documentation: None,
+    });
+  }
+
+  label.push(')');
+
+  if let Some(return_type) = return_type.filter(|info| info.firebase_type() != FirebaseType::Any) {
+    label.push_str(&format!(" -> {}", return_type.type_name()));
+  }
+
+  SignatureInformation {
+    label,
+    documentation: docstring.filter(|doc| !doc.is_empty()).map(|doc| {
+      Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: doc.to_owned(),
+      })
+    }),
+    parameters: Some(parameters),
+    active_parameter: None,
+  }
+}
@@ -1,9 +1,151 @@
-use lsp_types::{SemanticToken, SemanticTokenModifier, SemanticTokenType};
-use tree_sitter::{Node, Tree};
+use std::collections::HashMap;
 
-pub fn tokenize<'a>(tree: &Tree) -> Vec<SemanticToken> {
+use lsp_types::{
+  Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+  SemanticTokensDelta, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+};
+use tree_sitter::{Node, Point, Tree};
+
+use super::analysis::*;
+use crate::parser::{base::*, types::FirebaseTypeTrait};
+
+// Indices of the token types in the legend
+const TYPE_FUNCTION: u32 = 6;
+const TYPE_VARIABLE: u32 = 7;
+const TYPE_PROPERTY: u32 = 8;
+const TYPE_PARAMETER: u32 = 10;
+const TYPE_METHOD: u32 = 11;
+const TYPE_NAMESPACE: u32 = 12;
+
+// Bits of the token modifiers in the legend
+const MODIFIER_DECLARATION: u32 = 1 << 0;
+const MODIFIER_READONLY: u32 = 1 << 1;
+const MODIFIER_DEFAULT_LIBRARY: u32 = 1 << 2;
+const MODIFIER_DEPRECATED: u32 = 1 << 3;
+
+/// Syntax nodes whose token is determined by the symbol they name in the rules tree
+const SYMBOL_NODE_KINDS: [&str; 6] = [
+  "variable",
+  "identifier",
+  "function_name",
+  "function_calling_name",
+  "single_path_seg",
+  "multi_path_seg",
+];
+
+/// The token of a symbol, resolved from the rules tree
+#[derive(Debug, Clone, Copy)]
+struct SymbolToken {
+  span: (Point, Point),
+  token_type: u32,
+  modifiers: u32,
+}
+
+pub fn tokenize(tree: &Tree, rules_tree: &RulesTree) -> Vec<SemanticToken> {
+  let symbols = symbol_tokens(rules_tree);
+
+  encode(&absolute_tokenize(&tree.root_node(), &symbols))
+}
+
+/// The tokens starting within the range, encoded relative to each other like a full result
+pub fn tokenize_range(tree: &Tree, rules_tree: &RulesTree, range: Range) -> Vec<SemanticToken> {
+  let symbols = symbol_tokens(rules_tree);
+  let start = (range.start.line, range.start.character);
+  let end = (range.end.line, range.end.character);
+
+  let tokens = absolute_tokenize(&tree.root_node(), &symbols)
+    .into_iter()
+    .filter(|token| start <= (token.line, token.col) && (token.line, token.col) < end)
+    .collect::<Vec<AbsoluteToken>>();
+
+  encode(&tokens)
+}
+
+/// The last tokens sent for each document, so delta requests can be answered with the edits
+/// turning them into the current ones
+#[derive(Debug, Default)]
+pub struct SentTokens {
+  results: HashMap<String, SemanticTokens>,
+  last_result_id: u64,
+}
+
+impl SentTokens {
+  /// Remembers the tokens as the latest result of the document
+  pub fn remember(&mut self, uri: &str, data: Vec<SemanticToken>) -> SemanticTokens {
+    self.last_result_id += 1;
+
+    let result = SemanticTokens {
+      result_id: Some(self.last_result_id.to_string()),
+      data,
+    };
+
+    self.results.insert(uri.to_owned(), result.clone());
+    result
+  }
+
+  /// The edits from the previous result to the tokens, or all tokens if the previous result
+  /// is not the last one sent for the document
+  pub fn delta(
+    &mut self,
+    uri: &str,
+    previous_result_id: &str,
+    data: Vec<SemanticToken>,
+  ) -> SemanticTokensFullDeltaResult {
+    let previous = self
+      .results
+      .get(uri)
+      .filter(|previous| previous.result_id.as_deref() == Some(previous_result_id))
+      .map(|previous| previous.data.clone());
+
+    let result = self.remember(uri, data);
+
+    match previous {
+      Some(previous) => SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+        result_id: result.result_id,
+        edits: token_edits(&previous, &result.data),
+      }),
+      None => SemanticTokensFullDeltaResult::Tokens(result),
+    }
+  }
+
+  /// Drops the result of a closed document, its tokens are requested in full when reopened
+  pub fn forget(&mut self, uri: &str) {
+    self.results.remove(uri);
+  }
+}
+
+/// A single edit replacing the tokens between the common start and end of both results.
+/// Positions are counted in integers of the encoded data, five for each token.
+fn token_edits(previous: &[SemanticToken], current: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+  let common_start = previous
+    .iter()
+    .zip(current)
+    .take_while(|(previous, current)| previous == current)
+    .count();
+
+  let common_end = previous[common_start..]
+    .iter()
+    .rev()
+    .zip(current[common_start..].iter().rev())
+    .take_while(|(previous, current)| previous == current)
+    .count();
+
+  let deleted = previous.len() - common_start - common_end;
+  let inserted = &current[common_start..current.len() - common_end];
+
+  if deleted == 0 && inserted.is_empty() {
+    return vec![];
+  }
+
+  vec![SemanticTokensEdit {
+    start: (common_start * 5) as u32,
+    delete_count: (deleted * 5) as u32,
+    data: Some(inserted.to_vec()),
+  }]
+}
+
+fn encode(absolute_tokenization: &[AbsoluteToken]) -> Vec<SemanticToken> {
   let mut semantic_tokens = vec![];
-  let absolute_tokenization = absolute_tokenize(&tree.root_node());
 
   if absolute_tokenization.len() != 0 {
     let first = absolute_tokenization.first().unwrap();
@@ -29,14 +171,17 @@ pub fn tokenize<'a>(tree: &Tree) -> Vec<SemanticToken> {
   semantic_tokens
 }
 
-fn absolute_tokenize<'a>(node: &Node<'a>) -> Vec<AbsoluteToken> {
+fn absolute_tokenize<'a>(
+  node: &Node<'a>,
+  symbols: &HashMap<Point, SymbolToken>,
+) -> Vec<AbsoluteToken> {
   let mut level_cursor = node.walk();
   let mut absolute_tokens: Vec<AbsoluteToken> = vec![];
 
   loop {
     let curr_node = level_cursor.node();
 
-    let curr_tokenization = build_absolute_token(&curr_node);
+    let curr_tokenization = build_absolute_token(&curr_node, symbols);
 
     if curr_tokenization.is_none() {
       let mut curr_node_cursor = curr_node.walk();
@@ -44,7 +189,7 @@ fn absolute_tokenize<'a>(node: &Node<'a>) -> Vec<AbsoluteToken> {
 
       if curr_node_children.len() != 0 {
         for child in curr_node_children {
-          let mut child_tokenizations = absolute_tokenize(&child);
+          let mut child_tokenizations = absolute_tokenize(&child, symbols);
           absolute_tokens.append(&mut child_tokenizations);
         }
       }
@@ -87,7 +232,26 @@ impl AbsoluteToken {
   }
 }
 
-fn build_absolute_token<'a>(node: &Node<'a>) -> Option<AbsoluteToken> {
+fn build_absolute_token<'a>(
+  node: &Node<'a>,
+  symbols: &HashMap<Point, SymbolToken>,
+) -> Option<AbsoluteToken> {
+  let symbol = symbols
+    .get(&node.start_position())
+    .filter(|_| SYMBOL_NODE_KINDS.contains(&node.kind()));
+
+  if let Some(symbol) = symbol {
+    let (start, end) = symbol.span;
+
+    return Some(AbsoluteToken {
+      col: start.column.try_into().unwrap(),
+      line: start.row.try_into().unwrap(),
+      length: (end.column - start.column).try_into().unwrap(),
+      token_type: symbol.token_type,
+      token_modifiers_bitset: symbol.modifiers,
+    });
+  }
+
   let token_type = get_semantic_type(
     node.kind(),
     node.parent().and_then(|p| Some(p.kind())).unwrap_or(""),
@@ -110,24 +274,152 @@ fn build_absolute_token<'a>(node: &Node<'a>) -> Option<AbsoluteToken> {
   })
 }
 
+/// The tokens of all symbols of the rules tree keyed by the start of their syntax node. User
+/// defined symbols are told apart by the kind of their definition, built-in ones are marked
+/// as part of the default library.
+fn symbol_tokens(rules_tree: &RulesTree) -> HashMap<Point, SymbolToken> {
+  let definitions = bfs_execute_at(rules_tree, &vec![], &vec![symbol_definitions]);
+  let usages = bfs_execute_at(rules_tree, &vec![], &vec![resolved_identifier_usages]);
+  let built_ins = bfs_execute_at(rules_tree, &vec![], &vec![built_in_symbols]);
+
+  let kinds = definitions
+    .iter()
+    .map(|(_, kind, definition_key, _)| (*definition_key, kind))
+    .collect::<HashMap<(Point, Point), &DefinitionKind>>();
+
+  let mut symbols = built_ins
+    .into_iter()
+    .map(|symbol| (symbol.span.0, symbol))
+    .collect::<HashMap<Point, SymbolToken>>();
+
+  for (ident, definition_key) in usages {
+    if let Some(kind) = kinds.get(&definition_key) {
+      let (token_type, modifiers) = definition_token(kind);
+
+      symbols.insert(
+        ident.span().0,
+        SymbolToken {
+          span: ident.span(),
+          token_type,
+          modifiers,
+        },
+      );
+    }
+  }
+
+  for (_, kind, definition_key, name_span) in &definitions {
+    let (token_type, modifiers) = definition_token(kind);
+
+    // Path wildcards are keyed by their whole `/{name}` segment
+    let node_start = match kind {
+      DefinitionKind::PathWildcard => definition_key.0,
+      _ => name_span.0,
+    };
+
+    symbols.insert(
+      node_start,
+      SymbolToken {
+        span: *name_span,
+        token_type,
+        modifiers: modifiers | MODIFIER_DECLARATION,
+      },
+    );
+  }
+
+  symbols
+}
+
+fn definition_token(kind: &DefinitionKind) -> (u32, u32) {
+  match kind {
+    DefinitionKind::Function => (TYPE_FUNCTION, 0),
+    DefinitionKind::Variable => (TYPE_VARIABLE, 0),
+    DefinitionKind::Parameter => (TYPE_PARAMETER, 0),
+    DefinitionKind::PathWildcard => (TYPE_VARIABLE, MODIFIER_READONLY),
+  }
+}
+
+/// Built-in variables, namespaces and functions, methods and properties of built-in types
+fn built_in_symbols<'a>(traversing_path: &Vec<Base<'a>>) -> Option<Vec<SymbolToken>> {
+  let node = traversing_path.last()?.as_expr_node()?;
+  let traversal_to_node = traversing_path[..traversing_path.len() - 1].to_vec();
+
+  let (ident, token_type, modifiers) = match node.expr() {
+    Expr::Variable(ident) | Expr::FunctionCall(ident, _) => {
+      let Some(TypeInferenceResult::HiddenDefinition(type_information)) =
+        node.inferred_type(&traversal_to_node)
+      else {
+        return None;
+      };
+
+      match node.expr() {
+        Expr::FunctionCall(_, _) => (ident, TYPE_FUNCTION, MODIFIER_DEFAULT_LIBRARY),
+        _ if type_information.firebase_type().type_name() == "namespace" => {
+          (ident, TYPE_NAMESPACE, MODIFIER_DEFAULT_LIBRARY)
+        }
+        _ => (
+          ident,
+          TYPE_VARIABLE,
+          MODIFIER_DEFAULT_LIBRARY | MODIFIER_READONLY,
+        ),
+      }
+    }
+    Expr::Member(Some(object), Some(member)) => match member.expr() {
+      Expr::MemberFunction(ident, _) => (ident, TYPE_METHOD, MODIFIER_DEFAULT_LIBRARY),
+      Expr::MemberVariable(ident) => {
+        let object_type = object.inferred_type(traversing_path)?.type_information();
+
+        let property = object_type
+          .firebase_type()
+          .properties()
+          .into_iter()
+          .find(|(name, _)| *name == ident.value());
+
+        let modifiers = match property {
+          Some((_, property)) if property.is_deprecated() => {
+            MODIFIER_DEFAULT_LIBRARY | MODIFIER_READONLY | MODIFIER_DEPRECATED
+          }
+          Some(_) => MODIFIER_DEFAULT_LIBRARY | MODIFIER_READONLY,
+          None => 0,
+        };
+
+        (ident, TYPE_PROPERTY, modifiers)
+      }
+      _ => return None,
+    },
+    _ => return None,
+  };
+
+  Some(vec![SymbolToken {
+    span: ident.span(),
+    token_type,
+    modifiers,
+  }])
+}
+
 pub fn get_used_semantic_token_types() -> Vec<SemanticTokenType> {
   vec![
-    SemanticTokenType::COMMENT,  //0
-    SemanticTokenType::NUMBER,   //1
-    SemanticTokenType::STRING,   //2
-    SemanticTokenType::VARIABLE, //3
-    SemanticTokenType::OPERATOR, //4
-    SemanticTokenType::KEYWORD,  //5
-    SemanticTokenType::FUNCTION, //6
-    SemanticTokenType::VARIABLE, //7
-    SemanticTokenType::PROPERTY, //8
-    SemanticTokenType::TYPE,     //9
+    SemanticTokenType::COMMENT,   //0
+    SemanticTokenType::NUMBER,    //1
+    SemanticTokenType::STRING,    //2
+    SemanticTokenType::VARIABLE,  //3
+    SemanticTokenType::OPERATOR,  //4
+    SemanticTokenType::KEYWORD,   //5
+    SemanticTokenType::FUNCTION,  //6
+    SemanticTokenType::VARIABLE,  //7
+    SemanticTokenType::PROPERTY,  //8
+    SemanticTokenType::TYPE,      //9
+    SemanticTokenType::PARAMETER, //10
+    SemanticTokenType::METHOD,    //11
+    SemanticTokenType::NAMESPACE, //12
   ]
 }
 
 pub fn get_used_semantic_token_modifiers() -> Vec<SemanticTokenModifier> {
   vec![
-    SemanticTokenModifier::DECLARATION, //0
+    SemanticTokenModifier::DECLARATION,     //0
+    SemanticTokenModifier::READONLY,        //1
+    SemanticTokenModifier::DEFAULT_LIBRARY, //2
+    SemanticTokenModifier::DEPRECATED,      //3
   ]
 }
 
@@ -160,11 +452,10 @@ fn get_semantic_type(type_str: &str, parent_type: &str) -> Option<(u32, Option<u
   }
 
   let modifier = match type_str {
-    "function" => Some(1),
-    "function_name" => Some(1),
+    "function" => Some(MODIFIER_DECLARATION),
+    "function_name" => Some(MODIFIER_DECLARATION),
     _ => None,
   };
 
-  //TODO Implement modifiers
   Some((token_type.unwrap(), modifier))
 }
@@ -0,0 +1,149 @@
+use lsp_types::{Location, OneOf, Range, SymbolKind, Uri, WorkspaceSymbol};
+
+use crate::parser::base::{Function, Match, RulesTree};
+
+/// The functions and match paths of a rules file, with the match they are nested in
+/// as container name
+pub fn collect_workspace_symbols(tree: &RulesTree, uri: &Uri) -> Vec<WorkspaceSymbol> {
+  let mut symbols = vec![];
+
+  for func in tree.functions() {
+    symbols.extend(function_symbol(func, None, uri));
+  }
+
+  if let Some(body) = tree.service_body() {
+    for func in body.functions() {
+      symbols.extend(function_symbol(func, None, uri));
+    }
+
+    for m in body.matches() {
+      collect_match_symbols(m, None, uri, &mut symbols);
+    }
+  }
+
+  symbols
+}
+
+fn collect_match_symbols(
+  m: &Match,
+  container: Option<&str>,
+  uri: &Uri,
+  symbols: &mut Vec<WorkspaceSymbol>,
+) {
+  let Some(path) = m.path() else {
+    return;
+  };
+
+  let name: String = path.into();
+
+  symbols.push(WorkspaceSymbol {
+    name: name.clone(),
+    kind: SymbolKind::CLASS,
+    tags: None,
+    container_name: container.map(|c| c.to_owned()),
+    location: OneOf::Left(Location {
+      uri: uri.clone(),
+      range: path.into(),
+    }),
+    data: None,
+  });
+
+  if let Some(body) = m.body() {
+    for func in body.functions() {
+      symbols.extend(function_symbol(func, Some(&name), uri));
+    }
+
+    for nested in body.matches() {
+      collect_match_symbols(nested, Some(&name), uri, symbols);
+    }
+  }
+}
+
+fn function_symbol(func: &Function, container: Option<&str>, uri: &Uri) -> Option<WorkspaceSymbol> {
+  let name = func.name()?;
+  let range: Range = name.into();
+
+  Some(WorkspaceSymbol {
+    name: name.value().to_owned(),
+    kind: SymbolKind::FUNCTION,
+    tags: None,
+    container_name: container.map(|c| c.to_owned()),
+    location: OneOf::Left(Location {
+      uri: uri.clone(),
+      range,
+    }),
+    data: None,
+  })
+}
+
+/// Keeps the symbols whose name fuzzily matches the query, best matches first
+pub fn filter_workspace_symbols(
+  query: &str,
+  symbols: Vec<WorkspaceSymbol>,
+) -> Vec<WorkspaceSymbol> {
+  let mut scored = symbols
+    .into_iter()
+    .filter_map(|symbol| fuzzy_score(query, &symbol.name).map(|score| (score, symbol)))
+    .collect::<Vec<(i64, WorkspaceSymbol)>>();
+
+  scored.sort_by(|(a_score, a), (b_score, b)| {
+    b_score
+      .cmp(a_score)
+      .then_with(|| a.name.len().cmp(&b.name.len()))
+      .then_with(|| a.name.cmp(&b.name))
+  });
+
+  scored.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+/// Scores the query as a case insensitive subsequence of the candidate. Consecutive
+/// characters and characters at the start of a word or path segment score higher,
+/// skipped characters lower. `None` if the query is not a subsequence at all.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+  let query = query
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .flat_map(|c| c.to_lowercase())
+    .collect::<Vec<char>>();
+
+  if query.is_empty() {
+    return Some(0);
+  }
+
+  let candidate = candidate.chars().collect::<Vec<char>>();
+
+  let mut score = 0;
+  let mut query_index = 0;
+  let mut previous_match: Option<usize> = None;
+
+  for (i, c) in candidate.iter().enumerate() {
+    if query_index == query.len() {
+      break;
+    }
+
+    if !c.to_lowercase().eq(query[query_index].to_lowercase()) {
+      continue;
+    }
+
+    let word_start = i == 0
+      || matches!(candidate[i - 1], '/' | '{' | '_' | '-' | '.')
+      || (c.is_uppercase() && candidate[i - 1].is_lowercase());
+
+    score += 1;
+
+    if word_start {
+      score += 8;
+    }
+
+    match previous_match {
+      Some(previous) if previous + 1 == i => score += 5,
+      Some(previous) => score -= (i - previous - 1).min(5) as i64,
+      None => score -= i.min(5) as i64,
+    }
+
+    previous_match = Some(i);
+    query_index += 1;
+  }
+
+  (query_index == query.len()).then_some(score)
+}
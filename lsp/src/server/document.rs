@@ -0,0 +1,171 @@
+use std::rc::Rc;
+
+use lsp_types::{Position, TextDocumentContentChangeEvent};
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+use crate::parser::{
+  base::RulesTree,
+  evaluation::{PreviousEvaluation, evaluate_edited_tree, evaluate_tree},
+  schema::DocumentSchemas,
+};
+
+/// An open text document together with its syntax tree and the evaluated rules tree.
+/// The source text is kept around so incremental changes can be applied in place.
+#[derive(Debug, Clone)]
+pub struct LSPDocument {
+  text: String,
+  tree: Tree,
+  rules_tree: RulesTree,
+  document_schemas: Option<Rc<DocumentSchemas>>,
+}
+
+impl LSPDocument {
+  pub fn new(
+    text: String,
+    parser: &mut Parser,
+    document_schemas: Option<Rc<DocumentSchemas>>,
+  ) -> Option<Self> {
+    let tree = parser.parse(&text, None)?;
+    let mut rules_tree = evaluate_tree(tree.clone(), text.as_bytes());
+    rules_tree.set_document_schemas(document_schemas.clone());
+
+    Some(Self {
+      text,
+      tree,
+      rules_tree,
+      document_schemas,
+    })
+  }
+
+  /// Replaces the document schemas. The rules tree is evaluated again, as the inferred
+  /// types of the document data depend on them.
+  pub fn set_document_schemas(&mut self, document_schemas: Option<Rc<DocumentSchemas>>) {
+    self.rules_tree = evaluate_tree(self.tree.clone(), self.text.as_bytes());
+    self
+      .rules_tree
+      .set_document_schemas(document_schemas.clone());
+    self.document_schemas = document_schemas;
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  pub fn tree(&self) -> &Tree {
+    &self.tree
+  }
+
+  pub fn rules_tree(&self) -> &RulesTree {
+    &self.rules_tree
+  }
+
+  /// Applies the content changes in the order they were sent by the client.
+  /// Ranged changes are mirrored onto the old syntax tree so that the reparse
+  /// can reuse every subtree the edits did not touch. Only functions and matches
+  /// whose text changed or moved are evaluated again. If the reparse fails, the
+  /// changes are rolled back so the text always matches the trees.
+  pub fn apply_changes(&mut self, changes: &[TextDocumentContentChangeEvent], parser: &mut Parser) {
+    if changes.is_empty() {
+      return;
+    }
+
+    let old_text = self.text.clone();
+    let mut old_tree = Some(self.tree.clone());
+
+    for change in changes {
+      match change.range {
+        Some(range) => {
+          let edit = self.replace_range(range.start, range.end, &change.text);
+
+          if let Some(tree) = old_tree.as_mut() {
+            tree.edit(&edit);
+          }
+        }
+        None => {
+          self.text = change.text.clone();
+          old_tree = None;
+        }
+      }
+    }
+
+    let Some(tree) = parser.parse(&self.text, old_tree.as_ref()) else {
+      self.text = old_text;
+      return;
+    };
+
+    let previous = PreviousEvaluation::new(
+      &mut self.rules_tree,
+      old_text.as_bytes(),
+      self.text.as_bytes(),
+    );
+
+    let rules_tree = evaluate_edited_tree(tree.clone(), self.text.as_bytes(), Some(previous));
+
+    self.rules_tree = rules_tree;
+    self
+      .rules_tree
+      .set_document_schemas(self.document_schemas.clone());
+    self.tree = tree;
+  }
+
+  fn replace_range(&mut self, start: Position, end: Position, new_text: &str) -> InputEdit {
+    let start_byte = position_to_byte(&self.text, start);
+    let old_end_byte = position_to_byte(&self.text, end).max(start_byte);
+
+    let start_position = byte_to_point(&self.text, start_byte);
+    let old_end_position = byte_to_point(&self.text, old_end_byte);
+
+    self.text.replace_range(start_byte..old_end_byte, new_text);
+
+    let new_end_byte = start_byte + new_text.len();
+    let new_end_position = byte_to_point(&self.text, new_end_byte);
+
+    InputEdit {
+      start_byte,
+      old_end_byte,
+      new_end_byte,
+      start_position,
+      old_end_position,
+      new_end_position,
+    }
+  }
+}
+
+/// Converts an LSP position (UTF-16 based) into a byte offset of the text.
+/// Positions past the end of a line or of the document are clamped.
+pub fn position_to_byte(text: &str, position: Position) -> usize {
+  let mut line_start = 0;
+
+  for _ in 0..position.line {
+    match text[line_start..].find('\n') {
+      Some(offset) => line_start += offset + 1,
+      None => return text.len(),
+    }
+  }
+
+  let line_end = text[line_start..]
+    .find('\n')
+    .map_or(text.len(), |offset| line_start + offset);
+
+  let mut utf16_units = 0;
+
+  for (offset, c) in text[line_start..line_end].char_indices() {
+    if utf16_units >= position.character as usize {
+      return line_start + offset;
+    }
+
+    utf16_units += c.len_utf16();
+  }
+
+  line_end
+}
+
+/// Converts a byte offset of the text into a tree sitter point (row, byte column)
+pub fn byte_to_point(text: &str, byte: usize) -> Point {
+  let before = &text[..byte];
+
+  match before.rfind('\n') {
+    Some(newline) => Point::new(before.matches('\n').count(), byte - newline - 1),
+    None => Point::new(0, byte),
+  }
+}
@@ -1 +1,4 @@
+pub mod document;
 pub mod server;
+pub mod settings;
+pub mod workspace;
@@ -1,24 +1,44 @@
-use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
+use lsp_server::{
+  Connection, ErrorCode, ExtractError, Message, Notification, Request, RequestId, Response,
+};
 use lsp_types::notification::*;
 use lsp_types::*;
 use request::*;
 
-use std::{collections::HashMap, error::Error};
-use tree_sitter::{Parser, Tree};
+use std::{collections::HashMap, error::Error, path::PathBuf, rc::Rc, str::FromStr};
+use tree_sitter::Parser;
 
+use super::{
+  document::LSPDocument,
+  settings::ServerSettings,
+  workspace::{WorkspaceIndex, uri_to_path},
+};
 use crate::{
   StartUpType,
   parser::{
     base::{RulesTree, TypeInferenceResult},
-    evaluation::evaluate_tree,
+    schema::{DocumentSchemas, schemas_depend_on},
   },
   provider::{
     analysis::{
       generate_document_symbols, get_hover_result, get_path_traversal, get_possible_completions,
       get_references, to_position, try_see_if_typable,
     },
+    call_hierarchy::{incoming_calls, outgoing_calls, prepare_call_hierarchy},
+    code_actions::get_code_actions,
     diagnoser::build_diagnostics,
-    tokenizer::{get_used_semantic_token_modifiers, get_used_semantic_token_types, tokenize},
+    folding_ranges::get_folding_ranges,
+    formatter::{FormatterOptions, format_document_edits, format_range_edits},
+    highlights::get_document_highlights,
+    inlay_hints::get_inlay_hints,
+    rename::{prepare_rename, rename},
+    selection_ranges::get_selection_ranges,
+    signature_help::get_signature_help,
+    tokenizer::{
+      SentTokens, get_used_semantic_token_modifiers, get_used_semantic_token_types, tokenize,
+      tokenize_range,
+    },
+    workspace_symbols::filter_workspace_symbols,
   },
 };
 
@@ -33,9 +53,18 @@ pub fn start_server(startup_type: StartUpType, mut parser: Parser) -> Result<(),
   };
 
   let server_capabilities = serde_json::to_value(&ServerCapabilities {
-    text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+    text_document_sync: Some(TextDocumentSyncCapability::Options(
+      TextDocumentSyncOptions {
+        open_close: Some(true),
+        change: Some(TextDocumentSyncKind::INCREMENTAL),
+        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+        ..Default::default()
+      },
+    )),
     hover_provider: Some(HoverProviderCapability::Simple(true)),
     references_provider: Some(OneOf::Left(true)),
+    document_highlight_provider: Some(OneOf::Left(true)),
+    call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
     definition_provider: Some(OneOf::Left(true)),
     completion_provider: Some(CompletionOptions {
       trigger_characters: Some(vec![".".to_owned()]),
@@ -51,30 +80,148 @@ pub fn start_server(startup_type: StartUpType, mut parser: Parser) -> Result<(),
           token_types: get_used_semantic_token_types(),
           token_modifiers: get_used_semantic_token_modifiers(),
         },
-        range: None,
-        full: Some(SemanticTokensFullOptions::Bool(true)),
+        range: Some(true),
+        full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
       },
     )),
     document_symbol_provider: Some(OneOf::Left(true)),
+    rename_provider: Some(OneOf::Right(RenameOptions {
+      prepare_provider: Some(true),
+      work_done_progress_options: WorkDoneProgressOptions {
+        work_done_progress: Some(false),
+      },
+    })),
+    document_formatting_provider: Some(OneOf::Left(true)),
+    document_range_formatting_provider: Some(OneOf::Left(true)),
+    signature_help_provider: Some(SignatureHelpOptions {
+      trigger_characters: Some(vec!["(".to_owned(), ",".to_owned()]),
+      retrigger_characters: None,
+      work_done_progress_options: WorkDoneProgressOptions {
+        work_done_progress: Some(false),
+      },
+    }),
+    inlay_hint_provider: Some(OneOf::Left(true)),
+    workspace_symbol_provider: Some(OneOf::Left(true)),
+    workspace: Some(WorkspaceServerCapabilities {
+      workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+        supported: Some(true),
+        change_notifications: Some(OneOf::Left(true)),
+      }),
+      file_operations: None,
+    }),
+    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+    selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+    code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+      code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+      work_done_progress_options: WorkDoneProgressOptions {
+        work_done_progress: Some(false),
+      },
+      resolve_provider: Some(false),
+    })),
     ..Default::default()
   })
   .unwrap();
 
-  let _ = match connection.initialize(server_capabilities) {
+  let initialize_params = match connection.initialize(server_capabilities) {
     Ok(it) => it,
     Err(err) => return Err(Box::new(err)),
   };
 
-  main_loop(connection, &mut parser)?;
+  let initialize_params = serde_json::from_value::<InitializeParams>(initialize_params).ok();
+
+  let settings = ServerSettings::from_initialization_options(
+    initialize_params
+      .as_ref()
+      .and_then(|params| params.initialization_options.clone()),
+  );
+
+  let mut workspace_index = WorkspaceIndex::new(
+    initialize_params
+      .as_ref()
+      .map(workspace_folders)
+      .unwrap_or_default(),
+    &mut parser,
+  );
+
+  if initialize_params
+    .as_ref()
+    .is_some_and(supports_watched_files_registration)
+  {
+    register_file_watchers(&connection);
+  }
+
+  main_loop(connection, &mut parser, &settings, &mut workspace_index)?;
   io_threads.join()?;
 
   Ok(())
 }
 
-type LSPTreeStorage<'a> = HashMap<String, (RulesTree, Tree)>;
+/// The workspace folders of the client, falling back to the root for clients without
+/// workspace folder support
+#[allow(deprecated)]
+fn workspace_folders(params: &InitializeParams) -> Vec<PathBuf> {
+  match &params.workspace_folders {
+    Some(folders) => folders
+      .iter()
+      .filter_map(|folder| uri_to_path(&folder.uri))
+      .collect(),
+    None => params.root_uri.iter().filter_map(uri_to_path).collect(),
+  }
+}
+
+/// Whether the client lets the server register the files it wants to be notified about
+fn supports_watched_files_registration(params: &InitializeParams) -> bool {
+  params
+    .capabilities
+    .workspace
+    .as_ref()
+    .and_then(|workspace| workspace.did_change_watched_files)
+    .and_then(|watched_files| watched_files.dynamic_registration)
+    .unwrap_or(false)
+}
+
+/// Rules files for the workspace index, and the document schema configs together with the
+/// JSON Schema and TypeScript files they can refer to
+const WATCHED_FILES: [&str; 3] = ["**/*.rules", "**/*.json", "**/*.ts"];
+
+/// Asks the client to notify the server about changes of rules files and document schemas
+/// on disk, so the workspace index also covers files that are not open in the editor and
+/// open documents are checked against the current schemas
+fn register_file_watchers(connection: &Connection) {
+  let watchers = WATCHED_FILES
+    .iter()
+    .map(|glob| FileSystemWatcher {
+      glob_pattern: GlobPattern::String(glob.to_string()),
+      kind: None,
+    })
+    .collect();
+
+  let registration = Registration {
+    id: "watched-files".to_owned(),
+    method: "workspace/didChangeWatchedFiles".to_owned(),
+    register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions { watchers })
+      .ok(),
+  };
+
+  let _ = connection.sender.try_send(Message::Request(Request::new(
+    RequestId::from("register-watched-files".to_owned()),
+    "client/registerCapability".to_owned(),
+    RegistrationParams {
+      registrations: vec![registration],
+    },
+  )));
+}
+
+type LSPTreeStorage<'a> = HashMap<String, LSPDocument>;
 
-fn main_loop<'a>(connection: Connection, parser: &mut Parser) -> Result<(), Box<dyn Error>> {
+fn main_loop<'a>(
+  connection: Connection,
+  parser: &mut Parser,
+  settings: &ServerSettings,
+  workspace_index: &mut WorkspaceIndex,
+) -> Result<(), Box<dyn Error>> {
   let mut evaulated_trees: LSPTreeStorage<'a> = HashMap::new();
+  let mut sent_tokens = SentTokens::default();
 
   for msg in &connection.receiver {
     match msg {
@@ -103,7 +250,29 @@ fn main_loop<'a>(connection: Connection, parser: &mut Parser) -> Result<(), Box<
         }
 
         if let Ok(tokenize_r) = cast_req::<SemanticTokensFullRequest>(&req) {
-          handle_tokenize_request(tokenize_r, &evaulated_trees, req, &connection);
+          handle_tokenize_request(
+            tokenize_r,
+            &evaulated_trees,
+            &mut sent_tokens,
+            req,
+            &connection,
+          );
+          continue;
+        }
+
+        if let Ok(tokenize_delta_r) = cast_req::<SemanticTokensFullDeltaRequest>(&req) {
+          handle_tokenize_delta_request(
+            tokenize_delta_r,
+            &evaulated_trees,
+            &mut sent_tokens,
+            req,
+            &connection,
+          );
+          continue;
+        }
+
+        if let Ok(tokenize_range_r) = cast_req::<SemanticTokensRangeRequest>(&req) {
+          handle_tokenize_range_request(tokenize_range_r, &evaulated_trees, req, &connection);
           continue;
         }
 
@@ -112,21 +281,138 @@ fn main_loop<'a>(connection: Connection, parser: &mut Parser) -> Result<(), Box<
           continue;
         }
 
+        if let Ok(highlight_r) = cast_req::<DocumentHighlightRequest>(&req) {
+          handle_document_highlight_request(highlight_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(prepare_call_hierarchy_r) = cast_req::<CallHierarchyPrepare>(&req) {
+          handle_prepare_call_hierarchy_request(
+            prepare_call_hierarchy_r,
+            &evaulated_trees,
+            req,
+            &connection,
+          );
+          continue;
+        }
+
+        if let Ok(incoming_calls_r) = cast_req::<CallHierarchyIncomingCalls>(&req) {
+          handle_incoming_calls_request(incoming_calls_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(outgoing_calls_r) = cast_req::<CallHierarchyOutgoingCalls>(&req) {
+          handle_outgoing_calls_request(outgoing_calls_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
         if let Ok(symbols_r) = cast_req::<DocumentSymbolRequest>(&req) {
           handle_document_symbols_request(symbols_r, &evaulated_trees, req, &connection);
           continue;
         }
+
+        if let Ok(workspace_symbols_r) = cast_req::<WorkspaceSymbolRequest>(&req) {
+          handle_workspace_symbols_request(workspace_symbols_r, workspace_index, req, &connection);
+          continue;
+        }
+
+        if let Ok(prepare_rename_r) = cast_req::<PrepareRenameRequest>(&req) {
+          handle_prepare_rename_request(prepare_rename_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(rename_r) = cast_req::<Rename>(&req) {
+          handle_rename_request(rename_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(formatting_r) = cast_req::<Formatting>(&req) {
+          handle_formatting_request(formatting_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(range_formatting_r) = cast_req::<RangeFormatting>(&req) {
+          handle_range_formatting_request(range_formatting_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(signature_help_r) = cast_req::<SignatureHelpRequest>(&req) {
+          handle_signature_help_request(signature_help_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(inlay_hint_r) = cast_req::<InlayHintRequest>(&req) {
+          handle_inlay_hint_request(inlay_hint_r, &evaulated_trees, settings, req, &connection);
+          continue;
+        }
+
+        if let Ok(folding_range_r) = cast_req::<FoldingRangeRequest>(&req) {
+          handle_folding_range_request(folding_range_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(selection_range_r) = cast_req::<SelectionRangeRequest>(&req) {
+          handle_selection_range_request(selection_range_r, &evaulated_trees, req, &connection);
+          continue;
+        }
+
+        if let Ok(code_action_r) = cast_req::<CodeActionRequest>(&req) {
+          handle_code_action_request(code_action_r, &evaulated_trees, req, &connection);
+          continue;
+        }
       }
       Message::Response(_) => continue,
       Message::Notification(not) => {
         if let Ok(did_open) = cast_notif::<DidOpenTextDocument>(&not) {
+          let uri = &did_open.text_document.uri;
+
           open_doc(&did_open, parser, &mut evaulated_trees);
-          publish_diagnostics(&did_open.text_document.uri, &evaulated_trees, &connection);
+          index_doc(uri, &evaulated_trees, workspace_index);
+          publish_diagnostics(uri, &evaulated_trees, &connection);
         }
 
         if let Ok(did_change) = cast_notif::<DidChangeTextDocument>(&not) {
+          let uri = &did_change.text_document.uri;
+
           change_doc(&did_change, parser, &mut evaulated_trees);
-          publish_diagnostics(&did_change.text_document.uri, &evaulated_trees, &connection);
+          index_doc(uri, &evaulated_trees, workspace_index);
+          publish_diagnostics(uri, &evaulated_trees, &connection);
+        }
+
+        if let Ok(did_save) = cast_notif::<DidSaveTextDocument>(&not) {
+          let uri = &did_save.text_document.uri;
+
+          index_doc(uri, &evaulated_trees, workspace_index);
+
+          // Clients without file watching still pick up changed schemas when saving
+          if let Some(document) = evaulated_trees.get_mut(uri.as_str()) {
+            document.set_document_schemas(load_document_schemas(uri));
+            publish_diagnostics(uri, &evaulated_trees, &connection);
+          }
+        }
+
+        if let Ok(did_close) = cast_notif::<DidCloseTextDocument>(&not) {
+          workspace_index.close_document(&did_close.text_document.uri, parser);
+          sent_tokens.forget(did_close.text_document.uri.as_str());
+        }
+
+        if let Ok(did_change_watched) = cast_notif::<DidChangeWatchedFiles>(&not) {
+          let changed_files = did_change_watched
+            .changes
+            .iter()
+            .filter_map(|change| uri_to_path(&change.uri))
+            .collect::<Vec<PathBuf>>();
+
+          for file in &changed_files {
+            workspace_index.update_file(file, parser);
+          }
+
+          reload_document_schemas(&changed_files, &mut evaulated_trees, &connection);
+        }
+
+        if let Ok(did_change_folders) = cast_notif::<DidChangeWorkspaceFolders>(&not) {
+          workspace_index.remove_folders(&did_change_folders.event.removed);
+          workspace_index.add_folders(&did_change_folders.event.added, parser);
         }
 
         continue;
@@ -137,9 +423,26 @@ fn main_loop<'a>(connection: Connection, parser: &mut Parser) -> Result<(), Box<
   Ok(())
 }
 
+fn handle_workspace_symbols_request(
+  workspace_symbols_r: (RequestId, WorkspaceSymbolParams),
+  workspace_index: &WorkspaceIndex,
+  req: Request,
+  connection: &Connection,
+) {
+  let symbols = workspace_index.symbols();
+  let matching = filter_workspace_symbols(&workspace_symbols_r.1.query, symbols);
+
+  let msg = Response::new_ok::<Option<WorkspaceSymbolResponse>>(
+    req.id,
+    Some(WorkspaceSymbolResponse::Nested(matching)),
+  );
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
 fn handle_document_symbols_request<'a>(
   symbols_r: (RequestId, DocumentSymbolParams),
-  evaulated_trees: &'a HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &'a LSPTreeStorage<'a>,
   req: Request,
   connection: &Connection,
 ) {
@@ -160,9 +463,78 @@ fn handle_document_symbols_request<'a>(
   ));
 }
 
+fn handle_document_highlight_request<'a>(
+  highlight_r: (RequestId, DocumentHighlightParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let position_params = highlight_r.1.text_document_position_params;
+
+  let highlights = evaulated_trees
+    .get(position_params.text_document.uri.as_str())
+    .map(|document| get_document_highlights(position_params.position, document.rules_tree()));
+
+  let msg = Response::new_ok::<Option<Vec<DocumentHighlight>>>(req.id, highlights);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_prepare_call_hierarchy_request<'a>(
+  prepare_call_hierarchy_r: (RequestId, CallHierarchyPrepareParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let position_params = prepare_call_hierarchy_r.1.text_document_position_params;
+  let uri = position_params.text_document.uri;
+
+  let items = evaulated_trees.get(uri.as_str()).and_then(|document| {
+    prepare_call_hierarchy(position_params.position, document.rules_tree(), &uri)
+  });
+
+  let msg = Response::new_ok::<Option<Vec<CallHierarchyItem>>>(req.id, items);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_incoming_calls_request<'a>(
+  incoming_calls_r: (RequestId, CallHierarchyIncomingCallsParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let item = incoming_calls_r.1.item;
+
+  let calls = evaulated_trees
+    .get(item.uri.as_str())
+    .map(|document| incoming_calls(&item, document.rules_tree(), &item.uri));
+
+  let msg = Response::new_ok::<Option<Vec<CallHierarchyIncomingCall>>>(req.id, calls);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_outgoing_calls_request<'a>(
+  outgoing_calls_r: (RequestId, CallHierarchyOutgoingCallsParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let item = outgoing_calls_r.1.item;
+
+  let calls = evaulated_trees
+    .get(item.uri.as_str())
+    .map(|document| outgoing_calls(&item, document.rules_tree(), &item.uri));
+
+  let msg = Response::new_ok::<Option<Vec<CallHierarchyOutgoingCall>>>(req.id, calls);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
 fn handle_references_request<'a>(
   refernce_r: (RequestId, ReferenceParams),
-  evaulated_trees: &'a HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &'a LSPTreeStorage<'a>,
   req: Request,
   connection: &Connection,
 ) {
@@ -186,9 +558,213 @@ fn handle_references_request<'a>(
     )));
 }
 
+fn handle_prepare_rename_request<'a>(
+  prepare_rename_r: (RequestId, TextDocumentPositionParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let text_document = prepare_rename_r.1.text_document;
+  let position = prepare_rename_r.1.position;
+
+  let tree = match try_get_tree(evaulated_trees, &text_document) {
+    Some(value) => value,
+    None => {
+      let _ = connection
+        .sender
+        .try_send(Message::Response(Response::new_ok(req.id, ())));
+      return;
+    }
+  };
+
+  let response = match prepare_rename(position, tree) {
+    Ok(prepared) => Response::new_ok::<Option<PrepareRenameResponse>>(req.id, prepared),
+    Err(msg) => Response::new_err(req.id, ErrorCode::RequestFailed as i32, msg),
+  };
+
+  let _ = connection.sender.try_send(Message::Response(response));
+}
+
+fn handle_rename_request<'a>(
+  rename_r: (RequestId, RenameParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let text_document = rename_r.1.text_document_position.text_document;
+  let position = rename_r.1.text_document_position.position;
+
+  let tree = match try_get_tree(evaulated_trees, &text_document) {
+    Some(value) => value,
+    None => {
+      let _ = connection
+        .sender
+        .try_send(Message::Response(Response::new_ok(req.id, ())));
+      return;
+    }
+  };
+
+  let response = match rename(text_document.uri, position, &rename_r.1.new_name, tree) {
+    Ok(edit) => Response::new_ok::<Option<WorkspaceEdit>>(req.id, edit),
+    Err(msg) => Response::new_err(req.id, ErrorCode::RequestFailed as i32, msg),
+  };
+
+  let _ = connection.sender.try_send(Message::Response(response));
+}
+
+fn handle_formatting_request<'a>(
+  formatting_r: (RequestId, DocumentFormattingParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let formatting_params = formatting_r.1;
+
+  let edits = evaulated_trees
+    .get(formatting_params.text_document.uri.as_str())
+    .and_then(|document| {
+      format_document_edits(
+        document.tree(),
+        document.text(),
+        &FormatterOptions::from(&formatting_params.options),
+      )
+    });
+
+  let msg = Response::new_ok::<Option<Vec<TextEdit>>>(req.id, edits);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_range_formatting_request<'a>(
+  range_formatting_r: (RequestId, DocumentRangeFormattingParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let formatting_params = range_formatting_r.1;
+
+  let edits = evaulated_trees
+    .get(formatting_params.text_document.uri.as_str())
+    .and_then(|document| {
+      format_range_edits(
+        document.tree(),
+        document.text(),
+        formatting_params.range,
+        &FormatterOptions::from(&formatting_params.options),
+      )
+    });
+
+  let msg = Response::new_ok::<Option<Vec<TextEdit>>>(req.id, edits);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_signature_help_request<'a>(
+  signature_help_r: (RequestId, SignatureHelpParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let position_params = signature_help_r.1.text_document_position_params;
+
+  let signature_help = evaulated_trees
+    .get(position_params.text_document.uri.as_str())
+    .and_then(|document| {
+      get_signature_help(
+        position_params.position,
+        document.rules_tree(),
+        document.text(),
+      )
+    });
+
+  let msg = Response::new_ok::<Option<SignatureHelp>>(req.id, signature_help);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_inlay_hint_request<'a>(
+  inlay_hint_r: (RequestId, InlayHintParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  settings: &ServerSettings,
+  req: Request,
+  connection: &Connection,
+) {
+  let inlay_hint_params = inlay_hint_r.1;
+
+  let hints = evaulated_trees
+    .get(inlay_hint_params.text_document.uri.as_str())
+    .map(|document| {
+      get_inlay_hints(
+        inlay_hint_params.range,
+        document.rules_tree(),
+        document.text(),
+        settings.inlay_hints(),
+      )
+    });
+
+  let msg = Response::new_ok::<Option<Vec<InlayHint>>>(req.id, hints);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_folding_range_request<'a>(
+  folding_range_r: (RequestId, FoldingRangeParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let folding_ranges = evaulated_trees
+    .get(folding_range_r.1.text_document.uri.as_str())
+    .map(|document| get_folding_ranges(document.tree(), document.rules_tree(), document.text()));
+
+  let msg = Response::new_ok::<Option<Vec<FoldingRange>>>(req.id, folding_ranges);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_selection_range_request<'a>(
+  selection_range_r: (RequestId, SelectionRangeParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let selection_range_params = selection_range_r.1;
+
+  let selection_ranges = evaulated_trees
+    .get(selection_range_params.text_document.uri.as_str())
+    .map(|document| get_selection_ranges(&selection_range_params.positions, document.rules_tree()));
+
+  let msg = Response::new_ok::<Option<Vec<SelectionRange>>>(req.id, selection_ranges);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_code_action_request<'a>(
+  code_action_r: (RequestId, CodeActionParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let code_action_params = code_action_r.1;
+  let uri = code_action_params.text_document.uri;
+
+  let actions = evaulated_trees.get(uri.as_str()).map(|document| {
+    get_code_actions(
+      uri.clone(),
+      &code_action_params.context.diagnostics,
+      document.rules_tree(),
+      document.text(),
+    )
+  });
+
+  let msg = Response::new_ok::<Option<CodeActionResponse>>(req.id, actions);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
 fn handle_completion_request<'a>(
   definition_r: (RequestId, CompletionParams),
-  evaulated_trees: &HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &LSPTreeStorage<'a>,
   req: Request,
   connection: &Connection,
 ) {
@@ -222,7 +798,7 @@ fn handle_completion_request<'a>(
 
 fn publish_diagnostics<'a>(
   text_document_uri: &Uri,
-  firestore_trees: &HashMap<String, (RulesTree, Tree)>,
+  firestore_trees: &LSPTreeStorage<'a>,
   connection: &Connection,
 ) -> () {
   let find = firestore_trees.get(text_document_uri.as_str());
@@ -231,9 +807,9 @@ fn publish_diagnostics<'a>(
     return;
   }
 
-  let (firestore_tree, tree) = find.unwrap().to_owned();
+  let document = find.unwrap();
 
-  let diagnostics = build_diagnostics(&tree, &firestore_tree);
+  let diagnostics = build_diagnostics(document.tree(), document.rules_tree());
 
   let _ = connection
     .sender
@@ -250,54 +826,92 @@ fn open_doc<'a>(
 ) {
   let text = &did_open.text_document.text;
 
-  let parsed_tree_opt = parser.parse(text.clone(), None);
+  let document_schemas = load_document_schemas(&did_open.text_document.uri);
 
-  if parsed_tree_opt.is_none() {
+  let document_opt = LSPDocument::new(text.clone(), parser, document_schemas);
+
+  if document_opt.is_none() {
     return;
   }
 
-  let tree = parsed_tree_opt.unwrap();
-
-  let evaluated_tree = evaluate_tree(tree.clone(), text.as_bytes());
-
   evaulated_trees.insert(
     did_open.text_document.uri.to_string(),
-    (evaluated_tree, tree),
+    document_opt.unwrap(),
   );
 }
 
-fn change_doc<'a>(
-  did_change: &DidChangeTextDocumentParams,
-  parser: &mut Parser,
+fn load_document_schemas(uri: &Uri) -> Option<Rc<DocumentSchemas>> {
+  uri_to_path(uri).and_then(|path| {
+    DocumentSchemas::load_for(&path)
+      .inspect_err(|err| eprintln!("Could not load the document schemas, {}", err))
+      .ok()
+      .flatten()
+      .map(Rc::new)
+  })
+}
+
+/// Loads the document schemas of the open documents again if any of the changed files
+/// declares them
+fn reload_document_schemas<'a>(
+  changed_files: &[PathBuf],
   evaulated_trees: &mut LSPTreeStorage<'a>,
+  connection: &Connection,
 ) {
-  let content = &did_change.content_changes.last();
+  let mut reloaded = vec![];
+
+  for (uri, document) in evaulated_trees.iter_mut() {
+    let Some(uri) = Uri::from_str(uri).ok() else {
+      continue;
+    };
+
+    let Some(rules_file) = uri_to_path(&uri) else {
+      continue;
+    };
+
+    if changed_files
+      .iter()
+      .any(|file| schemas_depend_on(&rules_file, file))
+    {
+      document.set_document_schemas(load_document_schemas(&uri));
+      reloaded.push(uri);
+    }
+  }
 
-  if content.is_none() {
-    return;
+  for uri in reloaded {
+    publish_diagnostics(&uri, evaulated_trees, connection);
   }
+}
 
-  let text = content.cloned().unwrap().text;
+/// Updates the workspace index with the current content of the document
+fn index_doc<'a>(
+  uri: &Uri,
+  evaulated_trees: &LSPTreeStorage<'a>,
+  workspace_index: &mut WorkspaceIndex,
+) {
+  if let Some(document) = evaulated_trees.get(uri.as_str()) {
+    workspace_index.update_document(uri, document);
+  }
+}
 
-  let parsed_tree_opt = parser.parse(text.clone(), None);
+fn change_doc<'a>(
+  did_change: &DidChangeTextDocumentParams,
+  parser: &mut Parser,
+  evaulated_trees: &mut LSPTreeStorage<'a>,
+) {
+  let document_opt = evaulated_trees.get_mut(did_change.text_document.uri.as_str());
 
-  if parsed_tree_opt.is_none() {
+  if document_opt.is_none() {
     return;
   }
 
-  let tree = parsed_tree_opt.unwrap();
-
-  let evaluated_tree = evaluate_tree(tree.clone(), text.as_bytes());
-
-  evaulated_trees.insert(
-    did_change.text_document.uri.to_string(),
-    (evaluated_tree, tree),
-  );
+  document_opt
+    .unwrap()
+    .apply_changes(&did_change.content_changes, parser);
 }
 
 fn handle_go_to_definition<'a>(
   definition_r: (RequestId, GotoDefinitionParams),
-  evaulated_trees: &HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &LSPTreeStorage<'a>,
   req: Request,
   connection: &Connection,
 ) {
@@ -363,7 +977,7 @@ fn handle_go_to_definition<'a>(
 }
 
 fn try_get_tree<'a>(
-  evaulated_trees: &'a HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &'a LSPTreeStorage<'a>,
   doc: &TextDocumentIdentifier,
 ) -> Option<&'a RulesTree> {
   let find = evaulated_trees.get(doc.uri.as_str());
@@ -372,9 +986,7 @@ fn try_get_tree<'a>(
     return None;
   }
 
-  let (firestore_tree, _) = find.unwrap();
-
-  Some(firestore_tree)
+  Some(find.unwrap().rules_tree())
 }
 
 fn handle_hover<'a>(
@@ -419,28 +1031,72 @@ fn handle_hover<'a>(
 
 fn handle_tokenize_request<'a>(
   tokenize_r: (RequestId, SemanticTokensParams),
-  evaulated_trees: &HashMap<String, (RulesTree, Tree)>,
+  evaulated_trees: &LSPTreeStorage<'a>,
+  sent_tokens: &mut SentTokens,
   req: Request,
   connection: &Connection,
 ) -> () {
   let tokenize_params = tokenize_r.1;
+  let uri = tokenize_params.text_document.uri.as_str();
 
-  let find = evaulated_trees.get(tokenize_params.text_document.uri.as_str());
+  let find = evaulated_trees.get(uri);
 
   if find.is_none() {
     return;
   }
 
-  let (_, tree) = find.unwrap();
+  let document = find.unwrap();
+  let tokenization_result = tokenize(document.tree(), document.rules_tree());
+
+  let tokenize_msg = SemanticTokensResult::Tokens(sent_tokens.remember(uri, tokenization_result));
 
-  let tokenization_result = tokenize(&tree);
+  let msg = Response::new_ok::<SemanticTokensResult>(req.id, tokenize_msg);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_tokenize_delta_request<'a>(
+  tokenize_delta_r: (RequestId, SemanticTokensDeltaParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  sent_tokens: &mut SentTokens,
+  req: Request,
+  connection: &Connection,
+) {
+  let params = tokenize_delta_r.1;
+  let uri = params.text_document.uri.as_str();
 
-  let tokenize_msg = SemanticTokensResult::Tokens(SemanticTokens {
+  let Some(document) = evaulated_trees.get(uri) else {
+    return;
+  };
+
+  let tokenization_result = tokenize(document.tree(), document.rules_tree());
+  let delta = sent_tokens.delta(uri, &params.previous_result_id, tokenization_result);
+
+  let msg = Response::new_ok::<SemanticTokensFullDeltaResult>(req.id, delta);
+
+  let _ = connection.sender.try_send(Message::Response(msg));
+}
+
+fn handle_tokenize_range_request<'a>(
+  tokenize_range_r: (RequestId, SemanticTokensRangeParams),
+  evaulated_trees: &LSPTreeStorage<'a>,
+  req: Request,
+  connection: &Connection,
+) {
+  let params = tokenize_range_r.1;
+
+  let Some(document) = evaulated_trees.get(params.text_document.uri.as_str()) else {
+    return;
+  };
+
+  let tokenization_result = tokenize_range(document.tree(), document.rules_tree(), params.range);
+
+  let tokenize_msg = SemanticTokensRangeResult::Tokens(SemanticTokens {
     result_id: None,
     data: tokenization_result,
   });
 
-  let msg = Response::new_ok::<SemanticTokensResult>(req.id, tokenize_msg);
+  let msg = Response::new_ok::<SemanticTokensRangeResult>(req.id, tokenize_msg);
 
   let _ = connection.sender.try_send(Message::Response(msg));
 }
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::provider::inlay_hints::InlayHintOptions;
+
+/// Settings the client passes as `initializationOptions` when starting the server
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerSettings {
+  inlay_hints: InlayHintOptions,
+}
+
+impl ServerSettings {
+  /// Falls back to the defaults for missing or malformed options
+  pub fn from_initialization_options(options: Option<serde_json::Value>) -> Self {
+    options
+      .and_then(|options| serde_json::from_value(options).ok())
+      .unwrap_or_default()
+  }
+
+  pub fn inlay_hints(&self) -> &InlayHintOptions {
+    &self.inlay_hints
+  }
+}
@@ -0,0 +1,222 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  path::{Path, PathBuf},
+  str::FromStr,
+};
+
+use lsp_types::{Uri, WorkspaceFolder, WorkspaceSymbol};
+use tree_sitter::Parser;
+
+use super::document::LSPDocument;
+use crate::{
+  parser::evaluation::evaluate_tree, provider::workspace_symbols::collect_workspace_symbols,
+};
+
+const RULES_EXTENSION: &str = "rules";
+
+/// Directories that never contain rules files worth indexing
+const IGNORED_DIRECTORIES: [&str; 3] = ["node_modules", "target", "dist"];
+
+/// The symbols of every rules file below the workspace folders, including the ones
+/// the editor has not opened. The folders are searched once when they are added, after
+/// that the index is kept up to date with the open documents and the watched files.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceIndex {
+  folders: Vec<PathBuf>,
+  files: HashMap<PathBuf, Vec<WorkspaceSymbol>>,
+  /// Files whose symbols are taken from the editor's content instead of the disk
+  open_files: HashSet<PathBuf>,
+}
+
+impl WorkspaceIndex {
+  pub fn new(folders: Vec<PathBuf>, parser: &mut Parser) -> Self {
+    let mut index = Self::default();
+
+    for folder in folders {
+      index.add_folder(folder, parser);
+    }
+
+    index
+  }
+
+  pub fn add_folders(&mut self, folders: &[WorkspaceFolder], parser: &mut Parser) {
+    for folder in folders.iter().filter_map(|folder| uri_to_path(&folder.uri)) {
+      self.add_folder(folder, parser);
+    }
+  }
+
+  fn add_folder(&mut self, folder: PathBuf, parser: &mut Parser) {
+    if self.folders.contains(&folder) {
+      return;
+    }
+
+    let mut rules_files = vec![];
+    find_rules_files(&folder, &mut rules_files);
+
+    for file in rules_files {
+      if !self.open_files.contains(&file) {
+        self.index_file(&file, parser);
+      }
+    }
+
+    self.folders.push(folder);
+  }
+
+  pub fn remove_folders(&mut self, folders: &[WorkspaceFolder]) {
+    let removed = folders
+      .iter()
+      .filter_map(|folder| uri_to_path(&folder.uri))
+      .collect::<Vec<PathBuf>>();
+
+    self.folders.retain(|folder| !removed.contains(folder));
+
+    let folders = &self.folders;
+    let open_files = &self.open_files;
+
+    self.files.retain(|file, _| {
+      open_files.contains(file) || folders.iter().any(|folder| file.starts_with(folder))
+    });
+  }
+
+  /// Takes the symbols of an open document from the editor's current content
+  pub fn update_document(&mut self, uri: &Uri, document: &LSPDocument) {
+    let Some(path) = uri_to_path(uri) else {
+      return;
+    };
+
+    self.files.insert(
+      path.clone(),
+      collect_workspace_symbols(document.rules_tree(), uri),
+    );
+    self.open_files.insert(path);
+  }
+
+  /// Reads the symbols of a closed document from the disk again, as the editor's
+  /// content may not have been saved
+  pub fn close_document(&mut self, uri: &Uri, parser: &mut Parser) {
+    let Some(path) = uri_to_path(uri) else {
+      return;
+    };
+
+    self.open_files.remove(&path);
+    self.files.remove(&path);
+    self.update_file(&path, parser);
+  }
+
+  /// Indexes a rules file that was created or changed on disk, or drops it from the index
+  /// if it was deleted or is not part of the workspace folders
+  pub fn update_file(&mut self, file: &Path, parser: &mut Parser) {
+    if self.open_files.contains(file) {
+      return;
+    }
+
+    let in_folders = self.folders.iter().any(|folder| file.starts_with(folder));
+    let is_rules_file = file.extension().is_some_and(|ext| ext == RULES_EXTENSION);
+
+    if in_folders && is_rules_file && file.is_file() {
+      self.index_file(file, parser);
+    } else {
+      self.files.remove(file);
+    }
+  }
+
+  /// The symbols of all rules files
+  pub fn symbols(&self) -> Vec<WorkspaceSymbol> {
+    self.files.values().flatten().cloned().collect()
+  }
+
+  fn index_file(&mut self, file: &Path, parser: &mut Parser) {
+    let symbols = fs::read_to_string(file).ok().and_then(|source| {
+      let tree = parser.parse(&source, None)?;
+      let rules_tree = evaluate_tree(tree, source.as_bytes());
+      let uri = path_to_uri(file)?;
+
+      Some(collect_workspace_symbols(&rules_tree, &uri))
+    });
+
+    match symbols {
+      Some(symbols) => self.files.insert(file.to_path_buf(), symbols),
+      None => self.files.remove(file),
+    };
+  }
+}
+
+fn find_rules_files(directory: &Path, found: &mut Vec<PathBuf>) {
+  let Ok(entries) = fs::read_dir(directory) else {
+    return;
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let name = entry.file_name().to_string_lossy().into_owned();
+
+    let Ok(file_type) = entry.file_type() else {
+      continue;
+    };
+
+    if file_type.is_dir() {
+      if !name.starts_with('.') && !IGNORED_DIRECTORIES.contains(&name.as_str()) {
+        find_rules_files(&path, found);
+      }
+    } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == RULES_EXTENSION) {
+      found.push(path);
+    }
+  }
+}
+
+/// The local path of a `file://` uri, with percent escapes decoded
+pub fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+  let path = uri.as_str().strip_prefix("file://")?;
+  let bytes = path.as_bytes();
+
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let escaped = (bytes[i] == b'%')
+      .then(|| path.get(i + 1..i + 3))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+    match escaped {
+      Some(byte) => {
+        decoded.push(byte);
+        i += 3;
+      }
+      None => {
+        decoded.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+
+  let decoded = String::from_utf8(decoded).ok()?;
+
+  // Windows paths come as `/c:/...`
+  match decoded.get(2..3) {
+    Some(":") if decoded.starts_with('/') => Some(PathBuf::from(&decoded[1..])),
+    _ => Some(PathBuf::from(decoded)),
+  }
+}
+
+/// A `file://` uri for the path, percent escaping everything but unreserved characters
+pub fn path_to_uri(path: &Path) -> Option<Uri> {
+  let path = path.to_str()?.replace('\\', "/");
+  let mut uri = String::from("file://");
+
+  if !path.starts_with('/') {
+    uri.push('/');
+  }
+
+  for byte in path.bytes() {
+    match byte {
+      b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+        uri.push(byte as char)
+      }
+      _ => uri.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+
+  Uri::from_str(&uri).ok()
+}
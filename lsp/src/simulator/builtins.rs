@@ -0,0 +1,805 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use super::value::*;
+use crate::parser::types::FirebaseType;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_010.0;
+
+/// Calls the built in method `name` on the given value
+pub fn call_method(object: &Value, name: &str, args: &[Value]) -> Result<Value, String> {
+  match object {
+    Value::String(s) => string_method(s, name, args),
+    Value::List(elements) => list_method(elements, name, args),
+    Value::Set(elements) => set_method(elements, name, args),
+    Value::Map(entries) | Value::Object(_, entries) => map_method(entries, name, args),
+    Value::MapDiff(left, right) => map_diff_method(left, right, name, args),
+    Value::Path(segments) => path_method(segments, name, args),
+    Value::Bytes(bytes) => bytes_method(bytes, name, args),
+    Value::Timestamp(nanos) => timestamp_method(*nanos, name, args),
+    Value::Duration(nanos) => duration_method(*nanos, name, args),
+    Value::LatLng(lat, lng) => latlng_method(*lat, *lng, name, args),
+    Value::Module(module) => module_function(*module, name, args),
+    Value::Null => Err("Null value error.".to_owned()),
+    _ => Err(unknown_method(object, name)),
+  }
+}
+
+/// Calls one of the global conversion functions, returns `None` if there is no such function
+pub fn call_global_function(name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+  let result = match name {
+    "int" => arity(name, args, 1).and_then(|_| to_int(&args[0])),
+    "float" => arity(name, args, 1).and_then(|_| to_float(&args[0])),
+    "string" => arity(name, args, 1).and_then(|_| to_string(&args[0])),
+    "path" => arity(name, args, 1).and_then(|_| Ok(Value::Path(split_path(args[0].as_str()?)))),
+    "debug" => arity(name, args, 1).map(|_| args[0].clone()),
+    _ => return None,
+  };
+
+  Some(result)
+}
+
+/// Segments of a slash separated path, ignoring empty segments
+pub fn split_path(path: &str) -> Vec<String> {
+  path
+    .split('/')
+    .filter(|segment| !segment.is_empty())
+    .map(|segment| segment.to_owned())
+    .collect()
+}
+
+fn arity(name: &str, args: &[Value], expected: usize) -> Result<(), String> {
+  if args.len() != expected {
+    return Err(format!(
+      "Function {}() expects {} argument(s) but got {}",
+      name,
+      expected,
+      args.len()
+    ));
+  }
+
+  Ok(())
+}
+
+fn unknown_method(object: &Value, name: &str) -> String {
+  format!(
+    "Function not found error: Name: [{}] on {}.",
+    name,
+    object.type_name()
+  )
+}
+
+fn to_int(value: &Value) -> Result<Value, String> {
+  match value {
+    Value::Integer(i) => Ok(Value::Integer(*i)),
+    Value::Float(f) if f.is_finite() => Ok(Value::Integer(f.trunc() as i64)),
+    Value::String(s) => s
+      .trim()
+      .parse::<i64>()
+      .map(Value::Integer)
+      .map_err(|_| format!("Can not convert '{}' to int", s)),
+    _ => Err(format!("Can not convert {} to int", value.type_name())),
+  }
+}
+
+fn to_float(value: &Value) -> Result<Value, String> {
+  match value {
+    Value::Integer(i) => Ok(Value::Float(*i as f64)),
+    Value::Float(f) => Ok(Value::Float(*f)),
+    Value::String(s) => s
+      .trim()
+      .parse::<f64>()
+      .map(Value::Float)
+      .map_err(|_| format!("Can not convert '{}' to float", s)),
+    _ => Err(format!("Can not convert {} to float", value.type_name())),
+  }
+}
+
+fn to_string(value: &Value) -> Result<Value, String> {
+  match value {
+    Value::String(s) => Ok(Value::String(s.clone())),
+    Value::Null | Value::Boolean(_) | Value::Integer(_) | Value::Float(_) | Value::Path(_) => {
+      Ok(Value::String(value.to_string()))
+    }
+    _ => Err(format!("Can not convert {} to string", value.type_name())),
+  }
+}
+
+fn compile_regex(pattern: &str, anchored: bool) -> Result<Regex, String> {
+  let pattern = if anchored {
+    format!("^(?:{})$", pattern)
+  } else {
+    pattern.to_owned()
+  };
+
+  Regex::new(&pattern).map_err(|err| format!("Invalid regular expression: {}", err))
+}
+
+fn string_method(s: &str, name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "lower" => arity(name, args, 0).map(|_| Value::String(s.to_lowercase())),
+    "upper" => arity(name, args, 0).map(|_| Value::String(s.to_uppercase())),
+    "trim" => arity(name, args, 0).map(|_| Value::String(s.trim().to_owned())),
+    "size" => arity(name, args, 0).map(|_| Value::Integer(s.chars().count() as i64)),
+    "toUtf8" => arity(name, args, 0).map(|_| Value::Bytes(s.as_bytes().to_vec())),
+    "matches" => {
+      arity(name, args, 1)?;
+      let regex = compile_regex(args[0].as_str()?, true)?;
+
+      Ok(Value::Boolean(regex.is_match(s)))
+    }
+    "split" => {
+      arity(name, args, 1)?;
+      let regex = compile_regex(args[0].as_str()?, false)?;
+
+      Ok(Value::List(
+        regex
+          .split(s)
+          .map(|part| Value::String(part.to_owned()))
+          .collect(),
+      ))
+    }
+    "replace" => {
+      arity(name, args, 2)?;
+      let regex = compile_regex(args[0].as_str()?, false)?;
+
+      Ok(Value::String(
+        regex.replace_all(s, args[1].as_str()?).into_owned(),
+      ))
+    }
+    _ => Err(unknown_method(&Value::String(s.to_owned()), name)),
+  }
+}
+
+fn list_method(elements: &[Value], name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "size" => arity(name, args, 0).map(|_| Value::Integer(elements.len() as i64)),
+    "toSet" => arity(name, args, 0).map(|_| Value::set_of(elements.to_vec())),
+    "concat" => {
+      arity(name, args, 1)?;
+      let mut concatenated = elements.to_vec();
+      concatenated.extend(args[0].as_elements()?.iter().cloned());
+
+      Ok(Value::List(concatenated))
+    }
+    "removeAll" => {
+      arity(name, args, 1)?;
+      let removed = args[0].as_elements()?;
+
+      Ok(Value::List(
+        elements
+          .iter()
+          .filter(|el| !removed.contains(el))
+          .cloned()
+          .collect(),
+      ))
+    }
+    "join" => {
+      arity(name, args, 1)?;
+      let separator = args[0].as_str()?;
+
+      let parts = elements
+        .iter()
+        .map(|el| el.as_str().map(|s| s.to_owned()))
+        .collect::<Result<Vec<String>, String>>()?;
+
+      Ok(Value::String(parts.join(separator)))
+    }
+    "hasAll" | "hasAny" | "hasOnly" => containment(elements, name, args),
+    _ => Err(unknown_method(&Value::List(elements.to_vec()), name)),
+  }
+}
+
+fn set_method(elements: &[Value], name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "size" => arity(name, args, 0).map(|_| Value::Integer(elements.len() as i64)),
+    "hasAll" | "hasAny" | "hasOnly" => containment(elements, name, args),
+    "difference" | "intersection" | "union" => {
+      arity(name, args, 1)?;
+
+      let other = match &args[0] {
+        Value::Set(other) => other,
+        value => {
+          return Err(format!(
+            "Function {}() expects a set but got {}",
+            name,
+            value.type_name()
+          ));
+        }
+      };
+
+      let result: Vec<Value> = match name {
+        "difference" => elements
+          .iter()
+          .filter(|el| !other.contains(el))
+          .cloned()
+          .collect(),
+        "intersection" => elements
+          .iter()
+          .filter(|el| other.contains(el))
+          .cloned()
+          .collect(),
+        _ => elements.iter().chain(other.iter()).cloned().collect(),
+      };
+
+      Ok(Value::set_of(result))
+    }
+    _ => Err(unknown_method(&Value::Set(elements.to_vec()), name)),
+  }
+}
+
+/// `hasAll`, `hasAny` and `hasOnly` of lists and sets
+fn containment(elements: &[Value], name: &str, args: &[Value]) -> Result<Value, String> {
+  arity(name, args, 1)?;
+  let other = args[0].as_elements()?;
+
+  let result = match name {
+    "hasAll" => other.iter().all(|el| elements.contains(el)),
+    "hasAny" => other.iter().any(|el| elements.contains(el)),
+    _ => elements.iter().all(|el| other.contains(el)),
+  };
+
+  Ok(Value::Boolean(result))
+}
+
+fn map_method(
+  entries: &BTreeMap<String, Value>,
+  name: &str,
+  args: &[Value],
+) -> Result<Value, String> {
+  match name {
+    "size" => arity(name, args, 0).map(|_| Value::Integer(entries.len() as i64)),
+    "keys" => arity(name, args, 0).map(|_| {
+      Value::List(
+        entries
+          .keys()
+          .map(|key| Value::String(key.clone()))
+          .collect(),
+      )
+    }),
+    "values" => arity(name, args, 0).map(|_| Value::List(entries.values().cloned().collect())),
+    "diff" => {
+      arity(name, args, 1)?;
+
+      match args[0].as_map() {
+        Some(other) => Ok(Value::MapDiff(entries.clone(), other.clone())),
+        None => Err(format!(
+          "Function diff() expects a map but got {}",
+          args[0].type_name()
+        )),
+      }
+    }
+    "get" => {
+      arity(name, args, 2)?;
+
+      // The key can be a list of keys to look up nested fields
+      let keys = match &args[0] {
+        Value::String(key) => vec![key.as_str()],
+        Value::List(keys) => keys
+          .iter()
+          .map(|key| key.as_str())
+          .collect::<Result<Vec<&str>, String>>()?,
+        value => {
+          return Err(format!(
+            "Function get() expects a string or list key but got {}",
+            value.type_name()
+          ));
+        }
+      };
+
+      let mut current = entries;
+
+      for (i, key) in keys.iter().enumerate() {
+        match current.get(*key) {
+          Some(value) if i == keys.len() - 1 => return Ok(value.clone()),
+          Some(value) => match value.as_map() {
+            Some(nested) => current = nested,
+            None => break,
+          },
+          None => break,
+        }
+      }
+
+      Ok(args[1].clone())
+    }
+    _ => Err(unknown_method(&Value::Map(entries.clone()), name)),
+  }
+}
+
+fn map_diff_method(
+  left: &BTreeMap<String, Value>,
+  right: &BTreeMap<String, Value>,
+  name: &str,
+  args: &[Value],
+) -> Result<Value, String> {
+  arity(name, args, 0)?;
+
+  let keys: Vec<&String> = match name {
+    "addedKeys" => right
+      .keys()
+      .filter(|key| !left.contains_key(*key))
+      .collect(),
+    "removedKeys" => left
+      .keys()
+      .filter(|key| !right.contains_key(*key))
+      .collect(),
+    "changedKeys" => left
+      .iter()
+      .filter(|(key, value)| right.get(*key).is_some_and(|other| other != *value))
+      .map(|(key, _)| key)
+      .collect(),
+    "unchangedKeys" => left
+      .iter()
+      .filter(|(key, value)| right.get(*key).is_some_and(|other| other == *value))
+      .map(|(key, _)| key)
+      .collect(),
+    "affectedKeys" => left
+      .keys()
+      .chain(right.keys())
+      .filter(|key| left.get(*key) != right.get(*key))
+      .collect(),
+    _ => {
+      return Err(unknown_method(
+        &Value::MapDiff(left.clone(), right.clone()),
+        name,
+      ));
+    }
+  };
+
+  Ok(Value::set_of(
+    keys.into_iter().map(|key| Value::String(key.clone())),
+  ))
+}
+
+fn path_method(segments: &[String], name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "bind" => {
+      arity(name, args, 1)?;
+
+      let bindings = args[0].as_map().ok_or(format!(
+        "Function bind() expects a map but got {}",
+        args[0].type_name()
+      ))?;
+
+      let bound = segments
+        .iter()
+        .map(|segment| {
+          let variable = segment
+            .strip_prefix('{')
+            .and_then(|segment| segment.strip_suffix('}'));
+
+          match variable.and_then(|variable| bindings.get(variable)) {
+            Some(value) => value.as_str().map(|s| s.to_owned()),
+            None => Ok(segment.clone()),
+          }
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+
+      Ok(Value::Path(bound))
+    }
+    _ => Err(unknown_method(&Value::Path(segments.to_vec()), name)),
+  }
+}
+
+fn bytes_method(bytes: &[u8], name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "size" => arity(name, args, 0).map(|_| Value::Integer(bytes.len() as i64)),
+    "toHexString" => arity(name, args, 0).map(|_| Value::String(to_hex(bytes))),
+    "toBase64" => arity(name, args, 0).map(|_| Value::String(to_base64(bytes))),
+    _ => Err(unknown_method(&Value::Bytes(bytes.to_vec()), name)),
+  }
+}
+
+fn timestamp_method(nanos: i128, name: &str, args: &[Value]) -> Result<Value, String> {
+  arity(name, args, 0)?;
+
+  let seconds = nanos.div_euclid(NANOS_PER_SECOND) as i64;
+  let days = seconds.div_euclid(86400);
+  let seconds_of_day = seconds.rem_euclid(86400);
+  let (year, month, day) = civil_from_days(days);
+
+  let value = match name {
+    "date" => Value::Timestamp(days as i128 * 86400 * NANOS_PER_SECOND),
+    "year" => Value::Integer(year),
+    "month" => Value::Integer(month),
+    "day" => Value::Integer(day),
+    "dayOfYear" => Value::Integer(days - days_from_civil(year, 1, 1) + 1),
+    // The epoch was a thursday, monday is the first day of the week
+    "dayOfWeek" => Value::Integer((days + 3).rem_euclid(7) + 1),
+    "hours" => Value::Integer(seconds_of_day / 3600),
+    "minutes" => Value::Integer(seconds_of_day % 3600 / 60),
+    "seconds" => Value::Integer(seconds_of_day % 60),
+    "nanos" => Value::Integer(nanos.rem_euclid(NANOS_PER_SECOND) as i64),
+    "time" => Value::Duration(nanos.rem_euclid(86400 * NANOS_PER_SECOND)),
+    "toMillis" => Value::Integer(nanos.div_euclid(1_000_000) as i64),
+    _ => return Err(unknown_method(&Value::Timestamp(nanos), name)),
+  };
+
+  Ok(value)
+}
+
+fn duration_method(nanos: i128, name: &str, args: &[Value]) -> Result<Value, String> {
+  arity(name, args, 0)?;
+
+  match name {
+    "seconds" => Ok(Value::Integer((nanos / NANOS_PER_SECOND) as i64)),
+    "nanos" => Ok(Value::Integer((nanos % NANOS_PER_SECOND) as i64)),
+    _ => Err(unknown_method(&Value::Duration(nanos), name)),
+  }
+}
+
+fn latlng_method(lat: f64, lng: f64, name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "latitude" => arity(name, args, 0).map(|_| Value::Float(lat)),
+    "longitude" => arity(name, args, 0).map(|_| Value::Float(lng)),
+    "distance" => {
+      arity(name, args, 1)?;
+
+      let Value::LatLng(other_lat, other_lng) = args[0] else {
+        return Err(format!(
+          "Function distance() expects a latlng but got {}",
+          args[0].type_name()
+        ));
+      };
+
+      // Haversine formula
+      let d_lat = (other_lat - lat).to_radians();
+      let d_lng = (other_lng - lng).to_radians();
+      let a = (d_lat / 2.0).sin().powi(2)
+        + lat.to_radians().cos() * other_lat.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+
+      Ok(Value::Float(
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().atan2((1.0 - a).sqrt()),
+      ))
+    }
+    _ => Err(unknown_method(&Value::LatLng(lat, lng), name)),
+  }
+}
+
+fn module_function(module: FirebaseType, name: &str, args: &[Value]) -> Result<Value, String> {
+  match module {
+    FirebaseType::MathModule => math_function(name, args),
+    FirebaseType::HashingModule => hashing_function(name, args),
+    FirebaseType::TimestampModule => timestamp_function(name, args),
+    FirebaseType::DurationModule => duration_function(name, args),
+    FirebaseType::LatLngModule => match name {
+      "value" => {
+        arity(name, args, 2)?;
+        Ok(Value::LatLng(args[0].as_number()?, args[1].as_number()?))
+      }
+      _ => Err(unknown_method(&Value::Module(module), name)),
+    },
+    _ => Err(unknown_method(&Value::Module(module), name)),
+  }
+}
+
+fn math_function(name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "abs" => {
+      arity(name, args, 1)?;
+
+      match args[0] {
+        Value::Integer(i) => i
+          .checked_abs()
+          .map(Value::Integer)
+          .ok_or("Integer overflow".to_owned()),
+        _ => Ok(Value::Float(args[0].as_number()?.abs())),
+      }
+    }
+    "ceil" | "floor" | "round" => {
+      arity(name, args, 1)?;
+      let number = args[0].as_number()?;
+
+      let rounded = match name {
+        "ceil" => number.ceil(),
+        "floor" => number.floor(),
+        _ => number.round(),
+      };
+
+      Ok(Value::Integer(rounded as i64))
+    }
+    "sqrt" => {
+      arity(name, args, 1)?;
+      Ok(Value::Float(args[0].as_number()?.sqrt()))
+    }
+    "pow" => {
+      arity(name, args, 2)?;
+      Ok(Value::Float(
+        args[0].as_number()?.powf(args[1].as_number()?),
+      ))
+    }
+    "isInfinite" => {
+      arity(name, args, 1)?;
+      Ok(Value::Boolean(args[0].as_number()?.is_infinite()))
+    }
+    "isNaN" => {
+      arity(name, args, 1)?;
+      Ok(Value::Boolean(args[0].as_number()?.is_nan()))
+    }
+    _ => Err(unknown_method(
+      &Value::Module(FirebaseType::MathModule),
+      name,
+    )),
+  }
+}
+
+fn hashing_function(name: &str, args: &[Value]) -> Result<Value, String> {
+  arity(name, args, 1)?;
+
+  let bytes = match &args[0] {
+    Value::String(s) => s.as_bytes().to_vec(),
+    Value::Bytes(bytes) => bytes.clone(),
+    value => {
+      return Err(format!(
+        "Function {}() expects a string or bytes but got {}",
+        name,
+        value.type_name()
+      ));
+    }
+  };
+
+  match name {
+    "crc32" => Ok(Value::Integer(crc32(&bytes, 0xEDB88320) as i64)),
+    "crc32c" => Ok(Value::Integer(crc32(&bytes, 0x82F63B78) as i64)),
+    "md5" | "sha256" => Err(format!(
+      "hashing.{}() is not supported by the simulator",
+      name
+    )),
+    _ => Err(unknown_method(
+      &Value::Module(FirebaseType::HashingModule),
+      name,
+    )),
+  }
+}
+
+fn crc32(bytes: &[u8], polynomial: u32) -> u32 {
+  let mut crc = !0u32;
+
+  for byte in bytes {
+    crc ^= *byte as u32;
+
+    for _ in 0..8 {
+      crc = if crc & 1 == 1 {
+        (crc >> 1) ^ polynomial
+      } else {
+        crc >> 1
+      };
+    }
+  }
+
+  !crc
+}
+
+fn timestamp_function(name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "date" => {
+      arity(name, args, 3)?;
+
+      let days = days_from_civil(
+        args[0].as_integer()?,
+        args[1].as_integer()?,
+        args[2].as_integer()?,
+      );
+
+      Ok(Value::Timestamp(days as i128 * 86400 * NANOS_PER_SECOND))
+    }
+    "value" => {
+      arity(name, args, 1)?;
+      Ok(Value::Timestamp(args[0].as_integer()? as i128 * 1_000_000))
+    }
+    _ => Err(unknown_method(
+      &Value::Module(FirebaseType::TimestampModule),
+      name,
+    )),
+  }
+}
+
+fn duration_function(name: &str, args: &[Value]) -> Result<Value, String> {
+  match name {
+    "abs" => {
+      arity(name, args, 1)?;
+
+      match args[0] {
+        Value::Duration(nanos) => Ok(Value::Duration(nanos.abs())),
+        _ => Err(format!(
+          "Function abs() expects a duration but got {}",
+          args[0].type_name()
+        )),
+      }
+    }
+    "time" => {
+      arity(name, args, 4)?;
+
+      let hours = args[0].as_integer()? as i128;
+      let minutes = args[1].as_integer()? as i128;
+      let seconds = args[2].as_integer()? as i128;
+      let nanos = args[3].as_integer()? as i128;
+
+      Ok(Value::Duration(
+        ((hours * 60 + minutes) * 60 + seconds) * NANOS_PER_SECOND + nanos,
+      ))
+    }
+    "value" => {
+      arity(name, args, 2)?;
+
+      let magnitude = args[0].as_integer()? as i128;
+
+      let unit_nanos = match args[1].as_str()? {
+        "w" => 7 * 86400 * NANOS_PER_SECOND,
+        "d" => 86400 * NANOS_PER_SECOND,
+        "h" => 3600 * NANOS_PER_SECOND,
+        "m" => 60 * NANOS_PER_SECOND,
+        "s" => NANOS_PER_SECOND,
+        "ms" => 1_000_000,
+        "ns" => 1,
+        unit => return Err(format!("Unknown duration unit '{}'", unit)),
+      };
+
+      Ok(Value::Duration(magnitude * unit_nanos))
+    }
+    _ => Err(unknown_method(
+      &Value::Module(FirebaseType::DurationModule),
+      name,
+    )),
+  }
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut encoded = String::new();
+
+  for chunk in bytes.chunks(3) {
+    let buffer = chunk
+      .iter()
+      .enumerate()
+      .fold(0u32, |acc, (i, byte)| acc | (*byte as u32) << (16 - 8 * i));
+
+    for i in 0..4 {
+      if i <= chunk.len() {
+        encoded.push(ALPHABET[(buffer >> (18 - 6 * i) & 0x3F) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+
+  encoded
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn string(s: &str) -> Value {
+    Value::String(s.to_owned())
+  }
+
+  fn strings(values: &[&str]) -> Vec<Value> {
+    values.iter().map(|s| string(s)).collect()
+  }
+
+  fn map(entries: &[(&str, Value)]) -> Value {
+    Value::Map(
+      entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn string_methods() {
+    let s = string(" Hello ");
+
+    assert_eq!(call_method(&s, "trim", &[]).unwrap(), string("Hello"));
+    assert_eq!(call_method(&s, "lower", &[]).unwrap(), string(" hello "));
+    assert_eq!(call_method(&s, "size", &[]).unwrap(), Value::Integer(7));
+
+    let matches = call_method(&string("abc"), "matches", &[string("a.c")]);
+    assert_eq!(matches.unwrap(), Value::Boolean(true));
+
+    // The whole string has to match
+    let matches = call_method(&string("abcd"), "matches", &[string("a.c")]);
+    assert_eq!(matches.unwrap(), Value::Boolean(false));
+
+    let split = call_method(&string("a,b"), "split", &[string(",")]);
+    assert_eq!(split.unwrap(), Value::List(strings(&["a", "b"])));
+  }
+
+  #[test]
+  fn list_and_set_methods() {
+    let list = Value::List(strings(&["a", "b", "a"]));
+
+    assert_eq!(
+      call_method(&list, "toSet", &[]).unwrap(),
+      Value::Set(strings(&["a", "b"]))
+    );
+
+    let has_only = call_method(&list, "hasOnly", &[Value::List(strings(&["a", "b"]))]);
+    assert_eq!(has_only.unwrap(), Value::Boolean(true));
+
+    let has_all = call_method(&list, "hasAll", &[Value::List(strings(&["a", "c"]))]);
+    assert_eq!(has_all.unwrap(), Value::Boolean(false));
+
+    let set = Value::Set(strings(&["a", "b"]));
+    let difference = call_method(&set, "difference", &[Value::Set(strings(&["b"]))]);
+    assert_eq!(difference.unwrap(), Value::Set(strings(&["a"])));
+  }
+
+  #[test]
+  fn map_methods() {
+    let before = map(&[("a", Value::Integer(1)), ("b", Value::Integer(2))]);
+    let after = map(&[
+      ("a", Value::Integer(1)),
+      ("b", Value::Integer(3)),
+      ("c", Value::Null),
+    ]);
+
+    let diff = call_method(&after, "diff", std::slice::from_ref(&before)).unwrap();
+    let affected = call_method(&diff, "affectedKeys", &[]);
+    assert_eq!(affected.unwrap(), Value::Set(strings(&["b", "c"])));
+
+    let removed = call_method(&diff, "removedKeys", &[]);
+    assert_eq!(removed.unwrap(), Value::Set(strings(&["c"])));
+
+    let nested = map(&[("inner", before)]);
+    let get = call_method(
+      &nested,
+      "get",
+      &[Value::List(strings(&["inner", "b"])), Value::Null],
+    );
+    assert_eq!(get.unwrap(), Value::Integer(2));
+
+    let missing = call_method(&nested, "get", &[string("missing"), Value::Integer(0)]);
+    assert_eq!(missing.unwrap(), Value::Integer(0));
+  }
+
+  #[test]
+  fn time_functions() {
+    let timestamp = Value::Module(FirebaseType::TimestampModule);
+    let duration = Value::Module(FirebaseType::DurationModule);
+
+    let date = call_method(
+      &timestamp,
+      "date",
+      &[Value::Integer(2024), Value::Integer(2), Value::Integer(29)],
+    )
+    .unwrap();
+
+    assert_eq!(call_method(&date, "month", &[]).unwrap(), Value::Integer(2));
+    assert_eq!(
+      call_method(&date, "dayOfYear", &[]).unwrap(),
+      Value::Integer(60)
+    );
+
+    let hour = call_method(&duration, "value", &[Value::Integer(1), string("h")]);
+    assert_eq!(hour.unwrap(), Value::Duration(3600 * NANOS_PER_SECOND));
+
+    let unit = call_method(&duration, "value", &[Value::Integer(1), string("y")]);
+    assert!(unit.is_err());
+  }
+
+  #[test]
+  fn global_functions() {
+    let int = call_global_function("int", &[string("42")]).unwrap();
+    assert_eq!(int.unwrap(), Value::Integer(42));
+
+    let float = call_global_function("float", &[Value::Integer(1)]).unwrap();
+    assert_eq!(float.unwrap(), Value::Float(1.0));
+
+    let path = call_global_function("path", &[string("/users/alice")]).unwrap();
+    assert_eq!(path.unwrap(), Value::Path(split_path("users/alice")));
+
+    assert!(call_global_function("int", &[]).unwrap().is_err());
+    assert!(call_global_function("unknown", &[]).is_none());
+  }
+
+  #[test]
+  fn unknown_methods() {
+    assert!(call_method(&Value::Integer(1), "size", &[]).is_err());
+    assert!(call_method(&string("a"), "unknown", &[]).is_err());
+    assert!(call_method(&Value::Null, "size", &[]).is_err());
+  }
+}
@@ -0,0 +1,980 @@
+use std::{
+  cell::RefCell,
+  collections::BTreeMap,
+  fmt::{Display, Formatter},
+};
+
+use tree_sitter::Point;
+
+use super::{
+  builtins::{call_global_function, call_method},
+  request::{MockDatabase, MockRequest},
+  value::*,
+};
+use crate::{
+  parser::{base::*, types::FirebaseType},
+  provider::document_accesses::{MAX_CALL_DEPTH, MAX_DOCUMENT_ACCESSES},
+};
+
+#[derive(Debug, Clone)]
+pub struct EvaluationError {
+  message: String,
+  span: (Point, Point),
+}
+
+impl EvaluationError {
+  pub fn new(message: String, span: (Point, Point)) -> Self {
+    Self { message, span }
+  }
+
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  pub fn span(&self) -> (Point, Point) {
+    self.span
+  }
+}
+
+impl Display for EvaluationError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} (line {}, column {})",
+      self.message,
+      self.span.0.row + 1,
+      self.span.0.column + 1
+    )
+  }
+}
+
+pub type Evaluation = Result<Value, EvaluationError>;
+
+/// Functions and variables declared on one level of the document, e.g. the functions of
+/// a match body together with its wildcard bindings, or the parameters and `let` bindings
+/// of a function call
+#[derive(Debug, Clone)]
+pub struct Scope<'t> {
+  functions: &'t [Function],
+  variables: Vec<(String, Evaluation)>,
+}
+
+impl<'t> Scope<'t> {
+  pub fn new(functions: &'t [Function], variables: Vec<(String, Value)>) -> Self {
+    Self {
+      functions,
+      variables: variables
+        .into_iter()
+        .map(|(name, value)| (name, Ok(value)))
+        .collect(),
+    }
+  }
+}
+
+pub struct Interpreter<'r> {
+  request: &'r MockRequest,
+  database: &'r MockDatabase,
+  globals: Vec<(&'static str, Value)>,
+  accessed_documents: RefCell<Vec<Vec<String>>>,
+}
+
+impl<'r> Interpreter<'r> {
+  pub fn new(request: &'r MockRequest, database: &'r MockDatabase) -> Self {
+    let request_resource = request
+      .resource_data()
+      .map_or(Value::Null, |data| resource_object(request.path(), data));
+
+    let resource = database
+      .document(request.path())
+      .map_or(Value::Null, |data| resource_object(request.path(), data));
+
+    let request_object = Value::Object(
+      FirebaseType::Request,
+      BTreeMap::from([
+        (
+          "auth".to_owned(),
+          request.auth().cloned().unwrap_or(Value::Null),
+        ),
+        (
+          "method".to_owned(),
+          Value::String(method_name(request.method()).to_owned()),
+        ),
+        ("path".to_owned(), Value::Path(request.path().to_vec())),
+        ("query".to_owned(), Value::Map(BTreeMap::new())),
+        ("resource".to_owned(), request_resource),
+        ("time".to_owned(), Value::Timestamp(request.time())),
+      ]),
+    );
+
+    Self {
+      request,
+      database,
+      globals: vec![
+        ("request", request_object),
+        ("resource", resource),
+        ("math", Value::Module(FirebaseType::MathModule)),
+        ("hashing", Value::Module(FirebaseType::HashingModule)),
+        ("latlng", Value::Module(FirebaseType::LatLngModule)),
+        ("timestamp", Value::Module(FirebaseType::TimestampModule)),
+        ("duration", Value::Module(FirebaseType::DurationModule)),
+      ],
+      accessed_documents: RefCell::new(vec![]),
+    }
+  }
+
+  /// Evaluates the condition of a rule. Rules without a condition always evaluate to true.
+  pub fn evaluate_rule<'t>(&self, rule: &'t Rule, environment: &[Scope<'t>]) -> Evaluation {
+    match rule.condition() {
+      Some(condition) => self.evaluate(condition, environment, 0),
+      None => Ok(Value::Boolean(true)),
+    }
+  }
+
+  pub fn evaluate<'t>(&self, node: &'t ExprNode, env: &[Scope<'t>], depth: usize) -> Evaluation {
+    let error = |message: String| EvaluationError::new(message, node.span());
+
+    match node.expr() {
+      Expr::Literal(literal) => literal_value(literal).map_err(error),
+      Expr::Variable(ident) => self.variable(ident.value(), env, node),
+      Expr::ExprGroup(inner) | Expr::MemberObject(inner) => {
+        self.evaluate(required(inner, node)?, env, depth)
+      }
+      Expr::List(elements) => elements
+        .iter()
+        .map(|el| self.evaluate(el, env, depth))
+        .collect::<Result<Vec<Value>, EvaluationError>>()
+        .map(Value::List),
+      Expr::Map(entries) => {
+        let mut map = BTreeMap::new();
+
+        for entry in entries {
+          let Expr::MapEntry(key, value) = entry.expr() else {
+            return Err(error("Invalid map entry".to_owned()));
+          };
+
+          let key = self.evaluate(required(key, entry)?, env, depth)?;
+          let value = self.evaluate(required(value, entry)?, env, depth)?;
+
+          match key {
+            Value::String(key) => map.insert(key, value),
+            key => {
+              return Err(error(format!(
+                "Map keys must be strings but got {}",
+                key.type_name()
+              )));
+            }
+          };
+        }
+
+        Ok(Value::Map(map))
+      }
+      Expr::Unary(operation, operand) => {
+        let value = self.evaluate(required(operand, node)?, env, depth)?;
+
+        match (operation, value) {
+          (Some(Operation::Negation), Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+          (Some(Operation::Substraction), Value::Integer(i)) => i
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or(error("Integer overflow".to_owned())),
+          (Some(Operation::Substraction), Value::Float(f)) => Ok(Value::Float(-f)),
+          (_, value) => Err(error(format!(
+            "Unary operator can not be applied to {}",
+            value.type_name()
+          ))),
+        }
+      }
+      Expr::Binary(Some(Operation::And), left, right) => self.logical(
+        false,
+        required(left, node)?,
+        required(right, node)?,
+        env,
+        depth,
+      ),
+      Expr::Binary(Some(Operation::Or), left, right) => self.logical(
+        true,
+        required(left, node)?,
+        required(right, node)?,
+        env,
+        depth,
+      ),
+      Expr::Binary(Some(operation), left, right) => {
+        let left = self.evaluate(required(left, node)?, env, depth)?;
+        let right = self.evaluate(required(right, node)?, env, depth)?;
+
+        binary_operation(operation, left, right).map_err(error)
+      }
+      Expr::Binary(None, _, _) => Err(error("Unknown operator".to_owned())),
+      Expr::Ternary(condition, on_true, on_false) => {
+        let condition = self.evaluate(required(condition, node)?, env, depth)?;
+
+        if condition.as_bool().map_err(error)? {
+          self.evaluate(required(on_true, node)?, env, depth)
+        } else {
+          self.evaluate(required(on_false, node)?, env, depth)
+        }
+      }
+      Expr::TypeComparison(operand, compared_type) => {
+        let value = self.evaluate(required(operand, node)?, env, depth)?;
+
+        match compared_type {
+          Some(compared_type) => Ok(Value::Boolean(value.is_of_type(*compared_type))),
+          None => Err(error("Unknown type".to_owned())),
+        }
+      }
+      Expr::Member(object, field) => {
+        let object = self.evaluate(required(object, node)?, env, depth)?;
+        self.member(object, required(field, node)?, env, depth)
+      }
+      Expr::Indexing(object, index) => {
+        let object = self.evaluate(required(object, node)?, env, depth)?;
+        self.index(object, required(index, node)?, env, depth)
+      }
+      Expr::FunctionCall(ident, args) => {
+        let args = args
+          .iter()
+          .map(|arg| self.evaluate(arg, env, depth))
+          .collect::<Result<Vec<Value>, EvaluationError>>()?;
+
+        self.call_function(ident.value(), args, env, depth, node)
+      }
+      Expr::Path(segments) => {
+        let mut path = vec![];
+
+        for segment in segments {
+          match segment.expr() {
+            Expr::Literal(literal) => path.push(literal.value().to_owned()),
+            _ => match self.evaluate(segment, env, depth)? {
+              Value::String(s) => path.push(s),
+              Value::Integer(i) => path.push(i.to_string()),
+              Value::Path(segments) => path.extend(segments),
+              value => {
+                return Err(EvaluationError::new(
+                  format!("Can not use {} as a path segment", value.type_name()),
+                  segment.span(),
+                ));
+              }
+            },
+          }
+        }
+
+        Ok(Value::Path(path))
+      }
+      Expr::MemberVariable(_) | Expr::MemberFunction(_, _) | Expr::MapEntry(_, _) => {
+        Err(error("Unexpected expression".to_owned()))
+      }
+      Expr::Range(_, _) => Err(error("Ranges can only be used for indexing".to_owned())),
+    }
+  }
+
+  /// `&&` and `||` are short circuiting and tolerate errors on either side if
+  /// the other side already decides the result
+  fn logical<'t>(
+    &self,
+    deciding: bool,
+    left: &'t ExprNode,
+    right: &'t ExprNode,
+    env: &[Scope<'t>],
+    depth: usize,
+  ) -> Evaluation {
+    let as_bool = |node: &ExprNode, evaluation: Evaluation| {
+      evaluation.and_then(|value| {
+        value
+          .as_bool()
+          .map_err(|message| EvaluationError::new(message, node.span()))
+      })
+    };
+
+    let left_result = as_bool(left, self.evaluate(left, env, depth));
+
+    if left_result.as_ref().is_ok_and(|b| *b == deciding) {
+      return Ok(Value::Boolean(deciding));
+    }
+
+    let right_result = as_bool(right, self.evaluate(right, env, depth));
+
+    if right_result.as_ref().is_ok_and(|b| *b == deciding) {
+      return Ok(Value::Boolean(deciding));
+    }
+
+    left_result?;
+    right_result?;
+
+    Ok(Value::Boolean(!deciding))
+  }
+
+  fn variable<'t>(&self, name: &str, env: &[Scope<'t>], node: &ExprNode) -> Evaluation {
+    let scoped = env.iter().rev().find_map(|scope| {
+      scope
+        .variables
+        .iter()
+        .rev()
+        .find(|(variable, _)| variable == name)
+    });
+
+    if let Some((_, evaluation)) = scoped {
+      return evaluation.clone();
+    }
+
+    self
+      .globals
+      .iter()
+      .find(|(global, _)| *global == name)
+      .map(|(_, value)| value.clone())
+      .ok_or(EvaluationError::new(
+        format!("Variable {} is not defined", name),
+        node.span(),
+      ))
+  }
+
+  fn member<'t>(
+    &self,
+    object: Value,
+    field: &'t ExprNode,
+    env: &[Scope<'t>],
+    depth: usize,
+  ) -> Evaluation {
+    let error = |message: String| EvaluationError::new(message, field.span());
+
+    match field.expr() {
+      Expr::MemberVariable(ident) => property(&object, ident.value()).map_err(error),
+      Expr::MemberFunction(ident, args) => {
+        let args = args
+          .iter()
+          .map(|arg| self.evaluate(arg, env, depth))
+          .collect::<Result<Vec<Value>, EvaluationError>>()?;
+
+        call_method(&object, ident.value(), &args).map_err(error)
+      }
+      Expr::Indexing(inner_field, index) => {
+        let value = self.member(object, required(inner_field, field)?, env, depth)?;
+        self.index(value, required(index, field)?, env, depth)
+      }
+      _ => Err(error("Invalid member access".to_owned())),
+    }
+  }
+
+  fn index<'t>(
+    &self,
+    object: Value,
+    index: &'t ExprNode,
+    env: &[Scope<'t>],
+    depth: usize,
+  ) -> Evaluation {
+    let error = |message: String| EvaluationError::new(message, index.span());
+
+    if let Expr::Range(start, end) = index.expr() {
+      let start = self
+        .evaluate(required(start, index)?, env, depth)?
+        .as_integer()
+        .map_err(error)?;
+      let end = self
+        .evaluate(required(end, index)?, env, depth)?
+        .as_integer()
+        .map_err(error)?;
+
+      return slice(object, start, end).map_err(error);
+    }
+
+    let key = self.evaluate(index, env, depth)?;
+
+    match (&object, &key) {
+      (Value::Map(_) | Value::Object(_, _), Value::String(key)) => property(&object, key),
+      (Value::List(elements), Value::Integer(i)) => element(elements, *i).cloned(),
+      (Value::Path(segments), Value::Integer(i)) => {
+        element(segments, *i).map(|segment| Value::String(segment.clone()))
+      }
+      (Value::String(s), Value::Integer(i)) => {
+        let chars: Vec<char> = s.chars().collect();
+        element(&chars, *i).map(|c| Value::String(c.to_string()))
+      }
+      (Value::Null, _) => Err("Null value error.".to_owned()),
+      _ => Err(format!(
+        "Can not index {} with {}",
+        object.type_name(),
+        key.type_name()
+      )),
+    }
+    .map_err(error)
+  }
+
+  fn call_function<'t>(
+    &self,
+    name: &str,
+    args: Vec<Value>,
+    env: &[Scope<'t>],
+    depth: usize,
+    node: &ExprNode,
+  ) -> Evaluation {
+    let error = |message: String| EvaluationError::new(message, node.span());
+
+    let user_function = env.iter().enumerate().rev().find_map(|(i, scope)| {
+      scope
+        .functions
+        .iter()
+        .find(|f| f.name().is_some_and(|fname| fname.value() == name))
+        .map(|f| (i, f))
+    });
+
+    if let Some((scope_index, function)) = user_function {
+      if depth >= MAX_CALL_DEPTH {
+        return Err(error(format!(
+          "Maximum function call depth of {} exceeded",
+          MAX_CALL_DEPTH
+        )));
+      }
+
+      if function.parameters().len() != args.len() {
+        return Err(error(format!(
+          "Function {}() expects {} argument(s) but got {}",
+          name,
+          function.parameters().len(),
+          args.len()
+        )));
+      }
+
+      let params = function
+        .parameters()
+        .iter()
+        .map(|param| param.name().to_owned())
+        .zip(args)
+        .collect();
+
+      // Functions only see the scopes of where they are declared
+      let mut function_env = env[..=scope_index].to_vec();
+      function_env.push(Scope::new(&[], params));
+
+      let body = function
+        .body()
+        .ok_or(error(format!("Function {}() has no body", name)))?;
+
+      for variable_def in body.variable_defs() {
+        let evaluation = match variable_def.definition() {
+          Some(definition) => self.evaluate(definition, &function_env, depth + 1),
+          None => Err(EvaluationError::new(
+            format!("Variable {} has no value", variable_def.name()),
+            variable_def.to_base_model().span(),
+          )),
+        };
+
+        // Errors only surface when the variable is actually used
+        function_env
+          .last_mut()
+          .unwrap()
+          .variables
+          .push((variable_def.name().to_owned(), evaluation));
+      }
+
+      let ret = body.ret().ok_or(error(format!(
+        "Function {}() does not return a value",
+        name
+      )))?;
+
+      return self.evaluate(ret, &function_env, depth + 1);
+    }
+
+    match name {
+      "get" | "getAfter" | "exists" | "existsAfter" => {
+        let path = match args.as_slice() {
+          [Value::Path(path)] => path,
+          _ => {
+            return Err(error(format!(
+              "Function {}() expects a single path argument",
+              name
+            )));
+          }
+        };
+
+        let document = self
+          .document(path, name.ends_with("After"))
+          .map_err(error)?;
+
+        match name {
+          "get" | "getAfter" => {
+            Ok(document.map_or(Value::Null, |data| resource_object(path, &data)))
+          }
+          _ => Ok(Value::Boolean(document.is_some())),
+        }
+      }
+      _ => call_global_function(name, &args)
+        .unwrap_or(Err(format!("Function {}() is not defined", name)))
+        .map_err(error),
+    }
+  }
+
+  /// Looks up the data of a document, either before or after the request is applied
+  fn document(&self, path: &[String], after: bool) -> Result<Option<Value>, String> {
+    let mut accessed = self.accessed_documents.borrow_mut();
+
+    if !accessed.iter().any(|p| p == path) {
+      if accessed.len() >= MAX_DOCUMENT_ACCESSES {
+        return Err(format!(
+          "Exceeded the maximum of {} document accesses",
+          MAX_DOCUMENT_ACCESSES
+        ));
+      }
+
+      accessed.push(path.to_vec());
+    }
+
+    if after && path == self.request.path() {
+      return Ok(match self.request.method() {
+        MethodType::Create | MethodType::Update | MethodType::Write => {
+          self.request.resource_data().cloned()
+        }
+        MethodType::Delete => None,
+        _ => self.database.document(path).cloned(),
+      });
+    }
+
+    Ok(self.database.document(path).cloned())
+  }
+}
+
+fn required<'t>(
+  node: &'t Option<Box<ExprNode>>,
+  parent: &ExprNode,
+) -> Result<&'t ExprNode, EvaluationError> {
+  node.as_deref().ok_or(EvaluationError::new(
+    "Incomplete expression".to_owned(),
+    parent.span(),
+  ))
+}
+
+pub fn method_name(method: &MethodType) -> &'static str {
+  match method {
+    MethodType::Read => "read",
+    MethodType::Write => "write",
+    MethodType::Get => "get",
+    MethodType::List => "list",
+    MethodType::Create => "create",
+    MethodType::Update => "update",
+    MethodType::Delete => "delete",
+    MethodType::Unknown => "unknown",
+  }
+}
+
+fn resource_object(path: &[String], data: &Value) -> Value {
+  Value::Object(
+    FirebaseType::Resource,
+    BTreeMap::from([
+      ("__name__".to_owned(), Value::Path(path.to_vec())),
+      (
+        "id".to_owned(),
+        Value::String(path.last().cloned().unwrap_or_default()),
+      ),
+      ("data".to_owned(), data.clone()),
+    ]),
+  )
+}
+
+fn property(object: &Value, name: &str) -> Result<Value, String> {
+  match object.as_map() {
+    Some(entries) => entries
+      .get(name)
+      .cloned()
+      .ok_or(format!("Property {} is undefined on object.", name)),
+    None if matches!(object, Value::Null) => Err("Null value error.".to_owned()),
+    None => Err(format!(
+      "Property {} is undefined on {}.",
+      name,
+      object.type_name()
+    )),
+  }
+}
+
+fn element<T>(elements: &[T], index: i64) -> Result<&T, String> {
+  usize::try_from(index)
+    .ok()
+    .and_then(|i| elements.get(i))
+    .ok_or(format!("Index {} out of range", index))
+}
+
+fn slice(object: Value, start: i64, end: i64) -> Result<Value, String> {
+  let bounds = |len: usize| -> Result<(usize, usize), String> {
+    match (usize::try_from(start), usize::try_from(end)) {
+      (Ok(start), Ok(end)) if start <= end && end <= len => Ok((start, end)),
+      _ => Err(format!("Range {}:{} out of bounds", start, end)),
+    }
+  };
+
+  match object {
+    Value::List(elements) => {
+      let (start, end) = bounds(elements.len())?;
+      Ok(Value::List(elements[start..end].to_vec()))
+    }
+    Value::String(s) => {
+      let chars: Vec<char> = s.chars().collect();
+      let (start, end) = bounds(chars.len())?;
+      Ok(Value::String(chars[start..end].iter().collect()))
+    }
+    _ => Err(format!("Can not take a range of {}", object.type_name())),
+  }
+}
+
+/// Evaluates the parts of an expression that do not depend on the request, the document
+/// or any definitions. `None` if the value is only known at evaluation time.
+pub fn fold_constant(node: &ExprNode) -> Option<Value> {
+  let fold = |operand: &Option<Box<ExprNode>>| operand.as_deref().and_then(fold_constant);
+
+  match node.expr() {
+    Expr::Literal(literal) => literal_value(literal).ok(),
+    Expr::ExprGroup(inner) => fold(inner),
+    Expr::List(elements) => elements
+      .iter()
+      .map(fold_constant)
+      .collect::<Option<Vec<Value>>>()
+      .map(Value::List),
+    Expr::Unary(operation, operand) => match (operation, fold(operand)?) {
+      (Some(Operation::Negation), Value::Boolean(b)) => Some(Value::Boolean(!b)),
+      (Some(Operation::Substraction), Value::Integer(i)) => i.checked_neg().map(Value::Integer),
+      (Some(Operation::Substraction), Value::Float(f)) => Some(Value::Float(-f)),
+      _ => None,
+    },
+    Expr::Binary(Some(operation @ (Operation::And | Operation::Or)), left, right) => {
+      // One side deciding the result is enough, like when short circuiting
+      let deciding = matches!(operation, Operation::Or);
+      let left = fold(left).and_then(|value| value.as_bool().ok());
+      let right = fold(right).and_then(|value| value.as_bool().ok());
+
+      match (left, right) {
+        (Some(b), _) | (_, Some(b)) if b == deciding => Some(Value::Boolean(deciding)),
+        (Some(_), Some(_)) => Some(Value::Boolean(!deciding)),
+        _ => None,
+      }
+    }
+    Expr::Binary(Some(operation), left, right) => {
+      binary_operation(operation, fold(left)?, fold(right)?).ok()
+    }
+    Expr::Ternary(condition, on_true, on_false) => {
+      match fold(condition).and_then(|value| value.as_bool().ok()) {
+        Some(true) => fold(on_true),
+        Some(false) => fold(on_false),
+        None => {
+          let on_true = fold(on_true)?;
+          (fold(on_false)? == on_true).then_some(on_true)
+        }
+      }
+    }
+    Expr::TypeComparison(operand, Some(compared_type)) => {
+      Some(Value::Boolean(fold(operand)?.is_of_type(*compared_type)))
+    }
+    _ => None,
+  }
+}
+
+fn literal_value(literal: &Literal) -> Result<Value, String> {
+  let raw = literal.value();
+
+  match literal.firebase_type().get() {
+    FirebaseType::Null => Ok(Value::Null),
+    FirebaseType::Boolean => Ok(Value::Boolean(raw == "true")),
+    FirebaseType::String => Ok(Value::String(unescape(raw))),
+    FirebaseType::Integer | FirebaseType::Float if raw.contains('.') => raw
+      .parse::<f64>()
+      .map(Value::Float)
+      .map_err(|_| format!("Invalid number {}", raw)),
+    FirebaseType::Integer | FirebaseType::Float => raw
+      .parse::<i64>()
+      .map(Value::Integer)
+      .map_err(|_| format!("Integer {} out of range", raw)),
+    _ => Ok(Value::String(raw.to_owned())),
+  }
+}
+
+/// Strips the quotes of a string literal and resolves its escape sequences
+fn unescape(raw: &str) -> String {
+  let inner = raw.get(1..raw.len().saturating_sub(1)).unwrap_or("");
+
+  let mut unescaped = String::new();
+  let mut chars = inner.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => unescaped.extend(chars.next()),
+      c => unescaped.push(c),
+    }
+  }
+
+  unescaped
+}
+
+fn binary_operation(operation: &Operation, left: Value, right: Value) -> Result<Value, String> {
+  let unsupported = |left: &Value, right: &Value| {
+    Err(format!(
+      "Operator {:?} can not be applied to {} and {}",
+      operation,
+      left.type_name(),
+      right.type_name()
+    ))
+  };
+
+  let overflow = || "Integer overflow".to_owned();
+
+  match operation {
+    Operation::Relation(operator) => {
+      let result = match operator {
+        RelationOperator::Equal => left == right,
+        RelationOperator::NotEqual => left != right,
+        RelationOperator::Less => left.compare(&right)?.is_lt(),
+        RelationOperator::LessEqual => left.compare(&right)?.is_le(),
+        RelationOperator::Greater => left.compare(&right)?.is_gt(),
+        RelationOperator::GreaterEqual => left.compare(&right)?.is_ge(),
+      };
+
+      Ok(Value::Boolean(result))
+    }
+    Operation::Contains => match &right {
+      Value::List(elements) | Value::Set(elements) => Ok(Value::Boolean(elements.contains(&left))),
+      Value::Map(entries) | Value::Object(_, entries) => match &left {
+        Value::String(key) => Ok(Value::Boolean(entries.contains_key(key))),
+        _ => Ok(Value::Boolean(false)),
+      },
+      _ => unsupported(&left, &right),
+    },
+    Operation::Addition => match (&left, &right) {
+      (Value::Integer(a), Value::Integer(b)) => {
+        a.checked_add(*b).map(Value::Integer).ok_or_else(overflow)
+      }
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        Ok(Value::Float(left.as_number()? + right.as_number()?))
+      }
+      (Value::String(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
+      (Value::List(a), Value::List(b)) => Ok(Value::List([a.clone(), b.clone()].concat())),
+      (Value::Timestamp(t), Value::Duration(d)) | (Value::Duration(d), Value::Timestamp(t)) => {
+        Ok(Value::Timestamp(t + d))
+      }
+      (Value::Duration(a), Value::Duration(b)) => Ok(Value::Duration(a + b)),
+      _ => unsupported(&left, &right),
+    },
+    Operation::Substraction => match (&left, &right) {
+      (Value::Integer(a), Value::Integer(b)) => {
+        a.checked_sub(*b).map(Value::Integer).ok_or_else(overflow)
+      }
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        Ok(Value::Float(left.as_number()? - right.as_number()?))
+      }
+      (Value::Timestamp(t), Value::Duration(d)) => Ok(Value::Timestamp(t - d)),
+      (Value::Timestamp(a), Value::Timestamp(b)) => Ok(Value::Duration(a - b)),
+      (Value::Duration(a), Value::Duration(b)) => Ok(Value::Duration(a - b)),
+      _ => unsupported(&left, &right),
+    },
+    Operation::Multiplication => match (&left, &right) {
+      (Value::Integer(a), Value::Integer(b)) => {
+        a.checked_mul(*b).map(Value::Integer).ok_or_else(overflow)
+      }
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        Ok(Value::Float(left.as_number()? * right.as_number()?))
+      }
+      _ => unsupported(&left, &right),
+    },
+    Operation::Division | Operation::Modulo => match (&left, &right) {
+      (Value::Integer(_), Value::Integer(0)) => Err("Division by zero".to_owned()),
+      (Value::Integer(a), Value::Integer(b)) => match operation {
+        Operation::Division => a.checked_div(*b),
+        _ => a.checked_rem(*b),
+      }
+      .map(Value::Integer)
+      .ok_or_else(overflow),
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        let (a, b) = (left.as_number()?, right.as_number()?);
+
+        Ok(Value::Float(match operation {
+          Operation::Division => a / b,
+          _ => a % b,
+        }))
+      }
+      _ => unsupported(&left, &right),
+    },
+    Operation::And | Operation::Or | Operation::Negation => unsupported(&left, &right),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulator::{matching::applicable_rules, parse_rules};
+
+  const POST: &str = "/databases/(default)/documents/posts/first";
+
+  /// Evaluates the condition of a get rule on posts for the request
+  fn evaluate_with(condition: &str, request: &MockRequest, database: &MockDatabase) -> Evaluation {
+    let source = format!(
+      "rules_version = '2';
+      function isAuthor(uid) {{
+        let author = resource.data.author;
+        return uid == author;
+      }}
+      function loop(n) {{
+        return loop(n + 1);
+      }}
+      service cloud.firestore {{
+        match /databases/{{database}}/documents {{
+          match /posts/{{postId}} {{
+            allow get: if {};
+          }}
+        }}
+      }}",
+      condition
+    );
+
+    let tree = parse_rules(&source);
+    let applicable = applicable_rules(&tree, request.path(), request.method());
+    let interpreter = Interpreter::new(request, database);
+
+    interpreter.evaluate_rule(applicable[0].rule(), applicable[0].environment())
+  }
+
+  fn evaluate(condition: &str) -> Evaluation {
+    evaluate_with(
+      condition,
+      &MockRequest::new(MethodType::Get, POST),
+      &MockDatabase::new(),
+    )
+  }
+
+  fn string(s: &str) -> Value {
+    Value::String(s.to_owned())
+  }
+
+  #[test]
+  fn evaluates_operators() {
+    assert_eq!(evaluate("1 + 2 * 3 == 7").unwrap(), Value::Boolean(true));
+    assert_eq!(
+      evaluate("7 / 2 == 3 && 7 % 2 == 1").unwrap(),
+      Value::Boolean(true)
+    );
+    assert_eq!(evaluate("1.5 + 1 > 2").unwrap(), Value::Boolean(true));
+    assert_eq!(evaluate("'a' + 'b' == 'ab'").unwrap(), Value::Boolean(true));
+    assert_eq!(evaluate("'b' in ['a', 'b']").unwrap(), Value::Boolean(true));
+    assert_eq!(
+      evaluate("!(1 < 2) ? false : true").unwrap(),
+      Value::Boolean(true)
+    );
+    assert_eq!(
+      evaluate("1 is int && 'a' is string").unwrap(),
+      Value::Boolean(true)
+    );
+  }
+
+  #[test]
+  fn reports_errors() {
+    assert!(evaluate("1 / 0 == 0").is_err());
+    assert!(evaluate("'a' < 1").is_err());
+    assert!(evaluate("resource.data.author == 'alice'").is_err());
+    assert!(evaluate("undefinedVariable").is_err());
+
+    let error = evaluate("1 + 'a' == 1").unwrap_err();
+    assert_eq!(error.span().0.row, 11);
+  }
+
+  #[test]
+  fn short_circuits() {
+    assert_eq!(
+      evaluate("false && 1 / 0 == 0").unwrap(),
+      Value::Boolean(false)
+    );
+    assert_eq!(
+      evaluate("true || 1 / 0 == 0").unwrap(),
+      Value::Boolean(true)
+    );
+  }
+
+  #[test]
+  fn resolves_wildcards_and_request() {
+    let request = MockRequest::new(MethodType::Get, POST).with_auth("alice", BTreeMap::new());
+    let database = MockDatabase::new();
+
+    let evaluation = evaluate_with(
+      "postId == 'first' && database == '(default)' && request.auth.uid == 'alice'",
+      &request,
+      &database,
+    );
+    assert_eq!(evaluation.unwrap(), Value::Boolean(true));
+
+    assert_eq!(
+      evaluate_with("request.method", &request, &database).unwrap(),
+      string("get")
+    );
+    assert_eq!(
+      evaluate("request.auth == null").unwrap(),
+      Value::Boolean(true)
+    );
+  }
+
+  #[test]
+  fn calls_functions() {
+    let request = MockRequest::new(MethodType::Get, POST).with_auth("alice", BTreeMap::new());
+    let mut database = MockDatabase::new();
+    database.insert(
+      POST,
+      Value::Map(BTreeMap::from([("author".to_owned(), string("alice"))])),
+    );
+
+    let evaluation = evaluate_with("isAuthor(request.auth.uid)", &request, &database);
+    assert_eq!(evaluation.unwrap(), Value::Boolean(true));
+
+    let evaluation = evaluate_with("isAuthor('bob')", &request, &database);
+    assert_eq!(evaluation.unwrap(), Value::Boolean(false));
+
+    assert!(evaluate("loop(0)").is_err());
+  }
+
+  #[test]
+  fn accesses_documents() {
+    let mut database = MockDatabase::new();
+    database.insert(
+      "/databases/(default)/documents/users/alice",
+      Value::Map(BTreeMap::from([("admin".to_owned(), Value::Boolean(true))])),
+    );
+    let request = MockRequest::new(MethodType::Get, POST);
+
+    let condition = "get(/databases/$(database)/documents/users/alice).data.admin";
+    let evaluation = evaluate_with(condition, &request, &database);
+    assert_eq!(evaluation.unwrap(), Value::Boolean(true));
+
+    let condition = "exists(/databases/$(database)/documents/users/bob)";
+    let evaluation = evaluate_with(condition, &request, &database);
+    assert_eq!(evaluation.unwrap(), Value::Boolean(false));
+  }
+
+  #[test]
+  fn limits_document_accesses() {
+    let accesses = |count: usize| {
+      (0..count)
+        .map(|i| format!("exists(/databases/$(database)/documents/users/u{})", i))
+        .collect::<Vec<String>>()
+        .join(" || ")
+    };
+
+    assert!(evaluate(&accesses(MAX_DOCUMENT_ACCESSES)).is_ok());
+    assert!(evaluate(&accesses(MAX_DOCUMENT_ACCESSES + 1)).is_err());
+  }
+
+  #[test]
+  fn folds_constants() {
+    let fold = |condition: &str| {
+      let tree = parse_rules(&format!(
+        "service cloud.firestore {{ match /a/{{b}} {{ allow get: if {}; }} }}",
+        condition
+      ));
+      let rule = &tree.service_body().unwrap().matches()[0]
+        .body()
+        .unwrap()
+        .rules()[0];
+      fold_constant(rule.condition().unwrap())
+    };
+
+    assert_eq!(fold("1 + 2"), Some(Value::Integer(3)));
+    assert_eq!(
+      fold("['a', 'b']"),
+      Some(Value::List(vec![string("a"), string("b")]))
+    );
+    assert_eq!(
+      fold("request.auth != null || true"),
+      Some(Value::Boolean(true))
+    );
+    assert_eq!(fold("request.auth != null && true"), None);
+    assert_eq!(fold("1 / 0"), None);
+  }
+}
@@ -0,0 +1,291 @@
+use super::{interpreter::Scope, value::Value};
+use crate::parser::base::*;
+
+/// A rule whose match path matched the requested document, together with the scopes
+/// (functions and wildcard bindings) visible from the rule
+pub struct ApplicableRule<'t> {
+  rule: &'t Rule,
+  environment: Vec<Scope<'t>>,
+}
+
+impl<'t> ApplicableRule<'t> {
+  pub fn rule(&self) -> &'t Rule {
+    self.rule
+  }
+
+  pub fn environment(&self) -> &[Scope<'t>] {
+    &self.environment
+  }
+}
+
+/// All rules granting the method whose match paths fully match the requested path,
+/// in the order they appear in the document
+pub fn applicable_rules<'t>(
+  tree: &'t RulesTree,
+  path: &[String],
+  method: &MethodType,
+) -> Vec<ApplicableRule<'t>> {
+  let mut applicable = vec![];
+
+  let Some(service_body) = tree.service_body() else {
+    return applicable;
+  };
+
+  let environment = vec![
+    Scope::new(tree.functions(), vec![]),
+    Scope::new(service_body.functions(), vec![]),
+  ];
+
+  if path.is_empty() {
+    push_rules(service_body.rules(), method, &environment, &mut applicable);
+  }
+
+  for m in service_body.matches() {
+    collect_match(
+      m,
+      path,
+      method,
+      tree.rules_version(),
+      &environment,
+      &mut applicable,
+    );
+  }
+
+  applicable
+}
+
+fn collect_match<'t>(
+  m: &'t Match,
+  remaining: &[String],
+  method: &MethodType,
+  version: RulesVersion,
+  environment: &[Scope<'t>],
+  applicable: &mut Vec<ApplicableRule<'t>>,
+) {
+  let (Some(path), Some(body)) = (m.path(), m.body()) else {
+    return;
+  };
+
+  for (bindings, consumed) in match_path_parts(path.path_parts(), remaining, version) {
+    let mut match_environment = environment.to_vec();
+    match_environment.push(Scope::new(body.functions(), bindings));
+
+    let rest = &remaining[consumed..];
+
+    if rest.is_empty() {
+      push_rules(body.rules(), method, &match_environment, applicable);
+    }
+
+    for nested in body.matches() {
+      collect_match(
+        nested,
+        rest,
+        method,
+        version,
+        &match_environment,
+        applicable,
+      );
+    }
+  }
+}
+
+fn push_rules<'t>(
+  rules: &'t [Rule],
+  method: &MethodType,
+  environment: &[Scope<'t>],
+  applicable: &mut Vec<ApplicableRule<'t>>,
+) {
+  for rule in rules {
+    let grants_method = rule
+      .methods()
+      .iter()
+      .any(|m| m.method_type().covers(method));
+
+    // A rule reachable through several wildcard bindings is only evaluated once
+    let already_applicable = applicable.iter().any(|a| std::ptr::eq(a.rule, rule));
+
+    if grants_method && !already_applicable {
+      applicable.push(ApplicableRule {
+        rule,
+        environment: environment.to_vec(),
+      });
+    }
+  }
+}
+
+/// Every way the match path parts can match a prefix of the segments, as the
+/// wildcard bindings and the number of consumed segments
+fn match_path_parts(
+  parts: &[MatchPathPart],
+  segments: &[String],
+  version: RulesVersion,
+) -> Vec<(Vec<(String, Value)>, usize)> {
+  let Some((part, rest_parts)) = parts.split_first() else {
+    return vec![(vec![], 0)];
+  };
+
+  let prepend = |binding: Option<(String, Value)>, consumed: usize| {
+    match_path_parts(rest_parts, &segments[consumed..], version)
+      .into_iter()
+      .map(move |(mut bindings, rest_consumed)| {
+        if let Some(binding) = binding.clone() {
+          bindings.insert(0, binding);
+        }
+
+        (bindings, consumed + rest_consumed)
+      })
+      .collect::<Vec<_>>()
+  };
+
+  match part.pathpart_type() {
+    MatchPathPartType::Collection => match segments.first() {
+      Some(segment) if part.value().trim_start_matches('/') == segment => prepend(None, 1),
+      _ => vec![],
+    },
+    MatchPathPartType::Document => match segments.first() {
+      Some(segment) => prepend(
+        Some((part.value().to_owned(), Value::String(segment.clone()))),
+        1,
+      ),
+      None => vec![],
+    },
+    // Recursive wildcards match zero or more segments in version 2, one or more in version 1
+    MatchPathPartType::MultiPath => (min_multi_path_segments(version)..=segments.len())
+      .flat_map(|consumed| {
+        prepend(
+          Some((
+            part.value().to_owned(),
+            Value::Path(segments[..consumed].to_vec()),
+          )),
+          consumed,
+        )
+      })
+      .collect(),
+  }
+}
+
+fn min_multi_path_segments(version: RulesVersion) -> usize {
+  match version {
+    RulesVersion::V1 => 1,
+    RulesVersion::V2 => 0,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::simulator::{
+    builtins::split_path,
+    interpreter::Interpreter,
+    parse_rules,
+    request::{MockDatabase, MockRequest},
+  };
+
+  const RULES: &str = "rules_version = '2';
+service cloud.firestore {
+  match /databases/{database}/documents {
+    match /users/{userId} {
+      allow read: if true;
+      allow update: if false;
+
+      match /posts/{postId} {
+        allow write: if true;
+      }
+    }
+
+    match /logs/{path=**} {
+      allow get: if true;
+    }
+  }
+}";
+
+  fn applicable_lines(tree: &RulesTree, path: &str, method: MethodType) -> Vec<usize> {
+    applicable_rules(tree, &split_path(path), &method)
+      .iter()
+      .map(|applicable| applicable.rule().span().0.row)
+      .collect()
+  }
+
+  /// Whether the condition holds for the rules applicable to a get of the path
+  fn holds_for(match_path: &str, condition: &str, path: &str) -> bool {
+    let source = format!(
+      "rules_version = '2';
+      service cloud.firestore {{
+        match {} {{
+          allow get: if {};
+        }}
+      }}",
+      match_path, condition
+    );
+
+    let tree = parse_rules(&source);
+    let request = MockRequest::new(MethodType::Get, path);
+    let database = MockDatabase::new();
+    let interpreter = Interpreter::new(&request, &database);
+
+    let applicable = applicable_rules(&tree, request.path(), &MethodType::Get);
+
+    !applicable.is_empty()
+      && applicable.iter().all(|applicable| {
+        let outcome = interpreter.evaluate_rule(applicable.rule(), applicable.environment());
+        matches!(outcome, Ok(Value::Boolean(true)))
+      })
+  }
+
+  #[test]
+  fn matches_rules_granting_the_method() {
+    let tree = parse_rules(RULES);
+    let users = "/databases/(default)/documents/users/alice";
+
+    assert_eq!(applicable_lines(&tree, users, MethodType::Get), vec![4]);
+    assert_eq!(applicable_lines(&tree, users, MethodType::Update), vec![5]);
+    assert!(applicable_lines(&tree, users, MethodType::Create).is_empty());
+  }
+
+  #[test]
+  fn matches_only_full_paths() {
+    let tree = parse_rules(RULES);
+
+    let posts = "/databases/(default)/documents/users/alice/posts/first";
+    assert_eq!(applicable_lines(&tree, posts, MethodType::Create), vec![8]);
+
+    let collection = "/databases/(default)/documents/users";
+    assert!(applicable_lines(&tree, collection, MethodType::Get).is_empty());
+  }
+
+  #[test]
+  fn binds_wildcards() {
+    let path = "/users/alice/posts/first";
+
+    assert!(holds_for(
+      "/users/{userId}/posts/{postId}",
+      "userId == 'alice' && postId == 'first'",
+      path
+    ));
+    assert!(holds_for(
+      "/users/{rest=**}",
+      "rest == path('alice/posts/first')",
+      path
+    ));
+    assert!(holds_for("/{rest=**}", "rest == path('/')", "/"));
+  }
+
+  #[test]
+  fn recursive_wildcards_match_any_depth() {
+    let tree = parse_rules(RULES);
+
+    let entry = "/databases/(default)/documents/logs/2024/01/entry";
+    assert_eq!(applicable_lines(&tree, entry, MethodType::Get), vec![13]);
+
+    let logs = "/databases/(default)/documents/logs";
+    assert_eq!(applicable_lines(&tree, logs, MethodType::Get), vec![13]);
+  }
+
+  #[test]
+  fn recursive_wildcards_need_a_segment_in_version_one() {
+    let tree = parse_rules(&RULES.replace("rules_version = '2';", ""));
+    let path = "/databases/(default)/documents/logs/entry";
+
+    assert_eq!(applicable_lines(&tree, path, MethodType::Get), vec![13]);
+  }
+}
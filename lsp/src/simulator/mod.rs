@@ -0,0 +1,122 @@
+pub mod builtins;
+pub mod interpreter;
+pub mod matching;
+pub mod request;
+pub mod value;
+
+use interpreter::{EvaluationError, Interpreter};
+use matching::applicable_rules;
+use request::{MockDatabase, MockRequest};
+use value::Value;
+
+use crate::parser::base::{MethodType, Rule, RulesTree, Spanned};
+
+#[derive(Debug, Clone)]
+pub enum RuleOutcome {
+  Allow,
+  Deny,
+  Error(EvaluationError),
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleEvaluation<'t> {
+  rule: &'t Rule,
+  outcome: RuleOutcome,
+}
+
+impl<'t> RuleEvaluation<'t> {
+  pub fn rule(&self) -> &'t Rule {
+    self.rule
+  }
+
+  pub fn outcome(&self) -> &RuleOutcome {
+    &self.outcome
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationResult<'t> {
+  method: MethodType,
+  evaluations: Vec<RuleEvaluation<'t>>,
+}
+
+impl<'t> SimulationResult<'t> {
+  pub fn method(&self) -> &MethodType {
+    &self.method
+  }
+
+  pub fn allowed(&self) -> bool {
+    self
+      .evaluations
+      .iter()
+      .any(|evaluation| matches!(evaluation.outcome, RuleOutcome::Allow))
+  }
+
+  /// The first rule that granted the request, or if it was denied the last rule that was
+  /// evaluated for it. `None` if no rule applied to the request at all.
+  pub fn deciding_rule(&self) -> Option<&RuleEvaluation<'t>> {
+    self
+      .evaluations
+      .iter()
+      .find(|evaluation| matches!(evaluation.outcome, RuleOutcome::Allow))
+      .or(self.evaluations.last())
+  }
+
+  /// Every rule that applied to the request, in document order
+  pub fn evaluations(&self) -> &[RuleEvaluation<'t>] {
+    &self.evaluations
+  }
+}
+
+/// Simulates whether the rules allow the given request. Every rule whose match path matches
+/// the requested document and whose methods cover the requested method is evaluated.
+pub fn simulate<'t>(
+  tree: &'t RulesTree,
+  request: &MockRequest,
+  database: &MockDatabase,
+) -> SimulationResult<'t> {
+  let interpreter = Interpreter::new(request, database);
+
+  let evaluations = applicable_rules(tree, request.path(), request.method())
+    .into_iter()
+    .map(|applicable| {
+      let outcome = match interpreter.evaluate_rule(applicable.rule(), applicable.environment()) {
+        Ok(Value::Boolean(true)) => RuleOutcome::Allow,
+        Ok(Value::Boolean(false)) => RuleOutcome::Deny,
+        Ok(value) => RuleOutcome::Error(EvaluationError::new(
+          format!(
+            "Rule condition must evaluate to a bool but got {}",
+            value.type_name()
+          ),
+          applicable
+            .rule()
+            .condition()
+            .map_or(applicable.rule().span(), |condition| condition.span()),
+        )),
+        Err(err) => RuleOutcome::Error(err),
+      };
+
+      RuleEvaluation {
+        rule: applicable.rule(),
+        outcome,
+      }
+    })
+    .collect();
+
+  SimulationResult {
+    method: *request.method(),
+    evaluations,
+  }
+}
+
+/// Parses and evaluates a rules document for the tests of the simulator
+#[cfg(test)]
+pub(crate) fn parse_rules(source: &str) -> RulesTree {
+  let mut parser = tree_sitter::Parser::new();
+  parser
+    .set_language(&tree_sitter_firestore_rules::LANGUAGE.into())
+    .unwrap();
+
+  let tree = parser.parse(source, None).unwrap();
+  crate::parser::evaluation::evaluate_tree(tree, source.as_bytes())
+}
@@ -0,0 +1,106 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{builtins::split_path, value::Value};
+use crate::parser::{base::MethodType, types::FirebaseType};
+
+/// The request a simulation is run for
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+  method: MethodType,
+  path: Vec<String>,
+  auth: Option<Value>,
+  resource_data: Option<Value>,
+  time: i128,
+}
+
+impl MockRequest {
+  /// # Arguments
+  /// * `path` - The full path of the requested document, e.g. `/databases/(default)/documents/users/alice`
+  pub fn new(method: MethodType, path: &str) -> Self {
+    let time = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |since_epoch| since_epoch.as_nanos() as i128);
+
+    Self {
+      method,
+      path: split_path(path),
+      auth: None,
+      resource_data: None,
+      time,
+    }
+  }
+
+  /// Authenticates the request as the given user with the given token claims
+  pub fn with_auth(mut self, uid: &str, token: BTreeMap<String, Value>) -> Self {
+    self.auth = Some(Value::Object(
+      FirebaseType::Auth,
+      BTreeMap::from([
+        ("uid".to_owned(), Value::String(uid.to_owned())),
+        (
+          "token".to_owned(),
+          Value::Object(FirebaseType::Token, token),
+        ),
+      ]),
+    ));
+    self
+  }
+
+  /// The data of the document as it would be after a write, i.e. `request.resource.data`
+  pub fn with_resource_data(mut self, data: Value) -> Self {
+    self.resource_data = Some(data);
+    self
+  }
+
+  /// # Arguments
+  /// * `time` - Nanoseconds since the unix epoch
+  pub fn with_time(mut self, time: i128) -> Self {
+    self.time = time;
+    self
+  }
+
+  pub fn method(&self) -> &MethodType {
+    &self.method
+  }
+
+  pub fn path(&self) -> &[String] {
+    &self.path
+  }
+
+  pub fn auth(&self) -> Option<&Value> {
+    self.auth.as_ref()
+  }
+
+  pub fn resource_data(&self) -> Option<&Value> {
+    self.resource_data.as_ref()
+  }
+
+  pub fn time(&self) -> i128 {
+    self.time
+  }
+}
+
+/// The documents that exist before the simulated request is executed
+#[derive(Debug, Clone, Default)]
+pub struct MockDatabase {
+  documents: HashMap<Vec<String>, Value>,
+}
+
+impl MockDatabase {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// # Arguments
+  /// * `path` - The full path of the document
+  /// * `data` - The document fields
+  pub fn insert(&mut self, path: &str, data: Value) {
+    self.documents.insert(split_path(path), data);
+  }
+
+  pub fn document(&self, path: &[String]) -> Option<&Value> {
+    self.documents.get(path)
+  }
+}
@@ -0,0 +1,322 @@
+use std::{
+  cmp::Ordering,
+  collections::BTreeMap,
+  fmt::{Display, Formatter},
+};
+
+use crate::parser::types::FirebaseType;
+
+pub const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// A runtime value of the rules language
+#[derive(Debug, Clone)]
+pub enum Value {
+  Null,
+  Boolean(bool),
+  Integer(i64),
+  Float(f64),
+  String(String),
+  Bytes(Vec<u8>),
+  List(Vec<Value>),
+  Map(BTreeMap<String, Value>),
+  /// Unique values in insertion order
+  Set(Vec<Value>),
+  /// The left and right map of a `map.diff()` call
+  MapDiff(BTreeMap<String, Value>, BTreeMap<String, Value>),
+  Path(Vec<String>),
+  /// Nanoseconds since the unix epoch
+  Timestamp(i128),
+  /// Nanoseconds
+  Duration(i128),
+  LatLng(f64, f64),
+  /// Map like objects with a well known shape such as `request`, `resource` or `request.auth`
+  Object(FirebaseType, BTreeMap<String, Value>),
+  /// The built in namespaces `math`, `hashing`, `latlng`, `timestamp` and `duration`
+  Module(FirebaseType),
+}
+
+impl Value {
+  pub fn firebase_type(&self) -> FirebaseType {
+    match self {
+      Value::Null => FirebaseType::Null,
+      Value::Boolean(_) => FirebaseType::Boolean,
+      Value::Integer(_) => FirebaseType::Integer,
+      Value::Float(_) => FirebaseType::Float,
+      Value::String(_) => FirebaseType::String,
+      Value::Bytes(_) => FirebaseType::Bytes,
+      Value::List(_) => FirebaseType::List,
+      Value::Map(_) => FirebaseType::Map,
+      Value::Set(_) => FirebaseType::Set,
+      Value::MapDiff(_, _) => FirebaseType::MapDiff,
+      Value::Path(_) => FirebaseType::Path,
+      Value::Timestamp(_) => FirebaseType::Timestamp,
+      Value::Duration(_) => FirebaseType::Duration,
+      Value::LatLng(_, _) => FirebaseType::LatLng,
+      Value::Object(firebase_type, _) => *firebase_type,
+      Value::Module(firebase_type) => *firebase_type,
+    }
+  }
+
+  /// The type name as used in `is` checks and error messages
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Value::Null => "null",
+      Value::Boolean(_) => "bool",
+      Value::Integer(_) => "int",
+      Value::Float(_) => "float",
+      Value::String(_) => "string",
+      Value::Bytes(_) => "bytes",
+      Value::List(_) => "list",
+      Value::Map(_) | Value::Object(_, _) => "map",
+      Value::Set(_) => "set",
+      Value::MapDiff(_, _) => "map_diff",
+      Value::Path(_) => "path",
+      Value::Timestamp(_) => "timestamp",
+      Value::Duration(_) => "duration",
+      Value::LatLng(_, _) => "latlng",
+      Value::Module(_) => "namespace",
+    }
+  }
+
+  /// Whether the value satisfies `value is <firebase_type>`
+  pub fn is_of_type(&self, firebase_type: FirebaseType) -> bool {
+    match firebase_type {
+      FirebaseType::Number => matches!(self, Value::Integer(_) | Value::Float(_)),
+      FirebaseType::Map => matches!(self, Value::Map(_) | Value::Object(_, _)),
+      _ => self.firebase_type() == firebase_type,
+    }
+  }
+
+  /// Entries of map like values
+  pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+    match self {
+      Value::Map(entries) | Value::Object(_, entries) => Some(entries),
+      _ => None,
+    }
+  }
+
+  pub fn as_bool(&self) -> Result<bool, String> {
+    match self {
+      Value::Boolean(b) => Ok(*b),
+      _ => Err(format!("Expected a bool but got {}", self.type_name())),
+    }
+  }
+
+  pub fn as_integer(&self) -> Result<i64, String> {
+    match self {
+      Value::Integer(i) => Ok(*i),
+      _ => Err(format!("Expected an int but got {}", self.type_name())),
+    }
+  }
+
+  pub fn as_number(&self) -> Result<f64, String> {
+    match self {
+      Value::Integer(i) => Ok(*i as f64),
+      Value::Float(f) => Ok(*f),
+      _ => Err(format!("Expected a number but got {}", self.type_name())),
+    }
+  }
+
+  pub fn as_str(&self) -> Result<&str, String> {
+    match self {
+      Value::String(s) => Ok(s),
+      _ => Err(format!("Expected a string but got {}", self.type_name())),
+    }
+  }
+
+  /// Elements of lists and sets
+  pub fn as_elements(&self) -> Result<&[Value], String> {
+    match self {
+      Value::List(elements) | Value::Set(elements) => Ok(elements),
+      _ => Err(format!(
+        "Expected a list or set but got {}",
+        self.type_name()
+      )),
+    }
+  }
+
+  /// Builds a set, dropping duplicate values
+  pub fn set_of(values: impl IntoIterator<Item = Value>) -> Value {
+    let mut unique: Vec<Value> = vec![];
+
+    for value in values {
+      if !unique.contains(&value) {
+        unique.push(value);
+      }
+    }
+
+    Value::Set(unique)
+  }
+
+  pub fn compare(&self, other: &Value) -> Result<Ordering, String> {
+    let ordering = match (self, other) {
+      (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        self.as_number()?.partial_cmp(&other.as_number()?)
+      }
+      (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+      (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+      (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+      (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+      (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+      (Value::List(a), Value::List(b)) => {
+        for (el_a, el_b) in a.iter().zip(b.iter()) {
+          match el_a.compare(el_b)? {
+            Ordering::Equal => continue,
+            ordering => return Ok(ordering),
+          }
+        }
+
+        Some(a.len().cmp(&b.len()))
+      }
+      _ => None,
+    };
+
+    ordering.ok_or(format!(
+      "Can not compare {} with {}",
+      self.type_name(),
+      other.type_name()
+    ))
+  }
+}
+
+impl From<&serde_json::Value> for Value {
+  fn from(json: &serde_json::Value) -> Self {
+    match json {
+      serde_json::Value::Null => Value::Null,
+      serde_json::Value::Bool(b) => Value::Boolean(*b),
+      serde_json::Value::Number(number) => match number.as_i64() {
+        Some(i) => Value::Integer(i),
+        None => Value::Float(number.as_f64().unwrap_or(f64::NAN)),
+      },
+      serde_json::Value::String(s) => Value::String(s.clone()),
+      serde_json::Value::Array(elements) => Value::List(elements.iter().map(Value::from).collect()),
+      serde_json::Value::Object(entries) => Value::Map(
+        entries
+          .iter()
+          .map(|(key, value)| (key.clone(), Value::from(value)))
+          .collect(),
+      ),
+    }
+  }
+}
+
+impl PartialEq for Value {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Value::Null, Value::Null) => true,
+      (Value::Boolean(a), Value::Boolean(b)) => a == b,
+      (Value::Integer(a), Value::Integer(b)) => a == b,
+      (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+        self.as_number() == other.as_number()
+      }
+      (Value::String(a), Value::String(b)) => a == b,
+      (Value::Bytes(a), Value::Bytes(b)) => a == b,
+      (Value::List(a), Value::List(b)) => a == b,
+      (Value::Set(a), Value::Set(b)) => a.len() == b.len() && a.iter().all(|el| b.contains(el)),
+      (Value::Map(_) | Value::Object(_, _), Value::Map(_) | Value::Object(_, _)) => {
+        self.as_map() == other.as_map()
+      }
+      (Value::MapDiff(a_left, a_right), Value::MapDiff(b_left, b_right)) => {
+        a_left == b_left && a_right == b_right
+      }
+      (Value::Path(a), Value::Path(b)) => a == b,
+      (Value::Timestamp(a), Value::Timestamp(b)) => a == b,
+      (Value::Duration(a), Value::Duration(b)) => a == b,
+      (Value::LatLng(a_lat, a_lng), Value::LatLng(b_lat, b_lng)) => {
+        a_lat == b_lat && a_lng == b_lng
+      }
+      (Value::Module(a), Value::Module(b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Null => f.write_str("null"),
+      Value::Boolean(b) => write!(f, "{}", b),
+      Value::Integer(i) => write!(f, "{}", i),
+      Value::Float(fl) => write!(f, "{:?}", fl),
+      Value::String(s) => write!(f, "'{}'", s),
+      Value::Bytes(bytes) => write!(f, "b'{}'", to_hex(bytes)),
+      Value::List(elements) => write!(f, "[{}]", join_values(elements)),
+      Value::Set(elements) => write!(f, "set([{}])", join_values(elements)),
+      Value::Map(entries) | Value::Object(_, entries) => {
+        let entries = entries
+          .iter()
+          .map(|(key, value)| format!("'{}': {}", key, value))
+          .collect::<Vec<String>>()
+          .join(", ");
+
+        write!(f, "{{{}}}", entries)
+      }
+      Value::MapDiff(_, _) => f.write_str("map_diff"),
+      Value::Path(segments) => write!(f, "/{}", segments.join("/")),
+      Value::Timestamp(nanos) => {
+        let seconds = nanos.div_euclid(NANOS_PER_SECOND) as i64;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time = seconds.rem_euclid(86400);
+
+        write!(
+          f,
+          "timestamp({:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z)",
+          year,
+          month,
+          day,
+          time / 3600,
+          time % 3600 / 60,
+          time % 60
+        )
+      }
+      Value::Duration(nanos) => write!(f, "duration({}ns)", nanos),
+      Value::LatLng(lat, lng) => write!(f, "latlng({}, {})", lat, lng),
+      Value::Module(firebase_type) => write!(f, "{}", firebase_type.as_ref()),
+    }
+  }
+}
+
+fn join_values(values: &[Value]) -> String {
+  values
+    .iter()
+    .map(|value| value.to_string())
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Days since the unix epoch for a proleptic gregorian date
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let month_index = (month + 9) % 12;
+  let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+  era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic gregorian date (year, month, day) for the days since the unix epoch
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + 719468;
+  let era = days.div_euclid(146097);
+  let day_of_era = days - era * 146097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  (year, month, day)
+}